@@ -3,6 +3,8 @@ use std::sync::Arc;
 
 use crate::db::{ApiConfigRow, Database};
 use crate::error::VeyaError;
+use crate::llm_client::ollama::{self, OllamaOptions};
+use crate::llm_client::{GenerationParams, LlmConfig};
 use crate::stronghold_store::StrongholdStore;
 
 // ── Enums ────────────────────────────────────────────────────────
@@ -67,6 +69,48 @@ impl ModelType {
     }
 }
 
+/// LLM-backed features that can be assigned their own model.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelFeature {
+    Insight,
+    OcrCompletion,
+    PodcastScript,
+    /// Reading captured images directly; needs a model that accepts images.
+    VisionTranscription,
+}
+
+impl ModelFeature {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Insight => "insight",
+            Self::OcrCompletion => "ocr_completion",
+            Self::PodcastScript => "podcast_script",
+            Self::VisionTranscription => "vision_transcription",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, VeyaError> {
+        match s {
+            "insight" => Ok(Self::Insight),
+            "ocr_completion" => Ok(Self::OcrCompletion),
+            "podcast_script" => Ok(Self::PodcastScript),
+            "vision_transcription" => Ok(Self::VisionTranscription),
+            _ => Err(VeyaError::StorageError(format!("Unknown feature: {s}"))),
+        }
+    }
+
+    /// Model types that serve this feature when no override is set, in order
+    /// of preference. The first type with a configured chain is used.
+    fn model_types(&self) -> &'static [ModelType] {
+        match self {
+            Self::Insight | Self::PodcastScript => &[ModelType::Text],
+            Self::OcrCompletion => &[ModelType::Vision, ModelType::Text],
+            Self::VisionTranscription => &[ModelType::Vision],
+        }
+    }
+}
+
 // ── ApiConfig struct ─────────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -93,6 +137,15 @@ pub struct ApiConfig {
     pub is_active: bool,
     #[serde(default)]
     pub created_at: Option<String>,
+    /// Native Ollama options (context size, keep-alive); ignored by other providers.
+    #[serde(default)]
+    pub ollama_options: Option<OllamaOptions>,
+    /// Generation parameters (temperature, max_tokens, top_p, stop) for LLM configs.
+    #[serde(default)]
+    pub generation_params: Option<GenerationParams>,
+    /// Position in the model type's fallback chain; set via `set_fallback_chain`.
+    #[serde(default)]
+    pub fallback_rank: Option<u32>,
 }
 
 impl ApiConfig {
@@ -111,10 +164,104 @@ impl ApiConfig {
             is_local: row.is_local,
             is_active: row.is_active,
             created_at: Some(row.created_at.clone()),
+            ollama_options: row
+                .ollama_options
+                .as_deref()
+                .and_then(|json| serde_json::from_str(json).ok()),
+            generation_params: row
+                .generation_params
+                .as_deref()
+                .and_then(|json| serde_json::from_str(json).ok()),
+            fallback_rank: row.fallback_rank.map(|rank| rank as u32),
         })
     }
 }
 
+// ── Fallback chain ───────────────────────────────────────────────
+
+/// Configs of `model_type` in the order they should be tried: the active
+/// config first, then the fallback chain by rank.
+pub fn fallback_chain<'a>(rows: &'a [ApiConfigRow], model_type: &str) -> Vec<&'a ApiConfigRow> {
+    let of_type = || rows.iter().filter(|r| r.model_type == model_type);
+
+    let mut chain: Vec<&ApiConfigRow> = of_type().filter(|r| r.is_active).take(1).collect();
+    let mut ranked: Vec<&ApiConfigRow> = of_type()
+        .filter(|r| r.fallback_rank.is_some() && !r.is_active)
+        .collect();
+    ranked.sort_by_key(|r| r.fallback_rank);
+    chain.extend(ranked);
+    chain
+}
+
+/// Configs to try for `feature`: its override (if any) first, then the
+/// fallback chain of the first model type serving the feature.
+pub fn feature_chain<'a>(
+    rows: &'a [ApiConfigRow],
+    feature: ModelFeature,
+    override_id: Option<&str>,
+) -> Vec<&'a ApiConfigRow> {
+    let mut chain = feature
+        .model_types()
+        .iter()
+        .map(|model_type| fallback_chain(rows, model_type.as_str()))
+        .find(|chain| !chain.is_empty())
+        .unwrap_or_default();
+
+    if let Some(row) = override_id.and_then(|id| rows.iter().find(|r| r.id == id)) {
+        chain.retain(|r| r.id != row.id);
+        chain.insert(0, row);
+    }
+    chain
+}
+
+/// Reject an override whose model type cannot serve `feature`, e.g. a text
+/// model for vision transcription or a TTS model for anything.
+fn check_feature_override(
+    rows: &[ApiConfigRow],
+    feature: ModelFeature,
+    id: &str,
+) -> Result<(), VeyaError> {
+    let row = rows
+        .iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| VeyaError::StorageError(format!("No config with id {id}")))?;
+    let allowed = feature.model_types();
+    if allowed.iter().any(|t| t.as_str() == row.model_type) {
+        return Ok(());
+    }
+    let allowed: Vec<&str> = allowed.iter().map(|t| t.as_str()).collect();
+    Err(VeyaError::Generic(format!(
+        "Config {id} is a {} model and cannot serve {} (needs {})",
+        row.model_type,
+        feature.as_str(),
+        allowed.join(" or ")
+    )))
+}
+
+/// Resolve the configs for `feature` into LLM configs with their API keys.
+/// This is the single entry point the insight, OCR-completion and podcast
+/// resolvers use, so overrides and fallbacks behave the same everywhere.
+pub fn resolve_feature_chain(
+    db: &Database,
+    store: &StrongholdStore,
+    feature: ModelFeature,
+) -> Result<Vec<LlmConfig>, VeyaError> {
+    let rows = db.get_api_configs()?;
+    let override_id = db.get_feature_model(feature.as_str())?;
+    feature_chain(&rows, feature, override_id.as_deref())
+        .into_iter()
+        .map(|row| {
+            let config = ApiConfig::from_row(row)?;
+            let api_key = if config.is_local {
+                String::new()
+            } else {
+                store.get_api_key(&config.id)?.unwrap_or_default()
+            };
+            Ok(LlmConfig::from_api_config(config, api_key))
+        })
+        .collect()
+}
+
 // ── Tauri Commands ───────────────────────────────────────────────
 
 #[tauri::command]
@@ -153,9 +300,84 @@ pub async fn save_api_config(
         config.is_local,
     )?;
 
+    let ollama_options = config
+        .ollama_options
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| VeyaError::StorageError(format!("Failed to encode Ollama options: {e}")))?;
+    db.set_api_config_ollama_options(&config.id, ollama_options.as_deref())?;
+
+    let generation_params = config
+        .generation_params
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| VeyaError::StorageError(format!("Failed to encode generation params: {e}")))?;
+    db.set_api_config_generation_params(&config.id, generation_params.as_deref())?;
+
+    // The first config of a type becomes its active config.
+    db.ensure_active_configs()?;
+
     Ok(())
 }
 
+/// Make `id` the active config of `model_type`, deactivating the previous one.
+#[tauri::command]
+pub async fn set_active_config(
+    model_type: ModelType,
+    id: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<(), VeyaError> {
+    db.set_active_config(model_type.as_str(), &id)
+}
+
+/// A feature's model override.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureModel {
+    pub feature: ModelFeature,
+    pub api_config_id: String,
+}
+
+#[tauri::command]
+pub async fn get_feature_models(
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<Vec<FeatureModel>, VeyaError> {
+    db.get_feature_models()?
+        .into_iter()
+        .map(|(feature, api_config_id)| {
+            Ok(FeatureModel {
+                feature: ModelFeature::from_str(&feature)?,
+                api_config_id,
+            })
+        })
+        .collect()
+}
+
+/// Assign a text or vision config to a feature; `None` restores the default.
+#[tauri::command]
+pub async fn set_feature_model(
+    feature: ModelFeature,
+    api_config_id: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<(), VeyaError> {
+    if let Some(id) = &api_config_id {
+        check_feature_override(&db.get_api_configs()?, feature, id)?;
+    }
+    db.set_feature_model(feature.as_str(), api_config_id.as_deref())
+}
+
+/// Set the ordered fallback chain for a model type. Configs of that type that
+/// are not listed are removed from the chain.
+#[tauri::command]
+pub async fn set_fallback_chain(
+    model_type: ModelType,
+    config_ids: Vec<String>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<(), VeyaError> {
+    db.set_fallback_chain(model_type.as_str(), &config_ids)
+}
+
 #[tauri::command]
 pub async fn delete_api_config_cmd(
     id: String,
@@ -165,6 +387,8 @@ pub async fn delete_api_config_cmd(
     // Remove from Stronghold first (ignore errors if key doesn't exist).
     let _ = store.delete_api_key(&id);
     db.delete_api_config(&id)?;
+    // Promote another config if the deleted one was active.
+    db.ensure_active_configs()?;
     Ok(())
 }
 
@@ -174,7 +398,7 @@ pub async fn test_api_connection(config: ApiConfig) -> Result<bool, VeyaError> {
 
     // For local models (Ollama), just check if the endpoint is reachable.
     let url = if config.provider == ApiProvider::Ollama {
-        format!("{}/api/tags", config.base_url.trim_end_matches('/'))
+        ollama::api_url(&config.base_url, "tags")
     } else {
         format!("{}/models", config.base_url.trim_end_matches('/'))
     };
@@ -204,3 +428,105 @@ pub async fn test_api_connection(config: ApiConfig) -> Result<bool, VeyaError> {
         Err(e) => Err(VeyaError::NetworkTimeout(format!("Connection failed: {e}"))),
     }
 }
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: &str, model_type: &str, is_active: bool, fallback_rank: Option<i64>) -> ApiConfigRow {
+        ApiConfigRow {
+            id: id.into(),
+            name: id.into(),
+            provider: "openai".into(),
+            model_type: model_type.into(),
+            base_url: "https://example.com".into(),
+            model_name: id.into(),
+            api_key_ref: format!("api_key_{id}"),
+            language: None,
+            is_local: false,
+            is_active,
+            created_at: String::new(),
+            ollama_options: None,
+            generation_params: None,
+            fallback_rank,
+        }
+    }
+
+    #[test]
+    fn fallback_chain_starts_with_active_then_ranks() {
+        let rows = vec![
+            row("ollama", "text", false, Some(1)),
+            row("unranked", "text", false, None),
+            row("openai", "text", true, Some(0)),
+            row("backup", "text", false, Some(0)),
+            row("vision", "vision", true, Some(0)),
+        ];
+        let ids: Vec<&str> = fallback_chain(&rows, "text").iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["openai", "backup", "ollama"]);
+    }
+
+    #[test]
+    fn fallback_chain_without_active_config_uses_ranks() {
+        let rows = vec![row("b", "text", false, Some(1)), row("a", "text", false, Some(0))];
+        let ids: Vec<&str> = fallback_chain(&rows, "text").iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert!(fallback_chain(&rows, "tts").is_empty());
+    }
+
+    #[test]
+    fn feature_chain_puts_override_first() {
+        let rows = vec![
+            row("openai", "text", true, None),
+            row("ollama", "text", false, Some(0)),
+            row("local-vision", "vision", true, None),
+        ];
+        let ids = |chain: Vec<&ApiConfigRow>| -> Vec<String> { chain.iter().map(|r| r.id.clone()).collect() };
+
+        assert_eq!(ids(feature_chain(&rows, ModelFeature::PodcastScript, None)), vec!["openai", "ollama"]);
+        assert_eq!(
+            ids(feature_chain(&rows, ModelFeature::PodcastScript, Some("ollama"))),
+            vec!["ollama", "openai"]
+        );
+        assert_eq!(ids(feature_chain(&rows, ModelFeature::OcrCompletion, None)), vec!["local-vision"]);
+        // A dangling override is ignored.
+        assert_eq!(ids(feature_chain(&rows, ModelFeature::Insight, Some("gone"))), vec!["openai", "ollama"]);
+    }
+
+    #[test]
+    fn vision_transcription_never_uses_text_models() {
+        let rows = vec![row("openai", "text", true, None)];
+        assert!(feature_chain(&rows, ModelFeature::VisionTranscription, None).is_empty());
+
+        let rows = vec![row("openai", "text", true, None), row("local-vision", "vision", true, None)];
+        let chain = feature_chain(&rows, ModelFeature::VisionTranscription, None);
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain[0].id, "local-vision");
+    }
+
+    #[test]
+    fn feature_override_must_match_model_types() {
+        let rows = vec![
+            row("openai", "text", true, None),
+            row("local-vision", "vision", true, None),
+            row("voice", "tts", true, None),
+        ];
+        assert!(check_feature_override(&rows, ModelFeature::Insight, "openai").is_ok());
+        assert!(check_feature_override(&rows, ModelFeature::OcrCompletion, "openai").is_ok());
+        assert!(check_feature_override(&rows, ModelFeature::VisionTranscription, "local-vision").is_ok());
+
+        assert!(check_feature_override(&rows, ModelFeature::VisionTranscription, "openai").is_err());
+        assert!(check_feature_override(&rows, ModelFeature::PodcastScript, "local-vision").is_err());
+        assert!(check_feature_override(&rows, ModelFeature::Insight, "voice").is_err());
+        assert!(check_feature_override(&rows, ModelFeature::Insight, "gone").is_err());
+    }
+
+    #[test]
+    fn ocr_completion_falls_back_to_text_chain() {
+        let rows = vec![row("openai", "text", true, None)];
+        let chain = feature_chain(&rows, ModelFeature::OcrCompletion, None);
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain[0].id, "openai");
+    }
+}
@@ -4,14 +4,18 @@ use std::sync::Arc;
 use tauri::{AppHandle, Emitter, Manager};
 use uuid::Uuid;
 
-use crate::api_config::{ApiConfig, ApiProvider, ModelType};
+use crate::api_config::{resolve_feature_chain, ApiConfig, ApiProvider, ModelFeature};
 use crate::db::Database;
 use crate::error::VeyaError;
-use crate::llm_client::{LlmClient, LlmConfig, Message};
+use crate::language_detect::{detect, split_language_spans, DetectionHints};
+use crate::learner_profile::{describe_language, primary_subtag, LearnerProfile};
+use crate::llm_client::{GenerationParams, LlmClient, Message};
+use crate::prompt_template::{PromptId, PromptTemplate, PromptVars};
 use crate::retry::RetryPolicy;
 use crate::settings::AppSettings;
 use crate::stronghold_store::StrongholdStore;
 use crate::tts_client::{TtsClient, TtsConfig, TtsOptions};
+use crate::usage::UsageRecorder;
 
 // ── Types ────────────────────────────────────────────────────────
 
@@ -76,6 +80,9 @@ impl PodcastMode {
 pub struct PodcastInput {
     pub content: String,
     pub source: PodcastSource,
+    /// Language of `content` as chosen by the user; detected when absent.
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,10 +109,13 @@ pub struct CastEngineProgress {
 
 const EVENT_PROGRESS: &str = "veya://cast-engine/progress";
 
+/// Podcast scripts benefit from more varied, natural phrasing.
+const SCRIPT_TEMPERATURE: f32 = 0.8;
+
 // ── Helper: build prompt for script generation ───────────────────
 
-fn build_script_prompt(input: &PodcastInput, options: &PodcastOptions) -> Vec<Message> {
-    let mode_instruction = match options.mode {
+fn mode_instruction(mode: &PodcastMode) -> &'static str {
+    match mode {
         PodcastMode::Bilingual => {
             "Generate a bilingual podcast script. Alternate between the original language and the target language. \
              For each key phrase or sentence, first present it in the original language, then explain it in the target language."
@@ -114,34 +124,95 @@ fn build_script_prompt(input: &PodcastInput, options: &PodcastOptions) -> Vec<Me
             "Generate an immersive podcast script entirely in the target language. \
              Explain the content naturally as if teaching a language learner, using only the target language."
         }
-    };
+    }
+}
 
-    let speed_instruction = match options.speed {
+fn speed_instruction(speed: &SpeedMode) -> &'static str {
+    match speed {
         SpeedMode::Slow => "Use short, simple sentences. Pause between ideas. Speak slowly and clearly.",
         SpeedMode::Normal => "Use natural conversational pace and sentence length.",
+    }
+}
+
+/// Add the podcast-only template variables to `vars`.
+fn podcast_vars(vars: PromptVars, options: &PodcastOptions) -> PromptVars {
+    vars.set("target_language", describe_language(&options.target_language))
+        .set("mode_instruction", mode_instruction(&options.mode))
+        .set("speed_instruction", speed_instruction(&options.speed))
+}
+
+/// Podcast variables for template previews: a bilingual episode at normal speed.
+pub(crate) fn sample_podcast_vars(vars: PromptVars, target_language: &str) -> PromptVars {
+    let options = PodcastOptions {
+        speed: SpeedMode::Normal,
+        mode: PodcastMode::Bilingual,
+        target_language: target_language.into(),
     };
+    podcast_vars(vars, &options)
+}
 
-    let system = format!(
-        "You are a language learning podcast host. Your job is to transform the given content into \
-         an engaging spoken explanation that helps learners understand the material.\n\n\
-         Target language: {}\n\
-         {}\n\
-         {}\n\n\
-         Output ONLY the podcast script text, ready to be read aloud. \
-         Use paragraph breaks to separate segments. Do not include stage directions or metadata.",
-        options.target_language, mode_instruction, speed_instruction
-    );
+/// Language of the podcast's source material: the user's choice, else
+/// detected with the learner's languages as a prior.
+fn source_language(input: &PodcastInput, profile: &LearnerProfile) -> String {
+    let hints = DetectionHints::for_learner(profile).with_override(input.language.clone());
+    detect(&input.content, &hints).language
+}
 
-    vec![
-        Message {
-            role: "system".into(),
-            content: system,
-        },
-        Message {
-            role: "user".into(),
-            content: input.content.clone(),
-        },
-    ]
+fn build_script_prompt(
+    template: &PromptTemplate,
+    input: &PodcastInput,
+    source_language: &str,
+    options: &PodcastOptions,
+    profile: &LearnerProfile,
+) -> Vec<Message> {
+    let vars = PromptVars::for_learner(&input.content, source_language, profile);
+    template.render(&podcast_vars(vars, options))
+}
+
+/// Split a script segment into the pieces to synthesize, each with the
+/// language it is spoken in. Immersive scripts are entirely in the target
+/// language. Bilingual scripts quote the source material and carry terms in
+/// other scripts, so each language span is routed to its own TTS service;
+/// adjacent spans that `find_config` sends to the same service stay
+/// together, and spans without a service of their own use the target voice.
+pub fn tts_pieces(
+    tts: &TtsClient,
+    segment: &str,
+    mode: &PodcastMode,
+    source_language: &str,
+    target_language: &str,
+) -> Vec<(String, String)> {
+    if matches!(mode, PodcastMode::Immersive) {
+        return vec![(segment.to_string(), target_language.to_string())];
+    }
+    let hints = DetectionHints {
+        priors: vec![target_language.to_string(), source_language.to_string()],
+        override_language: None,
+    };
+
+    let mut pieces: Vec<(String, String, Option<String>)> = Vec::new();
+    for span in split_language_spans(segment, &hints) {
+        let routed = tts
+            .find_config(&span.language)
+            .ok()
+            .filter(|c| primary_subtag(&c.language) == primary_subtag(&span.language));
+        let (language, config_id) = match routed {
+            Some(config) => (span.language, Some(config.config_id.clone())),
+            None => (
+                target_language.to_string(),
+                tts.find_config(target_language).ok().map(|c| c.config_id.clone()),
+            ),
+        };
+        match pieces.last_mut() {
+            Some((text, _, last_config)) if *last_config == config_id => text.push_str(&span.text),
+            _ => pieces.push((span.text, language, config_id)),
+        }
+    }
+    pieces
+        .into_iter()
+        .filter(|(text, _, _)| !text.trim().is_empty())
+        .map(|(text, language, _)| (text, language))
+        .collect()
 }
 
 /// Split a script into segments for TTS synthesis.
@@ -178,27 +249,11 @@ fn resolve_llm_client(
     store: &StrongholdStore,
     retry_count: u32,
 ) -> Result<LlmClient, VeyaError> {
-    let rows = db.get_api_configs()?;
-    let text_row = rows
-        .iter()
-        .find(|r| r.model_type == "text")
-        .ok_or_else(|| VeyaError::ModelUnavailable("No text model configured".into()))?;
-
-    let config = ApiConfig::from_row(text_row)?;
-    let api_key = store
-        .get_api_key(&config.id)
-        .unwrap_or_default()
-        .unwrap_or_default();
-
-    let llm_config = LlmConfig {
-        provider: config.provider,
-        base_url: config.base_url,
-        model_name: config.model_name,
-        api_key,
-    };
+    let chain = resolve_feature_chain(db, store, ModelFeature::PodcastScript)?;
 
     let retry = RetryPolicy::new(retry_count, 500, 30_000);
-    Ok(LlmClient::new(llm_config, retry))
+    LlmClient::from_chain(chain, retry)
+        .ok_or_else(|| VeyaError::ModelUnavailable("No text model configured".into()))
 }
 
 fn resolve_tts_client(
@@ -222,6 +277,7 @@ fn resolve_tts_client(
             .unwrap_or_default();
 
         configs.push(TtsConfig {
+            config_id: config.id,
             provider: config.provider,
             base_url: config.base_url,
             model_name: config.model_name,
@@ -287,8 +343,13 @@ pub async fn generate_podcast(
     );
 
     // ── 2. Generate script via LLM ───────────────────────────────
-    let llm = resolve_llm_client(&db, &store, settings.retry_count)?;
-    let messages = build_script_prompt(&input, &options);
+    let llm = resolve_llm_client(&db, &store, settings.retry_count)?
+        .with_task_defaults(GenerationParams::with_temperature(SCRIPT_TEMPERATURE))
+        .with_usage_recorder(UsageRecorder::new(db.inner().clone()));
+    let template = PromptTemplate::load(&db, PromptId::PodcastScript)?;
+    let profile = LearnerProfile::load(&db)?;
+    let source_lang = source_language(&input, &profile);
+    let messages = build_script_prompt(&template, &input, &source_lang, &options, &profile);
     let script = llm.chat(messages).await?;
 
     // ── 3. Emit: script_done ─────────────────────────────────────
@@ -313,7 +374,8 @@ pub async fn generate_podcast(
     let total_segments = segments.len() as u32;
 
     // ── 5. TTS synthesis per segment ─────────────────────────────
-    let tts = resolve_tts_client(&db, &store, settings.retry_count)?;
+    let tts = resolve_tts_client(&db, &store, settings.retry_count)?
+        .with_usage_recorder(UsageRecorder::new(db.inner().clone()));
     let tts_options = TtsOptions {
         voice: None,
         speed: Some(options.speed.tts_speed()),
@@ -321,10 +383,10 @@ pub async fn generate_podcast(
 
     let mut all_audio: Vec<u8> = Vec::new();
     for (i, segment) in segments.iter().enumerate() {
-        let audio_bytes = tts
-            .synthesize(segment, &options.target_language, &tts_options)
-            .await?;
-        all_audio.extend_from_slice(&audio_bytes);
+        for (text, language) in tts_pieces(&tts, segment, &options.mode, &source_lang, &options.target_language) {
+            let audio_bytes = tts.synthesize(&text, &language, &tts_options).await?;
+            all_audio.extend_from_slice(&audio_bytes);
+        }
 
         let pct = 30 + ((i as u32 + 1) * 60 / total_segments.max(1));
         let _ = app.emit(
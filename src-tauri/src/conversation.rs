@@ -0,0 +1,499 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+use crate::db::{ConversationMessageRow, ConversationThreadRow, Database, QueryRow};
+use crate::error::VeyaError;
+use crate::llm_client::{LlmConfig, Message};
+use crate::settings::AppSettings;
+use crate::stronghold_store::StrongholdStore;
+use crate::text_insight::resolve_text_llm_client;
+use crate::usage::UsageRecorder;
+
+// ── Event types ──────────────────────────────────────────────────
+
+const EVENT_STREAM_CHUNK: &str = "veya://conversation/stream-chunk";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationChunk {
+    #[serde(rename = "type")]
+    pub chunk_type: String, // "start" | "delta" | "retrying" | "fallback" | "done" | "error"
+    pub thread_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempt: Option<u32>,
+    /// Model that took over (`fallback`) or answered (`done`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+// ── Thread types ─────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMessage {
+    pub role: String, // "user" | "assistant"
+    pub content: String,
+    pub created_at: String,
+}
+
+impl From<ConversationMessageRow> for ConversationMessage {
+    fn from(row: ConversationMessageRow) -> Self {
+        Self {
+            role: row.role,
+            content: row.content,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// A follow-up conversation on a saved text insight result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationThread {
+    pub id: String,
+    pub query_record_id: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub messages: Vec<ConversationMessage>,
+}
+
+// ── Context window ───────────────────────────────────────────────
+
+/// Context window assumed when the API config does not set one (`num_ctx`).
+/// Deliberately conservative: it fits every model we support, and hosted
+/// models with larger windows just get shorter thread history.
+const DEFAULT_CONTEXT_TOKENS: usize = 8192;
+/// Tokens reserved for the reply when `max_tokens` is not configured.
+const DEFAULT_REPLY_TOKENS: usize = 1024;
+/// Per-message overhead of the chat format (role markers, separators).
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// Appended to an analysis cut short to fit the context window.
+const TRUNCATION_MARKER: &str = "\n[…]";
+
+/// Rough token estimate: about four characters per token for ASCII text and
+/// one token per character otherwise (CJK text tokenizes far more densely).
+pub fn estimate_tokens(text: &str) -> usize {
+    let ascii = text.chars().filter(|c| c.is_ascii()).count();
+    let other = text.chars().count() - ascii;
+    ascii.div_ceil(4) + other
+}
+
+fn message_tokens(message: &Message) -> usize {
+    estimate_tokens(&message.content) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Longest prefix of `text` whose estimate fits in `max_tokens`.
+fn truncate_to_tokens(text: &str, max_tokens: usize) -> &str {
+    let (mut ascii, mut other) = (0usize, 0usize);
+    for (i, c) in text.char_indices() {
+        if c.is_ascii() {
+            ascii += 1;
+        } else {
+            other += 1;
+        }
+        if ascii.div_ceil(4) + other > max_tokens {
+            return &text[..i];
+        }
+    }
+    text
+}
+
+/// Prompt budget for a thread: the model's context window minus the reply.
+///
+/// Only Ollama configs declare a window (`num_ctx`); every other model is
+/// capped at `DEFAULT_CONTEXT_TOKENS` rather than guessing its real size.
+pub fn context_budget(config: &LlmConfig) -> usize {
+    let window = config
+        .ollama
+        .num_ctx
+        .map_or(DEFAULT_CONTEXT_TOKENS, |n| n as usize);
+    let reply = config
+        .generation
+        .max_tokens
+        .map_or(DEFAULT_REPLY_TOKENS, |n| n as usize);
+    window.saturating_sub(reply)
+}
+
+// ── Prompt ───────────────────────────────────────────────────────
+
+const FOLLOW_UP_SYSTEM_PROMPT: &str = "You are a language tutor. The user has read the analysis below of a text \
+they are studying and is asking follow-up questions about it. Answer concisely, \
+referring to the original text where helpful.";
+
+/// Build the messages for a follow-up question.
+///
+/// The system message always carries the original text and its analysis, and
+/// the new question is always sent. Older turns are dropped, oldest first,
+/// once the estimate exceeds `budget_tokens`; if the system message and the
+/// question alone exceed it, the analysis is cut short. Fails when even the
+/// original text and the question do not fit.
+pub fn build_thread_messages(
+    record: &QueryRow,
+    history: &[ConversationMessageRow],
+    question: &str,
+    budget_tokens: usize,
+) -> Result<Vec<Message>, VeyaError> {
+    let question = Message {
+        role: "user".into(),
+        content: question.to_string(),
+        images: Vec::new(),
+    };
+    let header = format!("{FOLLOW_UP_SYSTEM_PROMPT}\n\nOriginal text:\n{}\n\nAnalysis:\n", record.input_text);
+    let analysis_room = budget_tokens
+        .checked_sub(estimate_tokens(&header) + MESSAGE_OVERHEAD_TOKENS + message_tokens(&question))
+        .ok_or_else(|| {
+            VeyaError::Generic("The text and question are too long for the model's context window".into())
+        })?;
+    let analysis = if estimate_tokens(&record.analysis_result) <= analysis_room {
+        record.analysis_result.clone()
+    } else {
+        let room = analysis_room.saturating_sub(estimate_tokens(TRUNCATION_MARKER));
+        format!("{}{TRUNCATION_MARKER}", truncate_to_tokens(&record.analysis_result, room))
+    };
+    let system = Message {
+        role: "system".into(),
+        content: header + &analysis,
+        images: Vec::new(),
+    };
+
+    let mut used = message_tokens(&system) + message_tokens(&question);
+    let mut kept: Vec<Message> = Vec::new();
+    for row in history.iter().rev() {
+        let message = Message {
+            role: row.role.clone(),
+            content: row.content.clone(),
+            images: Vec::new(),
+        };
+        let cost = message_tokens(&message);
+        if used + cost > budget_tokens {
+            break;
+        }
+        used += cost;
+        kept.push(message);
+    }
+    kept.reverse();
+
+    // An answer without its question is confusing context; start on a user turn.
+    let first_user = kept.iter().position(|m| m.role == "user").unwrap_or(kept.len());
+    kept.drain(..first_user);
+
+    let mut messages = Vec::with_capacity(kept.len() + 2);
+    messages.push(system);
+    messages.extend(kept);
+    messages.push(question);
+    Ok(messages)
+}
+
+// ── Core logic (testable without Tauri) ──────────────────────────
+
+fn thread_from_row(db: &Database, row: ConversationThreadRow) -> Result<ConversationThread, VeyaError> {
+    let messages = db
+        .get_conversation_messages(&row.id)?
+        .into_iter()
+        .map(ConversationMessage::from)
+        .collect();
+    Ok(ConversationThread {
+        id: row.id,
+        query_record_id: row.query_record_id,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+        messages,
+    })
+}
+
+pub fn list_threads_for(db: &Database, query_record_id: &str) -> Result<Vec<ConversationThread>, VeyaError> {
+    db.get_conversation_threads(query_record_id)?
+        .into_iter()
+        .map(|row| thread_from_row(db, row))
+        .collect()
+}
+
+/// Resolve the thread to continue, or pick the ID of a new one when
+/// `thread_id` is None. A new thread is only stored together with its first
+/// exchange, so a failed reply never leaves an empty thread behind.
+fn open_thread(db: &Database, query_record_id: &str, thread_id: Option<String>) -> Result<String, VeyaError> {
+    match thread_id {
+        Some(id) => {
+            let thread = db
+                .get_conversation_thread(&id)?
+                .ok_or_else(|| VeyaError::StorageError(format!("Conversation thread {id} not found")))?;
+            if thread.query_record_id != query_record_id {
+                return Err(VeyaError::Generic(format!(
+                    "Conversation thread {id} does not belong to query record {query_record_id}"
+                )));
+            }
+            Ok(id)
+        }
+        None => Ok(Uuid::new_v4().to_string()),
+    }
+}
+
+// ── Follow-up flow ───────────────────────────────────────────────
+
+/// Store a finished exchange, creating the thread if this is its first one.
+/// Empty replies are rejected so a thread never holds a question without an
+/// answer.
+fn save_exchange(
+    db: &Database,
+    thread_id: &str,
+    query_record_id: &str,
+    question: &str,
+    reply: &str,
+) -> Result<(), VeyaError> {
+    if reply.trim().is_empty() {
+        return Err(VeyaError::ModelUnavailable("The model returned an empty reply".into()));
+    }
+    db.insert_conversation_exchange(thread_id, query_record_id, question, reply)
+}
+
+fn emit_chunk(app: &AppHandle, thread_id: &str, chunk_type: &str, content: Option<String>, model: Option<String>) {
+    let _ = app.emit(
+        EVENT_STREAM_CHUNK,
+        ConversationChunk {
+            chunk_type: chunk_type.into(),
+            thread_id: thread_id.into(),
+            content,
+            attempt: None,
+            model,
+        },
+    );
+}
+
+/// Stream the reply to `question` and store the exchange once complete.
+///
+/// `done` is emitted only after the exchange is saved; failures are left to
+/// the caller to report.
+async fn run_follow_up(
+    app: &AppHandle,
+    thread_id: &str,
+    record: &QueryRow,
+    history: &[ConversationMessageRow],
+    question: &str,
+) -> Result<(), VeyaError> {
+    let db = app.state::<Arc<Database>>();
+    let store = app.state::<Arc<StrongholdStore>>();
+
+    let client = AppSettings::load(&db)
+        .and_then(|settings| resolve_text_llm_client(&db, &store, &settings))?
+        .with_usage_recorder(UsageRecorder::new(db.inner().clone()));
+
+    // Budget for the primary model; fallbacks are usually no smaller.
+    let budget = context_budget(client.primary_config());
+    let messages = build_thread_messages(record, history, question, budget)?;
+
+    let mut reply = String::new();
+    let mut model = None;
+    client
+        .stream_chat(messages, |chunk| {
+            match chunk.chunk_type.as_str() {
+                "delta" => reply.push_str(chunk.content.as_deref().unwrap_or_default()),
+                // The stream restarts from scratch after a retry or fallback.
+                "retrying" | "fallback" => reply.clear(),
+                "done" => {
+                    model = chunk.model;
+                    return;
+                }
+                "error" => return,
+                _ => {}
+            }
+            let _ = app.emit(
+                EVENT_STREAM_CHUNK,
+                ConversationChunk {
+                    chunk_type: chunk.chunk_type,
+                    thread_id: thread_id.into(),
+                    content: chunk.content,
+                    attempt: chunk.attempt,
+                    model: chunk.model,
+                },
+            );
+        })
+        .await?;
+
+    save_exchange(&db, thread_id, &record.id, question, &reply)?;
+    emit_chunk(app, thread_id, "done", None, model);
+    Ok(())
+}
+
+// ── Tauri Commands ───────────────────────────────────────────────
+
+/// Ask a follow-up question about a saved text insight result.
+///
+/// Continues `thread_id`, or starts a new thread on the query record when it
+/// is None. Returns the thread ID immediately; the reply streams via events
+/// tagged with that ID, and the question and reply are saved to the thread
+/// together once it completes. Failures end the stream with an `error` chunk.
+#[tauri::command]
+pub async fn continue_thread(
+    query_record_id: String,
+    thread_id: Option<String>,
+    question: String,
+    app: AppHandle,
+) -> Result<String, VeyaError> {
+    if question.trim().is_empty() {
+        return Err(VeyaError::Generic("Empty question provided".into()));
+    }
+
+    let db = app.state::<Arc<Database>>();
+    let record = db
+        .get_query_record(&query_record_id)?
+        .ok_or_else(|| VeyaError::StorageError(format!("Query record {query_record_id} not found")))?;
+
+    let thread_id = open_thread(&db, &record.id, thread_id)?;
+    let history = db.get_conversation_messages(&thread_id)?;
+
+    let app = app.clone();
+    let id = thread_id.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = run_follow_up(&app, &id, &record, &history, &question).await {
+            log::warn!("Follow-up in thread {id} failed: {e}");
+            emit_chunk(&app, &id, "error", Some(e.to_string()), None);
+        }
+    });
+
+    Ok(thread_id)
+}
+
+#[tauri::command]
+pub async fn list_threads(
+    query_record_id: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<Vec<ConversationThread>, VeyaError> {
+    list_threads_for(&db, &query_record_id)
+}
+
+#[tauri::command]
+pub async fn delete_thread(
+    thread_id: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<(), VeyaError> {
+    db.delete_conversation_thread(&thread_id)
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_db() -> (Database, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let db = Database::open(dir.path().to_path_buf()).unwrap();
+        (db, dir)
+    }
+
+    fn record() -> QueryRow {
+        QueryRow {
+            id: "q1".into(),
+            input_text: "Ojalá que llueva".into(),
+            source: "text_insight".into(),
+            detected_language: Some("es".into()),
+            analysis_result: "[TRANSLATION] I hope it rains".into(),
+            created_at: String::new(),
+        }
+    }
+
+    fn turn(role: &str, content: &str) -> ConversationMessageRow {
+        ConversationMessageRow {
+            role: role.into(),
+            content: content.into(),
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn estimate_tokens_counts_cjk_densely() {
+        assert_eq!(estimate_tokens("abcdefgh"), 2);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("你好"), 2);
+    }
+
+    #[test]
+    fn thread_messages_include_text_analysis_and_question() {
+        let history = vec![turn("user", "What tense?"), turn("assistant", "Present subjunctive.")];
+        let messages = build_thread_messages(&record(), &history, "Why subjunctive?", usize::MAX).unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].role, "system");
+        assert!(messages[0].content.contains("Ojalá que llueva"));
+        assert!(messages[0].content.contains("I hope it rains"));
+        assert_eq!(messages[3].content, "Why subjunctive?");
+    }
+
+    #[test]
+    fn thread_messages_drop_oldest_turns_over_budget() {
+        let long = "x".repeat(400); // ~100 tokens each
+        let history = vec![
+            turn("user", &long),
+            turn("assistant", &long),
+            turn("user", &long),
+            turn("assistant", &long),
+        ];
+        let base = build_thread_messages(&record(), &[], "q", usize::MAX).unwrap();
+        let base_tokens: usize = base.iter().map(message_tokens).sum();
+
+        // Room for roughly three of the four turns.
+        let messages = build_thread_messages(&record(), &history, "q", base_tokens + 320).unwrap();
+        // The leading assistant turn is dropped too, so the context starts on a user turn.
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[1].role, "user");
+        assert_eq!(messages[2].role, "assistant");
+        assert_eq!(messages[3].content, "q");
+    }
+
+    #[test]
+    fn thread_messages_trim_oversized_analysis() {
+        let mut oversized = record();
+        oversized.analysis_result = format!("[TRANSLATION] I hope it rains\n{}", "解释".repeat(5000));
+
+        let history = vec![turn("user", "a"), turn("assistant", "b")];
+        let messages = build_thread_messages(&oversized, &history, "q", 1000).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].content.contains("Ojalá que llueva"));
+        assert!(messages[0].content.contains("I hope it rains"));
+        assert!(messages[0].content.ends_with(TRUNCATION_MARKER));
+        assert!(messages.iter().map(message_tokens).sum::<usize>() <= 1000);
+
+        // Not even the original text fits: fail instead of sending an over-long prompt.
+        assert!(build_thread_messages(&oversized, &[], "q", 20).is_err());
+    }
+
+    #[test]
+    fn open_thread_creates_and_validates_threads() {
+        let (db, _dir) = test_db();
+        db.insert_query_record("q1", "hola", "text_insight", Some("es"), "{}").unwrap();
+        db.insert_query_record("q2", "adiós", "text_insight", Some("es"), "{}").unwrap();
+
+        // A new thread is not stored until its first exchange is saved.
+        let id = open_thread(&db, "q1", None).unwrap();
+        assert!(list_threads_for(&db, "q1").unwrap().is_empty());
+        assert!(open_thread(&db, "q1", Some(id.clone())).is_err());
+
+        save_exchange(&db, &id, "q1", "why?", "because").unwrap();
+        assert_eq!(open_thread(&db, "q1", Some(id.clone())).unwrap(), id);
+        assert!(open_thread(&db, "q2", Some(id.clone())).is_err());
+        assert!(open_thread(&db, "q1", Some("missing".into())).is_err());
+
+        let threads = list_threads_for(&db, "q1").unwrap();
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].messages.len(), 2);
+        assert!(list_threads_for(&db, "q2").unwrap().is_empty());
+    }
+
+    #[test]
+    fn save_exchange_stores_both_turns_or_nothing() {
+        let (db, _dir) = test_db();
+        db.insert_query_record("q1", "hola", "text_insight", Some("es"), "{}").unwrap();
+        let id = open_thread(&db, "q1", None).unwrap();
+
+        assert!(save_exchange(&db, &id, "q1", "why?", "  ").is_err());
+        assert!(db.get_conversation_messages(&id).unwrap().is_empty());
+        assert!(db.get_conversation_thread(&id).unwrap().is_none());
+
+        save_exchange(&db, &id, "q1", "why?", "because").unwrap();
+        let messages = db.get_conversation_messages(&id).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!((messages[0].role.as_str(), messages[0].content.as_str()), ("user", "why?"));
+        assert_eq!((messages[1].role.as_str(), messages[1].content.as_str()), ("assistant", "because"));
+    }
+}
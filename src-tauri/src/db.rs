@@ -41,6 +41,29 @@ impl Database {
             VeyaError::StorageError(format!("Migration failed: {e}"))
         })?;
 
+        // Incremental migrations, tracked via `PRAGMA user_version`.
+        // MIGRATIONS[i] upgrades the schema to version i + 2.
+        let version: usize = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| VeyaError::StorageError(format!("Failed to read schema version: {e}")))?;
+
+        for (i, sql) in MIGRATIONS.iter().enumerate().skip(version.saturating_sub(1)) {
+            let target = i + 2;
+            conn.execute_batch(&format!(
+                "BEGIN;\n{sql}\nPRAGMA user_version = {target};\nCOMMIT;"
+            ))
+            .map_err(|e| {
+                VeyaError::StorageError(format!("Migration to v{target} failed: {e}"))
+            })?;
+        }
+
+        // Databases from before v7 may have no active config at all, and
+        // only save/delete used to repair that; without one the fallback
+        // chain would be empty.
+        conn.execute_batch(ENSURE_ACTIVE_CONFIGS).map_err(|e| {
+            VeyaError::StorageError(format!("Failed to activate configs: {e}"))
+        })?;
+
         Ok(())
     }
 
@@ -122,6 +145,26 @@ impl Database {
         })
     }
 
+    pub fn get_query_record(&self, id: &str) -> Result<Option<QueryRow>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, input_text, source, detected_language, analysis_result, created_at
+                 FROM query_records WHERE id = ?1",
+            )?;
+            let mut rows = stmt.query_map(params![id], |row| {
+                Ok(QueryRow {
+                    id: row.get(0)?,
+                    input_text: row.get(1)?,
+                    source: row.get(2)?,
+                    detected_language: row.get(3)?,
+                    analysis_result: row.get(4)?,
+                    created_at: row.get(5)?,
+                })
+            })?;
+            rows.next().transpose()
+        })
+    }
+
     // ── Podcast record helpers ───────────────────────────────────────
 
     pub fn insert_podcast_record(
@@ -220,7 +263,8 @@ impl Database {
                  ON CONFLICT(id) DO UPDATE SET
                    name=excluded.name, provider=excluded.provider, model_type=excluded.model_type,
                    base_url=excluded.base_url, model_name=excluded.model_name, api_key_ref=excluded.api_key_ref,
-                   language=excluded.language, is_local=excluded.is_local",
+                   language=excluded.language, is_local=excluded.is_local,
+                   is_active=CASE WHEN api_configs.model_type = excluded.model_type THEN api_configs.is_active ELSE 0 END",
                 params![id, name, provider, model_type, base_url, model_name, api_key_ref, language, is_local as i32],
             )?;
             Ok(())
@@ -230,7 +274,8 @@ impl Database {
     pub fn get_api_configs(&self) -> Result<Vec<ApiConfigRow>, VeyaError> {
         self.with_conn(|conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, name, provider, model_type, base_url, model_name, api_key_ref, language, is_local, is_active, created_at
+                "SELECT id, name, provider, model_type, base_url, model_name, api_key_ref, language, is_local, is_active, created_at,
+                        ollama_options, generation_params, fallback_rank
                  FROM api_configs ORDER BY created_at ASC",
             )?;
             let rows = stmt.query_map([], |row| {
@@ -246,18 +291,506 @@ impl Database {
                     is_local: row.get::<_, i32>(8)? != 0,
                     is_active: row.get::<_, i32>(9)? != 0,
                     created_at: row.get(10)?,
+                    ollama_options: row.get(11)?,
+                    generation_params: row.get(12)?,
+                    fallback_rank: row.get(13)?,
                 })
             })?;
             rows.collect::<Result<Vec<_>, _>>()
         })
     }
 
+    /// Store Ollama-specific options (JSON) for an API config.
+    pub fn set_api_config_ollama_options(
+        &self,
+        id: &str,
+        ollama_options: Option<&str>,
+    ) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "UPDATE api_configs SET ollama_options = ?2 WHERE id = ?1",
+                params![id, ollama_options],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Store generation parameters (JSON) for an API config.
+    pub fn set_api_config_generation_params(
+        &self,
+        id: &str,
+        generation_params: Option<&str>,
+    ) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "UPDATE api_configs SET generation_params = ?2 WHERE id = ?1",
+                params![id, generation_params],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Replace the fallback chain of `model_type` with `ids`, in order.
+    /// Configs of that type not listed are removed from the chain.
+    pub fn set_fallback_chain(&self, model_type: &str, ids: &[String]) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "UPDATE api_configs SET fallback_rank = NULL WHERE model_type = ?1",
+                params![model_type],
+            )?;
+            for (rank, id) in ids.iter().enumerate() {
+                tx.execute(
+                    "UPDATE api_configs SET fallback_rank = ?3 WHERE id = ?1 AND model_type = ?2",
+                    params![id, model_type, rank as i64],
+                )?;
+            }
+            tx.commit()
+        })
+    }
+
     pub fn delete_api_config(&self, id: &str) -> Result<(), VeyaError> {
         self.with_conn(|conn| {
+            conn.execute("DELETE FROM feature_models WHERE api_config_id = ?1", params![id])?;
             conn.execute("DELETE FROM api_configs WHERE id = ?1", params![id])?;
             Ok(())
         })
     }
+
+    /// Make `id` the only active config of `model_type`.
+    pub fn set_active_config(&self, model_type: &str, id: &str) -> Result<(), VeyaError> {
+        let activated = self.with_conn(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "UPDATE api_configs SET is_active = 0 WHERE model_type = ?1 AND is_active = 1",
+                params![model_type],
+            )?;
+            let changed = tx.execute(
+                "UPDATE api_configs SET is_active = 1 WHERE id = ?1 AND model_type = ?2",
+                params![id, model_type],
+            )?;
+            if changed == 0 {
+                // Dropping the transaction rolls back the deactivation.
+                return Ok(false);
+            }
+            tx.commit()?;
+            Ok(true)
+        })?;
+
+        if activated {
+            Ok(())
+        } else {
+            Err(VeyaError::StorageError(format!("No {model_type} config with id {id}")))
+        }
+    }
+
+    /// Activate the oldest config of every model type that has no active config.
+    pub fn ensure_active_configs(&self) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            conn.execute_batch(ENSURE_ACTIVE_CONFIGS)?;
+            Ok(())
+        })
+    }
+
+    // ── Feature model helpers ────────────────────────────────────────
+
+    pub fn get_feature_model(&self, feature: &str) -> Result<Option<String>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare("SELECT api_config_id FROM feature_models WHERE feature = ?1")?;
+            let mut rows = stmt.query(params![feature])?;
+            match rows.next()? {
+                Some(row) => Ok(Some(row.get(0)?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    /// All feature overrides as `(feature, api_config_id)` pairs.
+    pub fn get_feature_models(&self) -> Result<Vec<(String, String)>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare("SELECT feature, api_config_id FROM feature_models ORDER BY feature")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+    }
+
+    /// Assign a config to a feature, or clear the override with `None`.
+    pub fn set_feature_model(&self, feature: &str, api_config_id: Option<&str>) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            match api_config_id {
+                Some(id) => conn.execute(
+                    "INSERT INTO feature_models (feature, api_config_id) VALUES (?1, ?2)
+                     ON CONFLICT(feature) DO UPDATE SET api_config_id = excluded.api_config_id",
+                    params![feature, id],
+                )?,
+                None => conn.execute("DELETE FROM feature_models WHERE feature = ?1", params![feature])?,
+            };
+            Ok(())
+        })
+    }
+
+    // ── Conversation helpers ─────────────────────────────────────────
+
+    pub fn get_conversation_thread(&self, id: &str) -> Result<Option<ConversationThreadRow>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, query_record_id, created_at, updated_at
+                 FROM conversation_threads WHERE id = ?1",
+            )?;
+            let mut rows = stmt.query_map(params![id], |row| {
+                Ok(ConversationThreadRow {
+                    id: row.get(0)?,
+                    query_record_id: row.get(1)?,
+                    created_at: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            })?;
+            rows.next().transpose()
+        })
+    }
+
+    /// Threads attached to a query record, most recently active first.
+    pub fn get_conversation_threads(&self, query_record_id: &str) -> Result<Vec<ConversationThreadRow>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, query_record_id, created_at, updated_at
+                 FROM conversation_threads WHERE query_record_id = ?1
+                 ORDER BY updated_at DESC, created_at DESC",
+            )?;
+            let rows = stmt.query_map(params![query_record_id], |row| {
+                Ok(ConversationThreadRow {
+                    id: row.get(0)?,
+                    query_record_id: row.get(1)?,
+                    created_at: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+    }
+
+    /// Append a question and its reply to a thread in one transaction,
+    /// creating the thread on `query_record_id` if it does not exist yet.
+    pub fn insert_conversation_exchange(
+        &self,
+        thread_id: &str,
+        query_record_id: &str,
+        question: &str,
+        reply: &str,
+    ) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "INSERT OR IGNORE INTO conversation_threads (id, query_record_id) VALUES (?1, ?2)",
+                params![thread_id, query_record_id],
+            )?;
+            for (role, content) in [("user", question), ("assistant", reply)] {
+                tx.execute(
+                    "INSERT INTO conversation_messages (thread_id, role, content) VALUES (?1, ?2, ?3)",
+                    params![thread_id, role, content],
+                )?;
+            }
+            tx.execute(
+                "UPDATE conversation_threads SET updated_at = datetime('now') WHERE id = ?1",
+                params![thread_id],
+            )?;
+            tx.commit()
+        })
+    }
+
+    /// Messages of a thread in insertion order.
+    pub fn get_conversation_messages(&self, thread_id: &str) -> Result<Vec<ConversationMessageRow>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT role, content, created_at FROM conversation_messages
+                 WHERE thread_id = ?1 ORDER BY id",
+            )?;
+            let rows = stmt.query_map(params![thread_id], |row| {
+                Ok(ConversationMessageRow {
+                    role: row.get(0)?,
+                    content: row.get(1)?,
+                    created_at: row.get(2)?,
+                })
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+    }
+
+    pub fn delete_conversation_thread(&self, id: &str) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            conn.execute("DELETE FROM conversation_messages WHERE thread_id = ?1", params![id])?;
+            conn.execute("DELETE FROM conversation_threads WHERE id = ?1", params![id])?;
+            Ok(())
+        })
+    }
+
+    // ── Usage helpers ────────────────────────────────────────────────
+
+    pub fn insert_usage_record(
+        &self,
+        api_config_id: &str,
+        model_name: &str,
+        kind: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+        characters: u64,
+    ) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO usage_records (api_config_id, model_name, kind, input_tokens, output_tokens, characters)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![api_config_id, model_name, kind, input_tokens as i64, output_tokens as i64, characters as i64],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Aggregate usage per (api_config_id, model_name, kind) since `since_sql`,
+    /// a trusted SQLite datetime expression (see `UsageRange::since_sql`).
+    pub fn get_usage_aggregates(&self, since_sql: &str) -> Result<Vec<UsageAggregateRow>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT api_config_id, model_name, kind, COUNT(*), SUM(input_tokens), SUM(output_tokens), SUM(characters)
+                 FROM usage_records WHERE created_at >= {since_sql}
+                 GROUP BY api_config_id, model_name, kind
+                 ORDER BY api_config_id, model_name"
+            ))?;
+            let rows = stmt.query_map([], |row| {
+                Ok(UsageAggregateRow {
+                    api_config_id: row.get(0)?,
+                    model_name: row.get(1)?,
+                    kind: row.get(2)?,
+                    requests: row.get::<_, i64>(3)? as u64,
+                    input_tokens: row.get::<_, i64>(4)? as u64,
+                    output_tokens: row.get::<_, i64>(5)? as u64,
+                    characters: row.get::<_, i64>(6)? as u64,
+                })
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+    }
+
+    pub fn upsert_model_price(
+        &self,
+        model_name: &str,
+        input_per_million: f64,
+        output_per_million: f64,
+        per_million_chars: f64,
+    ) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO model_prices (model_name, input_per_million, output_per_million, per_million_chars)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(model_name) DO UPDATE SET
+                   input_per_million=excluded.input_per_million, output_per_million=excluded.output_per_million,
+                   per_million_chars=excluded.per_million_chars",
+                params![model_name, input_per_million, output_per_million, per_million_chars],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn get_model_prices(&self) -> Result<Vec<ModelPriceRow>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT model_name, input_per_million, output_per_million, per_million_chars
+                 FROM model_prices ORDER BY model_name",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(ModelPriceRow {
+                    model_name: row.get(0)?,
+                    input_per_million: row.get(1)?,
+                    output_per_million: row.get(2)?,
+                    per_million_chars: row.get(3)?,
+                })
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+    }
+
+    pub fn delete_model_price(&self, model_name: &str) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            conn.execute("DELETE FROM model_prices WHERE model_name = ?1", params![model_name])?;
+            Ok(())
+        })
+    }
+
+    // ── Prompt template helpers ──────────────────────────────────────
+
+    pub fn get_prompt_template(&self, id: &str) -> Result<Option<PromptTemplateRow>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, system_prompt, user_prompt, updated_at FROM prompt_templates WHERE id = ?1",
+            )?;
+            let mut rows = stmt.query_map(params![id], Self::prompt_template_row)?;
+            rows.next().transpose()
+        })
+    }
+
+    pub fn get_prompt_templates(&self) -> Result<Vec<PromptTemplateRow>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, system_prompt, user_prompt, updated_at FROM prompt_templates ORDER BY id",
+            )?;
+            let rows = stmt.query_map([], Self::prompt_template_row)?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+    }
+
+    pub fn upsert_prompt_template(&self, id: &str, system_prompt: &str, user_prompt: &str) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO prompt_templates (id, system_prompt, user_prompt) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(id) DO UPDATE SET
+                   system_prompt=excluded.system_prompt, user_prompt=excluded.user_prompt,
+                   updated_at=datetime('now')",
+                params![id, system_prompt, user_prompt],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn delete_prompt_template(&self, id: &str) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            conn.execute("DELETE FROM prompt_templates WHERE id = ?1", params![id])?;
+            Ok(())
+        })
+    }
+
+    fn prompt_template_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<PromptTemplateRow> {
+        Ok(PromptTemplateRow {
+            id: row.get(0)?,
+            system_prompt: row.get(1)?,
+            user_prompt: row.get(2)?,
+            updated_at: row.get(3)?,
+        })
+    }
+
+    // ── Analysis preset helpers ──────────────────────────────────────
+
+    pub fn get_analysis_preset(&self, id: &str) -> Result<Option<AnalysisPresetRow>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, name, sections, created_at, updated_at FROM analysis_presets WHERE id = ?1",
+            )?;
+            let mut rows = stmt.query_map(params![id], Self::analysis_preset_row)?;
+            rows.next().transpose()
+        })
+    }
+
+    pub fn get_analysis_presets(&self) -> Result<Vec<AnalysisPresetRow>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, name, sections, created_at, updated_at FROM analysis_presets ORDER BY created_at, id",
+            )?;
+            let rows = stmt.query_map([], Self::analysis_preset_row)?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+    }
+
+    /// Insert or update a preset; `sections` is the JSON-encoded section list.
+    pub fn upsert_analysis_preset(&self, id: &str, name: &str, sections: &str) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO analysis_presets (id, name, sections) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(id) DO UPDATE SET
+                   name=excluded.name, sections=excluded.sections, updated_at=datetime('now')",
+                params![id, name, sections],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn delete_analysis_preset(&self, id: &str) -> Result<(), VeyaError> {
+        self.with_conn(|conn| {
+            conn.execute("DELETE FROM analysis_presets WHERE id = ?1", params![id])?;
+            Ok(())
+        })
+    }
+
+    fn analysis_preset_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<AnalysisPresetRow> {
+        Ok(AnalysisPresetRow {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            sections: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+        })
+    }
+
+    // ── Analysis cache helpers ───────────────────────────────────────
+
+    /// Look up a cached analysis and mark it as recently used.
+    pub fn get_analysis_cache_entry(&self, key: &str) -> Result<Option<AnalysisCacheRow>, VeyaError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT key, model, sections, result, size_bytes, created_at FROM analysis_cache WHERE key = ?1",
+            )?;
+            let mut rows = stmt.query_map(params![key], |row| {
+                Ok(AnalysisCacheRow {
+                    key: row.get(0)?,
+                    model: row.get(1)?,
+                    sections: row.get(2)?,
+                    result: row.get(3)?,
+                    size_bytes: row.get(4)?,
+                    created_at: row.get(5)?,
+                })
+            })?;
+            let entry = rows.next().transpose()?;
+            if entry.is_some() {
+                conn.execute(
+                    "UPDATE analysis_cache SET last_hit_at = datetime('now') WHERE key = ?1",
+                    params![key],
+                )?;
+            }
+            Ok(entry)
+        })
+    }
+
+    pub fn put_analysis_cache_entry(&self, key: &str, model: &str, sections: &str, result: &str) -> Result<(), VeyaError> {
+        let size_bytes = (sections.len() + result.len()) as i64;
+        self.with_conn(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO analysis_cache (key, model, sections, result, size_bytes)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![key, model, sections, result, size_bytes],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Remove entries created more than `max_days` ago, then the least recently
+    /// used entries until the total size is within `max_bytes`.
+    /// Returns the number of entries removed.
+    pub fn prune_analysis_cache(&self, max_bytes: u64, max_days: u32) -> Result<usize, VeyaError> {
+        self.with_conn(|conn| {
+            let mut removed = conn.execute(
+                "DELETE FROM analysis_cache WHERE created_at < datetime('now', ?1)",
+                params![format!("-{max_days} days")],
+            )?;
+
+            let mut stmt = conn.prepare(
+                "SELECT key, size_bytes FROM analysis_cache ORDER BY last_hit_at DESC, created_at DESC",
+            )?;
+            let entries = stmt
+                .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut total: u64 = 0;
+            for (key, size) in entries {
+                total += size.max(0) as u64;
+                if total > max_bytes {
+                    removed += conn.execute("DELETE FROM analysis_cache WHERE key = ?1", params![key])?;
+                }
+            }
+            Ok(removed)
+        })
+    }
+
+    /// Delete every cached analysis. Returns the number of entries removed.
+    pub fn clear_analysis_cache(&self) -> Result<usize, VeyaError> {
+        self.with_conn(|conn| conn.execute("DELETE FROM analysis_cache", []))
+    }
 }
 
 
@@ -306,10 +839,91 @@ pub struct ApiConfigRow {
     pub is_local: bool,
     pub is_active: bool,
     pub created_at: String,
+    /// JSON-encoded `OllamaOptions`, if any.
+    pub ollama_options: Option<String>,
+    /// JSON-encoded `GenerationParams`, if any.
+    pub generation_params: Option<String>,
+    /// Position in the model type's fallback chain; None when not in the chain.
+    pub fallback_rank: Option<i64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConversationThreadRow {
+    pub id: String,
+    pub query_record_id: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConversationMessageRow {
+    pub role: String,
+    pub content: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct UsageAggregateRow {
+    pub api_config_id: String,
+    pub model_name: String,
+    pub kind: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub characters: u64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModelPriceRow {
+    pub model_name: String,
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+    pub per_million_chars: f64,
+}
+
+/// A user-edited prompt template; templates without a row use the built-in default.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PromptTemplateRow {
+    pub id: String,
+    pub system_prompt: String,
+    pub user_prompt: String,
+    pub updated_at: String,
+}
+
+/// A user-defined analysis preset; `sections` holds the JSON-encoded section list.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AnalysisPresetRow {
+    pub id: String,
+    pub name: String,
+    pub sections: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A cached analysis; `sections` is the JSON-encoded list of (key, content) pairs.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AnalysisCacheRow {
+    pub key: String,
+    pub model: String,
+    pub sections: String,
+    pub result: String,
+    pub size_bytes: i64,
+    pub created_at: String,
 }
 
 // ── Migration SQL ────────────────────────────────────────────────
 
+/// Activates the oldest config (by insertion order) of each model type without an active one.
+const ENSURE_ACTIVE_CONFIGS: &str = r#"
+UPDATE api_configs SET is_active = 1 WHERE rowid IN (
+    SELECT MIN(a.rowid) FROM api_configs a
+    WHERE NOT EXISTS (
+        SELECT 1 FROM api_configs b WHERE b.model_type = a.model_type AND b.is_active = 1
+    )
+    GROUP BY a.model_type
+);
+"#;
+
 const MIGRATION_V1: &str = r#"
 CREATE TABLE IF NOT EXISTS query_records (
     id TEXT PRIMARY KEY,
@@ -359,6 +973,100 @@ CREATE TABLE IF NOT EXISTS settings (
 );
 "#;
 
+/// Incremental migrations applied after `MIGRATION_V1`; entry `i` produces schema v`i + 2`.
+const MIGRATIONS: &[&str] = &[
+    // v2: native Ollama options per API config
+    "ALTER TABLE api_configs ADD COLUMN ollama_options TEXT;",
+    // v3: generation parameters (temperature, max_tokens, top_p, stop) per API config
+    "ALTER TABLE api_configs ADD COLUMN generation_params TEXT;",
+    // v4: token / character usage and per-model prices
+    r#"
+    CREATE TABLE IF NOT EXISTS usage_records (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        api_config_id TEXT NOT NULL,
+        model_name TEXT NOT NULL,
+        kind TEXT NOT NULL CHECK(kind IN ('llm', 'tts')),
+        input_tokens INTEGER NOT NULL DEFAULT 0,
+        output_tokens INTEGER NOT NULL DEFAULT 0,
+        characters INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+    CREATE INDEX IF NOT EXISTS idx_usage_records_created_at ON usage_records(created_at);
+
+    CREATE TABLE IF NOT EXISTS model_prices (
+        model_name TEXT PRIMARY KEY,
+        input_per_million REAL NOT NULL DEFAULT 0,
+        output_per_million REAL NOT NULL DEFAULT 0,
+        per_million_chars REAL NOT NULL DEFAULT 0
+    );
+    "#,
+    // v5: follow-up conversation threads on query records
+    r#"
+    CREATE TABLE IF NOT EXISTS conversation_threads (
+        id TEXT PRIMARY KEY,
+        query_record_id TEXT NOT NULL REFERENCES query_records(id),
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+    CREATE INDEX IF NOT EXISTS idx_conversation_threads_query ON conversation_threads(query_record_id);
+
+    CREATE TABLE IF NOT EXISTS conversation_messages (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        thread_id TEXT NOT NULL REFERENCES conversation_threads(id),
+        role TEXT NOT NULL CHECK(role IN ('user', 'assistant')),
+        content TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+    CREATE INDEX IF NOT EXISTS idx_conversation_messages_thread ON conversation_messages(thread_id);
+    "#,
+    // v6: ordered fallback chain per model type
+    "ALTER TABLE api_configs ADD COLUMN fallback_rank INTEGER;",
+    // v7: at most one active config per model type, and per-feature model overrides
+    r#"
+    UPDATE api_configs SET is_active = 0 WHERE is_active = 1 AND rowid NOT IN (
+        SELECT MIN(rowid) FROM api_configs WHERE is_active = 1 GROUP BY model_type
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_api_configs_active_per_type
+        ON api_configs(model_type) WHERE is_active = 1;
+
+    CREATE TABLE IF NOT EXISTS feature_models (
+        feature TEXT PRIMARY KEY,
+        api_config_id TEXT NOT NULL REFERENCES api_configs(id)
+    );
+    "#,
+    // v8: user-edited prompt templates (overrides of the built-in defaults)
+    r#"
+    CREATE TABLE IF NOT EXISTS prompt_templates (
+        id TEXT PRIMARY KEY,
+        system_prompt TEXT NOT NULL,
+        user_prompt TEXT NOT NULL,
+        updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+    "#,
+    // v9: user-defined analysis presets (section lists as JSON)
+    r#"
+    CREATE TABLE IF NOT EXISTS analysis_presets (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        sections TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+    "#,
+    // v10: cache of finished analyses, keyed by a hash of text, prompt and model
+    r#"
+    CREATE TABLE IF NOT EXISTS analysis_cache (
+        key TEXT PRIMARY KEY,
+        model TEXT NOT NULL,
+        sections TEXT NOT NULL,
+        result TEXT NOT NULL,
+        size_bytes INTEGER NOT NULL,
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        last_hit_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+    "#,
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -385,6 +1093,14 @@ mod tests {
             assert!(tables.contains(&"word_frequency".to_string()));
             assert!(tables.contains(&"api_configs".to_string()));
             assert!(tables.contains(&"settings".to_string()));
+            assert!(tables.contains(&"usage_records".to_string()));
+            assert!(tables.contains(&"conversation_threads".to_string()));
+            assert!(tables.contains(&"conversation_messages".to_string()));
+            assert!(tables.contains(&"feature_models".to_string()));
+            assert!(tables.contains(&"model_prices".to_string()));
+            assert!(tables.contains(&"prompt_templates".to_string()));
+            assert!(tables.contains(&"analysis_presets".to_string()));
+            assert!(tables.contains(&"analysis_cache".to_string()));
             Ok(())
         })
         .unwrap();
@@ -428,4 +1144,111 @@ mod tests {
         db.delete_api_config("c1").unwrap();
         assert_eq!(db.get_api_configs().unwrap().len(), 0);
     }
+
+    #[test]
+    fn migrations_are_idempotent_on_reopen() {
+        let dir = TempDir::new().unwrap();
+        {
+            let db = Database::open(dir.path().to_path_buf()).unwrap();
+            db.insert_api_config("c1", "Local", "ollama", "text", "http://localhost:11434", "llama3", "ref_c1", None, true).unwrap();
+            db.set_api_config_ollama_options("c1", Some(r#"{"num_ctx":8192}"#)).unwrap();
+            db.set_api_config_generation_params("c1", Some(r#"{"temperature":0.2}"#)).unwrap();
+        }
+        let db = Database::open(dir.path().to_path_buf()).unwrap();
+        let version: usize = db.with_conn(|conn| conn.query_row("PRAGMA user_version", [], |r| r.get(0))).unwrap();
+        assert_eq!(version, MIGRATIONS.len() + 1);
+        let configs = db.get_api_configs().unwrap();
+        assert_eq!(configs[0].ollama_options.as_deref(), Some(r#"{"num_ctx":8192}"#));
+        assert_eq!(configs[0].generation_params.as_deref(), Some(r#"{"temperature":0.2}"#));
+    }
+
+    #[test]
+    fn upgrade_activates_a_config_per_type() {
+        let dir = TempDir::new().unwrap();
+        {
+            let db = Database::open(dir.path().to_path_buf()).unwrap();
+            db.insert_api_config("a", "A", "openai", "text", "https://a", "gpt-4o", "ref_a", None, false).unwrap();
+            db.insert_api_config("b", "B", "ollama", "text", "http://b", "llama3", "ref_b", None, true).unwrap();
+            // A v6 database whose configs were never activated.
+            db.with_conn(|conn| conn.execute_batch("UPDATE api_configs SET is_active = 0; PRAGMA user_version = 6;"))
+                .unwrap();
+        }
+        let db = Database::open(dir.path().to_path_buf()).unwrap();
+        let rows = db.get_api_configs().unwrap();
+        let active: Vec<&str> = rows.iter().filter(|r| r.is_active).map(|r| r.id.as_str()).collect();
+        assert_eq!(active, vec!["a"]);
+        assert_eq!(crate::api_config::fallback_chain(&rows, "text").len(), 1);
+    }
+
+    #[test]
+    fn conversation_thread_crud() {
+        let (db, _dir) = test_db();
+        db.insert_query_record("q1", "hola", "text_insight", Some("es"), "{}").unwrap();
+        db.insert_conversation_exchange("t1", "q1", "why?", "because").unwrap();
+
+        assert_eq!(db.get_conversation_threads("q1").unwrap().len(), 1);
+        let messages = db.get_conversation_messages("t1").unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "user");
+        assert_eq!(messages[1].content, "because");
+
+        db.delete_conversation_thread("t1").unwrap();
+        assert!(db.get_conversation_thread("t1").unwrap().is_none());
+        assert!(db.get_conversation_messages("t1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn fallback_chain_replaces_previous_order() {
+        let (db, _dir) = test_db();
+        db.insert_api_config("a", "A", "openai", "text", "https://a", "gpt-4o", "ref_a", None, false).unwrap();
+        db.insert_api_config("b", "B", "ollama", "text", "http://b", "llama3", "ref_b", None, true).unwrap();
+        db.insert_api_config("t", "T", "openai", "tts", "https://t", "tts-1", "ref_t", None, false).unwrap();
+
+        db.set_fallback_chain("text", &["b".into(), "a".into(), "t".into()]).unwrap();
+        let rank = |id: &str| db.get_api_configs().unwrap().into_iter().find(|r| r.id == id).unwrap().fallback_rank;
+        assert_eq!((rank("b"), rank("a"), rank("t")), (Some(0), Some(1), None));
+
+        db.set_fallback_chain("text", &["a".into()]).unwrap();
+        assert_eq!((rank("a"), rank("b")), (Some(0), None));
+    }
+
+    #[test]
+    fn set_active_config_keeps_one_active_per_type() {
+        let (db, _dir) = test_db();
+        db.insert_api_config("a", "A", "openai", "text", "https://a", "gpt-4o", "ref_a", None, false).unwrap();
+        db.insert_api_config("b", "B", "ollama", "text", "http://b", "llama3", "ref_b", None, true).unwrap();
+        db.insert_api_config("v", "V", "openai", "vision", "https://v", "gpt-4o", "ref_v", None, false).unwrap();
+        let active = |db: &Database| -> Vec<String> {
+            db.get_api_configs().unwrap().into_iter().filter(|r| r.is_active).map(|r| r.id).collect()
+        };
+
+        db.ensure_active_configs().unwrap();
+        assert_eq!(active(&db), vec!["a", "v"]);
+
+        db.set_active_config("text", "b").unwrap();
+        assert_eq!(active(&db), vec!["b", "v"]);
+
+        // Unknown or mismatched IDs leave the current assignment untouched.
+        assert!(db.set_active_config("text", "v").is_err());
+        assert_eq!(active(&db), vec!["b", "v"]);
+
+        // Moving a config to another type drops its active flag.
+        db.insert_api_config("b", "B", "ollama", "vision", "http://b", "llava", "ref_b", None, true).unwrap();
+        assert_eq!(active(&db), vec!["v"]);
+    }
+
+    #[test]
+    fn feature_model_overrides_are_cleared_with_their_config() {
+        let (db, _dir) = test_db();
+        db.insert_api_config("a", "A", "openai", "text", "https://a", "gpt-4o", "ref_a", None, false).unwrap();
+        db.set_feature_model("podcast_script", Some("a")).unwrap();
+        assert_eq!(db.get_feature_model("podcast_script").unwrap().as_deref(), Some("a"));
+
+        db.set_feature_model("podcast_script", None).unwrap();
+        assert!(db.get_feature_model("podcast_script").unwrap().is_none());
+
+        db.set_feature_model("insight", Some("a")).unwrap();
+        db.delete_api_config("a").unwrap();
+        assert!(db.get_feature_models().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,597 @@
+//! Language detection for selected and recognized text.
+//!
+//! Trigram detection (whatlang) alone misreads short snippets, mixed CJK/Latin
+//! text and kanji-only Japanese, so detection here works in two steps:
+//! script heuristics decide between Chinese, Japanese and Korean (kana ⇒ ja,
+//! hangul ⇒ ko), and for alphabetic text the learner's languages compete
+//! with whatlang's guess as a prior, which matters most for short snippets
+//! where that guess is unreliable. A language the user picks always wins.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use whatlang::{Detector, Lang};
+
+use crate::db::Database;
+use crate::error::VeyaError;
+use crate::learner_profile::{primary_subtag, LearnerProfile};
+
+/// Code returned when nothing could be detected.
+pub const UNKNOWN_LANGUAGE: &str = "unknown";
+
+/// How much a learner language that reads about as well as whatlang's guess
+/// counts, when that guess is unreliable (short text) or reliable.
+const PRIOR_WEIGHT_UNRELIABLE: f64 = 0.9;
+const PRIOR_WEIGHT_RELIABLE: f64 = 0.6;
+
+/// Below this confidence, alphabetic fragments in CJK text count as English.
+const EMBEDDED_MIN_CONFIDENCE: f64 = 0.5;
+
+/// Frequent English words that are rare in other Latin-script languages.
+/// Trigrams can't tell "hello world" from Dutch, so short English snippets
+/// are recognized by their words instead.
+const COMMON_ENGLISH_WORDS: &[&str] = &[
+    "i", "you", "he", "she", "we", "they", "it", "my", "your", "his", "her", "our", "their",
+    "the", "and", "of", "to", "that", "this", "these", "those", "with", "for", "from", "at",
+    "by", "on", "about", "into", "over", "after", "before", "because", "if", "or", "but",
+    "not", "are", "be", "been", "being", "have", "has", "had", "do", "does", "did", "will",
+    "would", "can", "could", "should", "must", "shall", "what", "which", "who", "when",
+    "where", "why", "how", "all", "some", "any", "there", "here", "just", "very", "much",
+    "more", "most", "than", "then", "now", "like", "get", "got", "make", "made", "take",
+    "took", "go", "going", "went", "come", "know", "think", "see", "look", "want", "need",
+    "good", "great", "new", "old", "day", "time", "people", "world", "hello", "hi", "thanks",
+    "thank", "please", "yes", "morning", "night", "today", "tomorrow", "yesterday",
+];
+
+/// Languages with their own short codes; everything else uses whatlang's
+/// ISO 639-3 code.
+const LANG_CODES: &[(Lang, &str)] = &[
+    (Lang::Eng, "en"),
+    (Lang::Cmn, "zh"),
+    (Lang::Jpn, "ja"),
+    (Lang::Kor, "ko"),
+    (Lang::Fra, "fr"),
+    (Lang::Deu, "de"),
+    (Lang::Spa, "es"),
+    (Lang::Por, "pt"),
+    (Lang::Rus, "ru"),
+    (Lang::Ita, "it"),
+];
+
+// ── Types ────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageCandidate {
+    /// Language code, e.g. "en" or "ja".
+    pub language: String,
+    /// 0.0 – 1.0; 1.0 for a user override.
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageDetection {
+    /// The language to use: the override, else the best candidate, else "unknown".
+    pub language: String,
+    /// Candidates, most likely first.
+    pub candidates: Vec<LanguageCandidate>,
+    /// True when `language` was supplied by the user.
+    pub overridden: bool,
+}
+
+/// A run of text in a single language.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageSpan {
+    pub text: String,
+    pub language: String,
+    /// Byte offsets of `text` within the split string.
+    pub start: usize,
+    pub end: usize,
+}
+
+/// What detection may take into account besides the text.
+#[derive(Debug, Clone, Default)]
+pub struct DetectionHints {
+    /// Languages the user is likely to read, most relevant first.
+    pub priors: Vec<String>,
+    /// Language chosen by the user; skips detection.
+    pub override_language: Option<String>,
+}
+
+impl DetectionHints {
+    /// The learner's target languages (in order), then their native language.
+    pub fn for_learner(profile: &LearnerProfile) -> Self {
+        let mut priors: Vec<String> = profile
+            .target_languages
+            .iter()
+            .map(|t| t.language.clone())
+            .collect();
+        priors.push(profile.native_language.clone());
+        Self {
+            priors,
+            override_language: None,
+        }
+    }
+
+    /// Use `language` instead of detecting; empty or "auto" means detect.
+    pub fn with_override(mut self, language: Option<String>) -> Self {
+        self.override_language = language
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty() && !l.eq_ignore_ascii_case("auto"));
+        self
+    }
+}
+
+// ── Detection ────────────────────────────────────────────────────
+
+/// Detect the language of `text`, returning candidates with confidence.
+pub fn detect(text: &str, hints: &DetectionHints) -> LanguageDetection {
+    let mut candidates = score_candidates(text, &hints.priors);
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    if let Some(language) = &hints.override_language {
+        let code = primary_subtag(language);
+        candidates.retain(|c| c.language != code);
+        candidates.insert(0, LanguageCandidate { language: language.clone(), confidence: 1.0 });
+        return LanguageDetection {
+            language: language.clone(),
+            candidates,
+            overridden: true,
+        };
+    }
+
+    LanguageDetection {
+        language: candidates
+            .first()
+            .map(|c| c.language.clone())
+            .unwrap_or_else(|| UNKNOWN_LANGUAGE.into()),
+        candidates,
+        overridden: false,
+    }
+}
+
+/// Best guess for `text` without any hints, e.g. "en", "zh", "ja".
+pub fn detect_language(text: &str) -> String {
+    detect(text, &DetectionHints::default()).language
+}
+
+// ── Spans ────────────────────────────────────────────────────────
+
+/// Split `text` into runs of a single language, e.g. "这个 API 的 rate limit
+/// 是多少" into Chinese, English, Chinese, English and Chinese spans.
+///
+/// Text is cut where the script changes between CJK and alphabetic; spaces,
+/// digits and punctuation stay with the preceding run, so the spans cover
+/// `text` exactly. Each CJK run is judged on its own characters, falling back
+/// to the whole text for kanji-only runs. Alphabetic runs are judged together,
+/// since single words are too short for trigrams; short fragments in mostly
+/// CJK text are taken as English (technical terms, names) unless they clearly
+/// read as something else. An override replaces the language of the text as
+/// a whole; runs in another script keep their own.
+pub fn split_language_spans(text: &str, hints: &DetectionHints) -> Vec<LanguageSpan> {
+    let runs = script_runs(text);
+    let counts = ScriptCounts::of(text);
+    let detection_hints = DetectionHints {
+        priors: hints.priors.clone(),
+        override_language: None,
+    };
+
+    let alphabetic: String = runs
+        .iter()
+        .filter(|(class, _)| *class == ScriptClass::Alphabetic)
+        .map(|(_, range)| &text[range.clone()])
+        .collect::<Vec<_>>()
+        .join(" ");
+    let alphabetic_language = (!alphabetic.is_empty()).then(|| {
+        let detection = detect(&alphabetic, &detection_hints);
+        let confident = detection.candidates.first().is_some_and(|c| c.confidence >= EMBEDDED_MIN_CONFIDENCE);
+        if counts.cjk() > counts.alphabetic_words && !confident {
+            "en".to_string()
+        } else {
+            detection.language
+        }
+    });
+
+    let whole_text_language = hints
+        .override_language
+        .as_ref()
+        .map(|_| detect(text, &detection_hints).language);
+
+    let mut spans: Vec<LanguageSpan> = Vec::new();
+    for (class, range) in runs {
+        let mut language = match class {
+            ScriptClass::Cjk => cjk_run_language(&text[range.clone()], &counts, &hints.priors),
+            ScriptClass::Alphabetic => alphabetic_language.clone().unwrap_or_else(|| UNKNOWN_LANGUAGE.into()),
+            ScriptClass::Neutral => whole_text_language.clone().unwrap_or_else(|| UNKNOWN_LANGUAGE.into()),
+        };
+        if let (Some(overridden), Some(whole)) = (&hints.override_language, &whole_text_language) {
+            if language == *whole || class == ScriptClass::Neutral {
+                language = overridden.clone();
+            }
+        }
+
+        match spans.last_mut() {
+            Some(last) if last.language == language => {
+                last.text.push_str(&text[range.clone()]);
+                last.end = range.end;
+            }
+            _ => spans.push(LanguageSpan {
+                text: text[range.clone()].to_string(),
+                language,
+                start: range.start,
+                end: range.end,
+            }),
+        }
+    }
+    spans
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScriptClass {
+    Cjk,
+    Alphabetic,
+    /// Spaces, digits and punctuation: no language of their own.
+    Neutral,
+}
+
+fn script_class(c: char) -> ScriptClass {
+    if cjk_script(c).is_some() {
+        ScriptClass::Cjk
+    } else if c.is_alphabetic() {
+        ScriptClass::Alphabetic
+    } else {
+        ScriptClass::Neutral
+    }
+}
+
+/// Byte ranges of CJK and alphabetic runs. Neutral characters join the
+/// preceding run (or the following one at the start); text without any
+/// letters is a single neutral run.
+fn script_runs(text: &str) -> Vec<(ScriptClass, std::ops::Range<usize>)> {
+    let mut runs: Vec<(ScriptClass, std::ops::Range<usize>)> = Vec::new();
+    let mut leading = 0;
+    for (i, c) in text.char_indices() {
+        let end = i + c.len_utf8();
+        match (script_class(c), runs.last_mut()) {
+            (ScriptClass::Neutral, Some((_, range))) => range.end = end,
+            (ScriptClass::Neutral, None) => leading = end,
+            (class, Some((last, range))) if *last == class => range.end = end,
+            (class, _) => {
+                let start = if runs.is_empty() { 0 } else { i };
+                runs.push((class, start..end));
+            }
+        }
+    }
+    if runs.is_empty() && leading > 0 {
+        runs.push((ScriptClass::Neutral, 0..leading));
+    }
+    runs
+}
+
+/// Hangul and kana decide a run by themselves; a kanji-only run takes the
+/// language the whole text's CJK characters point to.
+fn cjk_run_language(run: &str, whole: &ScriptCounts, priors: &[String]) -> String {
+    let counts = ScriptCounts::of(run);
+    let decisive = if counts.hangul > 0 || counts.kana > 0 { &counts } else { whole };
+    cjk_candidates(decisive, priors, 1.0)
+        .into_iter()
+        .next()
+        .map(|c| c.language)
+        .unwrap_or_else(|| UNKNOWN_LANGUAGE.into())
+}
+
+/// Counts of the scripts that matter for telling languages apart.
+#[derive(Debug, Default)]
+struct ScriptCounts {
+    kana: usize,
+    hangul: usize,
+    han: usize,
+    /// Words of alphabetic (non-CJK) letters.
+    alphabetic_words: usize,
+}
+
+impl ScriptCounts {
+    fn of(text: &str) -> Self {
+        let mut counts = Self::default();
+        let mut in_word = false;
+        for c in text.chars() {
+            match cjk_script(c) {
+                Some(CjkScript::Kana) => counts.kana += 1,
+                Some(CjkScript::Hangul) => counts.hangul += 1,
+                Some(CjkScript::Han) => counts.han += 1,
+                None => {}
+            }
+            let alphabetic = c.is_alphabetic() && cjk_script(c).is_none();
+            if alphabetic && !in_word {
+                counts.alphabetic_words += 1;
+            }
+            in_word = alphabetic;
+        }
+        counts
+    }
+
+    fn cjk(&self) -> usize {
+        self.kana + self.hangul + self.han
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CjkScript {
+    Kana,
+    Hangul,
+    Han,
+}
+
+/// Han and kana: written without spaces between words, unlike Hangul.
+pub fn is_unspaced_script(c: char) -> bool {
+    matches!(cjk_script(c), Some(CjkScript::Han | CjkScript::Kana))
+}
+
+fn cjk_script(c: char) -> Option<CjkScript> {
+    match c as u32 {
+        0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Some(CjkScript::Kana),
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Some(CjkScript::Hangul),
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F => Some(CjkScript::Han),
+        _ => None,
+    }
+}
+
+fn score_candidates(text: &str, priors: &[String]) -> Vec<LanguageCandidate> {
+    let counts = ScriptCounts::of(text);
+    let cjk = counts.cjk();
+    if cjk == 0 && counts.alphabetic_words == 0 {
+        return Vec::new();
+    }
+
+    // A CJK character carries about as much as an alphabetic word.
+    let cjk_share = cjk as f64 / (cjk + counts.alphabetic_words) as f64;
+    let mut candidates = Vec::new();
+    if cjk > 0 {
+        candidates.extend(cjk_candidates(&counts, priors, cjk_share));
+    }
+    if counts.alphabetic_words > 0 {
+        // Leave CJK characters out so they don't skew trigram detection.
+        let alphabetic: String = text.chars().filter(|c| cjk_script(*c).is_none()).collect();
+        candidates.extend(alphabetic_candidates(&alphabetic, priors, 1.0 - cjk_share));
+    }
+    candidates
+}
+
+/// Kana means Japanese and hangul Korean; text with only Han characters is
+/// Chinese unless the learner reads Japanese but not Chinese (or lists it first).
+fn cjk_candidates(counts: &ScriptCounts, priors: &[String], share: f64) -> Vec<LanguageCandidate> {
+    let candidate = |language: &str, confidence: f64| LanguageCandidate {
+        language: language.into(),
+        confidence: confidence * share,
+    };
+
+    if counts.hangul > 0 && counts.hangul >= counts.kana {
+        return vec![candidate("ko", 0.95)];
+    }
+    if counts.kana > 0 {
+        return vec![candidate("ja", 0.95)];
+    }
+
+    let rank = |code: &str| priors.iter().position(|p| primary_subtag(p) == code);
+    let japanese_first = match (rank("ja"), rank("zh")) {
+        (Some(ja), Some(zh)) => ja < zh,
+        (Some(_), None) => true,
+        _ => false,
+    };
+    if japanese_first {
+        vec![candidate("ja", 0.7), candidate("zh", 0.3)]
+    } else {
+        vec![candidate("zh", 0.8), candidate("ja", 0.2)]
+    }
+}
+
+/// whatlang's guess, plus the learner's languages that read about as well
+/// as it does, and English when the text is mostly common English words.
+fn alphabetic_candidates(text: &str, priors: &[String], share: f64) -> Vec<LanguageCandidate> {
+    let mut scores: Vec<(String, f64)> = Vec::new();
+    let mut add = |language: String, confidence: f64| match scores.iter_mut().find(|(l, _)| *l == language) {
+        Some((_, existing)) => *existing = existing.max(confidence),
+        None => scores.push((language, confidence)),
+    };
+
+    if let Some(guess) = whatlang::detect(text) {
+        let confidence = guess.confidence();
+        add(lang_code(guess.lang()), confidence);
+
+        let weight = if guess.is_reliable() { PRIOR_WEIGHT_RELIABLE } else { PRIOR_WEIGHT_UNRELIABLE };
+        let mut prior_langs: Vec<Lang> = priors.iter().filter_map(|p| to_lang(p)).collect();
+        prior_langs.dedup();
+        for prior in prior_langs {
+            if prior == guess.lang() {
+                add(lang_code(prior), confidence + (1.0 - confidence) * weight * 0.5);
+                continue;
+            }
+            // With only the two allowed, the confidence is whatlang's margin
+            // between them: a small margin makes the prior a close second.
+            let Some(pair) = Detector::with_allowlist(vec![guess.lang(), prior]).detect(text) else {
+                continue;
+            };
+            let closeness = if pair.lang() == prior { 1.0 } else { 1.0 - pair.confidence() };
+            add(lang_code(prior), weight * closeness);
+        }
+
+        if !guess.is_reliable() {
+            add("en".into(), 0.9 * english_word_share(text));
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter(|(_, confidence)| *confidence > 0.0)
+        .map(|(language, confidence)| LanguageCandidate {
+            language,
+            confidence: confidence.clamp(0.0, 1.0) * share,
+        })
+        .collect()
+}
+
+/// Share of the words in `text` that are common English words.
+fn english_word_share(text: &str) -> f64 {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic() && c != '\'')
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    if words.is_empty() {
+        return 0.0;
+    }
+    let english = words.iter().filter(|w| COMMON_ENGLISH_WORDS.contains(&w.as_str())).count();
+    english as f64 / words.len() as f64
+}
+
+fn lang_code(lang: Lang) -> String {
+    LANG_CODES
+        .iter()
+        .find(|(l, _)| *l == lang)
+        .map(|(_, code)| *code)
+        .unwrap_or_else(|| lang.code())
+        .to_string()
+}
+
+fn to_lang(code: &str) -> Option<Lang> {
+    let code = primary_subtag(code);
+    LANG_CODES
+        .iter()
+        .find(|(_, c)| *c == code)
+        .map(|(lang, _)| *lang)
+        .or_else(|| Lang::from_code(code))
+}
+
+// ── Tauri Commands ───────────────────────────────────────────────
+
+/// Detect the language of `text` with the learner's languages as a prior.
+/// Lets the UI show the candidates and offer an override.
+#[tauri::command]
+pub async fn detect_text_language(
+    text: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<LanguageDetection, VeyaError> {
+    let hints = DetectionHints::for_learner(&LearnerProfile::load(&db)?);
+    Ok(detect(&text, &hints))
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn priors(languages: &[&str]) -> DetectionHints {
+        DetectionHints {
+            priors: languages.iter().map(|l| l.to_string()).collect(),
+            override_language: None,
+        }
+    }
+
+    #[test]
+    fn detects_english_and_chinese_sentences() {
+        let english = "The quick brown fox jumps over the lazy dog. This is a simple English sentence for testing purposes.";
+        assert_eq!(detect_language(english), "en");
+        assert_eq!(detect_language("你好，今天天气怎么样？"), "zh");
+    }
+
+    #[test]
+    fn empty_text_is_unknown() {
+        let detection = detect("  123 !", &DetectionHints::default());
+        assert_eq!(detection.language, UNKNOWN_LANGUAGE);
+        assert!(detection.candidates.is_empty());
+    }
+
+    #[test]
+    fn kana_and_hangul_decide_japanese_and_korean() {
+        assert_eq!(detect_language("今日はいい天気ですね"), "ja");
+        assert_eq!(detect_language("안녕하세요, 만나서 반갑습니다"), "ko");
+    }
+
+    #[test]
+    fn kanji_only_text_follows_the_learner() {
+        assert_eq!(detect("日本語", &DetectionHints::default()).language, "zh");
+        let detection = detect("日本語", &priors(&["ja", "en"]));
+        assert_eq!(detection.language, "ja");
+        assert_eq!(detection.candidates[1].language, "zh");
+        assert_eq!(detect("日本語", &priors(&["zh", "ja"])).language, "zh");
+    }
+
+    #[test]
+    fn mixed_text_goes_by_the_dominant_script() {
+        assert_eq!(detect_language("我今天用 Python 写了一个小程序"), "zh");
+        let detection = detect("I had 寿司 for dinner with my friends yesterday", &DetectionHints::default());
+        assert_eq!(detection.language, "en");
+        assert!(detection.candidates.iter().any(|c| c.language == "zh"));
+    }
+
+    #[test]
+    fn short_snippets_prefer_the_learners_languages() {
+        assert_eq!(detect_language("hello world"), "en");
+        assert_eq!(detect_language("I like it"), "en");
+        assert_eq!(detect("buenos días amigo", &priors(&["es", "zh"])).language, "es");
+        // A prior doesn't override text that clearly reads as another language.
+        assert_eq!(detect("Bonjour tout le monde", &priors(&["es", "zh"])).language, "fr");
+    }
+
+    #[test]
+    fn override_wins_and_keeps_other_candidates() {
+        let hints = priors(&["es"]).with_override(Some(" pt ".into()));
+        let detection = detect("Hola amigos, ¿cómo están?", &hints);
+        assert!(detection.overridden);
+        assert_eq!(detection.language, "pt");
+        assert_eq!(detection.candidates[0], LanguageCandidate { language: "pt".into(), confidence: 1.0 });
+        assert!(detection.candidates.iter().any(|c| c.language == "es"));
+
+        let auto = priors(&[]).with_override(Some("auto".into()));
+        assert!(auto.override_language.is_none());
+    }
+
+    fn span_languages(spans: &[LanguageSpan]) -> Vec<(&str, &str)> {
+        spans.iter().map(|s| (s.text.as_str(), s.language.as_str())).collect()
+    }
+
+    #[test]
+    fn splits_mixed_chinese_and_english() {
+        let text = "这个 API 的 rate limit 是多少";
+        let spans = split_language_spans(text, &priors(&["zh"]));
+        assert_eq!(
+            span_languages(&spans),
+            vec![("这个 ", "zh"), ("API ", "en"), ("的 ", "zh"), ("rate limit ", "en"), ("是多少", "zh")]
+        );
+        for span in &spans {
+            assert_eq!(&text[span.start..span.end], span.text);
+        }
+    }
+
+    #[test]
+    fn spans_keep_kanji_with_japanese_and_merge_same_language_runs() {
+        let spans = split_language_spans("東京でReactを勉強しています", &DetectionHints::default());
+        assert_eq!(
+            span_languages(&spans),
+            vec![("東京で", "ja"), ("React", "en"), ("を勉強しています", "ja")]
+        );
+
+        let spans = split_language_spans("Hello, world!", &DetectionHints::default());
+        assert_eq!(span_languages(&spans), vec![("Hello, world!", "en")]);
+        assert_eq!(split_language_spans("", &DetectionHints::default()), vec![]);
+        assert_eq!(span_languages(&split_language_spans(" 42 ", &DetectionHints::default())), vec![(" 42 ", "unknown")]);
+    }
+
+    #[test]
+    fn span_override_applies_to_the_dominant_language() {
+        let hints = priors(&[]).with_override(Some("zh-TW".into()));
+        let spans = split_language_spans("這個 API 很好用", &hints);
+        assert_eq!(span_languages(&spans), vec![("這個 ", "zh-TW"), ("API ", "en"), ("很好用", "zh-TW")]);
+    }
+
+    #[test]
+    fn learner_hints_list_targets_before_native() {
+        let profile = LearnerProfile {
+            native_language: "zh".into(),
+            target_languages: vec![crate::learner_profile::TargetLanguage {
+                language: "ja".into(),
+                level: crate::learner_profile::CefrLevel::A2,
+            }],
+        };
+        assert_eq!(DetectionHints::for_learner(&profile).priors, vec!["ja", "zh"]);
+    }
+}
@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::db::Database;
+use crate::error::VeyaError;
+use crate::settings::AppSettings;
+
+// ── Types ────────────────────────────────────────────────────────
+
+/// Common European Framework of Reference proficiency level.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CefrLevel {
+    A1,
+    A2,
+    B1,
+    B2,
+    C1,
+    C2,
+}
+
+impl CefrLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::A1 => "A1",
+            Self::A2 => "A2",
+            Self::B1 => "B1",
+            Self::B2 => "B2",
+            Self::C1 => "C1",
+            Self::C2 => "C2",
+        }
+    }
+
+    /// Short description used in prompts.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::A1 => "beginner",
+            Self::A2 => "elementary",
+            Self::B1 => "intermediate",
+            Self::B2 => "upper intermediate",
+            Self::C1 => "advanced",
+            Self::C2 => "proficient",
+        }
+    }
+}
+
+/// A language the user is learning.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TargetLanguage {
+    /// Language code, e.g. "es" or "pt-BR".
+    pub language: String,
+    pub level: CefrLevel,
+}
+
+/// Who the analyses are written for: explanations and translations use the
+/// native language, and difficulty follows the level in the text's language.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LearnerProfile {
+    pub native_language: String,
+    #[serde(default)]
+    pub target_languages: Vec<TargetLanguage>,
+}
+
+// Setting key of the JSON-encoded profile in the SQLite `settings` table.
+const KEY_LEARNER_PROFILE: &str = "learner_profile";
+
+impl LearnerProfile {
+    /// Default profile for a UI locale: the locale's language as the native
+    /// language and no target languages yet.
+    pub fn for_locale(locale: &str) -> Self {
+        Self {
+            native_language: primary_subtag(locale),
+            target_languages: Vec::new(),
+        }
+    }
+
+    /// Load the stored profile, or the default for the UI locale if none is set.
+    pub fn load(db: &Database) -> Result<Self, VeyaError> {
+        let stored = db
+            .get_setting(KEY_LEARNER_PROFILE)?
+            .and_then(|json| serde_json::from_str(&json).ok());
+        match stored {
+            Some(profile) => Ok(profile),
+            None => Ok(Self::for_locale(&AppSettings::load(db)?.locale)),
+        }
+    }
+
+    pub fn save(&self, db: &Database) -> Result<(), VeyaError> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| VeyaError::StorageError(format!("Failed to encode learner profile: {e}")))?;
+        db.set_setting(KEY_LEARNER_PROFILE, &json)
+    }
+
+    /// Trim codes and reject an empty native language or duplicate targets.
+    pub fn normalized(mut self) -> Result<Self, VeyaError> {
+        self.native_language = self.native_language.trim().to_string();
+        if self.native_language.is_empty() {
+            return Err(VeyaError::Generic("Native language is required".into()));
+        }
+        for (index, target) in self.target_languages.iter_mut().enumerate() {
+            target.language = target.language.trim().to_string();
+            if target.language.is_empty() {
+                return Err(VeyaError::Generic(format!("Target language #{} is empty", index + 1)));
+            }
+        }
+        for (index, target) in self.target_languages.iter().enumerate() {
+            let code = primary_subtag(&target.language);
+            if self.target_languages[..index]
+                .iter()
+                .any(|t| primary_subtag(&t.language) == code)
+            {
+                return Err(VeyaError::Generic(format!(
+                    "Target language {} is listed twice",
+                    target.language
+                )));
+            }
+        }
+        Ok(self)
+    }
+
+    /// The learner's level in `language`, matched on the primary subtag
+    /// ("pt-BR" matches "pt"). None if it is not a target language.
+    pub fn level_for(&self, language: &str) -> Option<CefrLevel> {
+        let code = primary_subtag(language);
+        self.target_languages
+            .iter()
+            .find(|t| primary_subtag(&t.language) == code)
+            .map(|t| t.level)
+    }
+
+    pub fn is_native(&self, language: &str) -> bool {
+        primary_subtag(language) == primary_subtag(&self.native_language)
+    }
+
+    /// Language translations of text in `language` should be written in:
+    /// the native language, or the first target language for native text.
+    pub fn translation_language(&self, language: &str) -> &str {
+        match self.target_languages.first() {
+            Some(target) if self.is_native(language) => &target.language,
+            _ => &self.native_language,
+        }
+    }
+
+    /// Profile lines for a system prompt. When `language` (the language of
+    /// the material) is a target language, its level is called out.
+    /// Which language to answer in is left to the caller's prompt.
+    pub fn prompt_context(&self, language: Option<&str>) -> String {
+        let mut lines = vec![format!(
+            "Learner profile:\n- Native language: {}",
+            describe_language(&self.native_language)
+        )];
+        for target in &self.target_languages {
+            lines.push(format!(
+                "- Learning {} at CEFR {} ({})",
+                describe_language(&target.language),
+                target.level.as_str(),
+                target.level.description()
+            ));
+        }
+
+        if let Some((language, level)) = language.and_then(|l| Some((l, self.level_for(l)?))) {
+            lines.push(format!(
+                "The material is in {}, which the learner reads at CEFR {}: pitch vocabulary, \
+                 explanations and simplifications at that level.",
+                describe_language(language),
+                level.as_str()
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+// ── Language names ───────────────────────────────────────────────
+
+/// Primary language subtag, lowercased: "zh-CN" → "zh".
+pub fn primary_subtag(code: &str) -> String {
+    code.trim()
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// English name for common language codes, so prompts don't rely on the
+/// model knowing every code. None for codes without a known name.
+pub fn language_name(code: &str) -> Option<&'static str> {
+    let name = match primary_subtag(code).as_str() {
+        "en" => "English",
+        "zh" => "Chinese",
+        "ja" => "Japanese",
+        "ko" => "Korean",
+        "fr" => "French",
+        "de" => "German",
+        "es" => "Spanish",
+        "pt" => "Portuguese",
+        "ru" => "Russian",
+        "it" => "Italian",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// "Spanish (es)", or just the code when it has no known name.
+pub fn describe_language(code: &str) -> String {
+    match language_name(code) {
+        Some(name) => format!("{name} ({code})"),
+        None => code.to_string(),
+    }
+}
+
+// ── Tauri Commands ───────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn get_learner_profile(
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<LearnerProfile, VeyaError> {
+    LearnerProfile::load(&db)
+}
+
+#[tauri::command]
+pub async fn update_learner_profile(
+    profile: LearnerProfile,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<LearnerProfile, VeyaError> {
+    let profile = profile.normalized()?;
+    profile.save(&db)?;
+    Ok(profile)
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_db() -> (Database, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let db = Database::open(dir.path().to_path_buf()).unwrap();
+        (db, dir)
+    }
+
+    fn spanish_learner() -> LearnerProfile {
+        LearnerProfile {
+            native_language: "zh".into(),
+            target_languages: vec![
+                TargetLanguage { language: "es".into(), level: CefrLevel::B1 },
+                TargetLanguage { language: "en-US".into(), level: CefrLevel::C1 },
+            ],
+        }
+    }
+
+    #[test]
+    fn defaults_to_locale_language() {
+        let (db, _dir) = test_db();
+        db.set_setting("locale", "en-US").unwrap();
+        let profile = LearnerProfile::load(&db).unwrap();
+        assert_eq!(profile.native_language, "en");
+        assert!(profile.target_languages.is_empty());
+    }
+
+    #[test]
+    fn save_and_load_roundtrip() {
+        let (db, _dir) = test_db();
+        spanish_learner().save(&db).unwrap();
+        assert_eq!(LearnerProfile::load(&db).unwrap(), spanish_learner());
+    }
+
+    #[test]
+    fn normalized_rejects_duplicates_and_empty_native() {
+        let mut profile = spanish_learner();
+        profile.target_languages.push(TargetLanguage { language: " ES ".into(), level: CefrLevel::A1 });
+        assert!(profile.normalized().is_err());
+
+        let profile = LearnerProfile { native_language: "  ".into(), target_languages: vec![] };
+        assert!(profile.normalized().is_err());
+    }
+
+    #[test]
+    fn level_matches_primary_subtag() {
+        let profile = spanish_learner();
+        assert_eq!(profile.level_for("es"), Some(CefrLevel::B1));
+        assert_eq!(profile.level_for("en"), Some(CefrLevel::C1));
+        assert_eq!(profile.level_for("fr"), None);
+    }
+
+    #[test]
+    fn native_text_translates_into_first_target() {
+        let profile = spanish_learner();
+        assert_eq!(profile.translation_language("es"), "zh");
+        assert_eq!(profile.translation_language("zh"), "es");
+        assert_eq!(LearnerProfile::for_locale("zh-CN").translation_language("zh"), "zh");
+    }
+
+    #[test]
+    fn prompt_context_names_languages_and_level() {
+        let context = spanish_learner().prompt_context(Some("es"));
+        assert!(context.contains("Native language: Chinese (zh)"));
+        assert!(context.contains("Learning Spanish (es) at CEFR B1 (intermediate)"));
+        assert!(context.contains("reads at CEFR B1"));
+
+        let context = spanish_learner().prompt_context(Some("fr"));
+        assert!(!context.contains("reads at"));
+    }
+}
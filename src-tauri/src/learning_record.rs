@@ -4,6 +4,7 @@ use uuid::Uuid;
 
 use crate::db::{Database, PodcastRow, QueryRow, WordFreqRow};
 use crate::error::VeyaError;
+use crate::language_detect::{split_language_spans, DetectionHints};
 
 // ── Input types ──────────────────────────────────────────────────
 
@@ -82,11 +83,17 @@ pub fn save_query(db: &Database, input: &SaveQueryInput) -> Result<QueryRow, Vey
 
     db.insert_query_record(&id, &input.input_text, &input.source, language, &input.analysis_result)?;
 
-    // Update word frequency table
-    let lang_code = language.unwrap_or("unknown");
-    let words = tokenize(&input.input_text);
-    for word in &words {
-        db.increment_word_frequency(word, lang_code)?;
+    // Update word frequency table, labelling each word with the language of
+    // its span: the record's language, except for runs in another script
+    // (e.g. English terms in Chinese text).
+    let hints = DetectionHints {
+        priors: language.map(|l| vec![l.to_string()]).unwrap_or_default(),
+        override_language: language.map(str::to_string),
+    };
+    for span in split_language_spans(&input.input_text, &hints) {
+        for word in tokenize(&span.text) {
+            db.increment_word_frequency(&word, &span.language)?;
+        }
     }
 
     // Return the saved record
@@ -218,6 +225,25 @@ mod tests {
         assert_eq!(world.count, 1);
     }
 
+    #[test]
+    fn save_query_labels_words_by_span_language() {
+        let (db, _dir) = test_db();
+        let input = SaveQueryInput {
+            input_text: "这个 API 的 rate limit 是多少".into(),
+            source: "text_insight".into(),
+            detected_language: Some("zh".into()),
+            analysis_result: "{}".into(),
+        };
+        save_query(&db, &input).unwrap();
+
+        let words = db.get_frequent_words(20).unwrap();
+        let language_of = |word: &str| words.iter().find(|w| w.word == word).unwrap().language.clone();
+        assert_eq!(language_of("api"), "en");
+        assert_eq!(language_of("limit"), "en");
+        assert_eq!(language_of("这"), "zh");
+        assert_eq!(language_of("少"), "zh");
+    }
+
     #[test]
     fn save_podcast_creates_record() {
         let (db, _dir) = test_db();
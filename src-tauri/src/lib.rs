@@ -1,14 +1,19 @@
 pub mod api_config;
 pub mod cast_engine;
+pub mod conversation;
 pub mod db;
 pub mod error;
+pub mod language_detect;
+pub mod learner_profile;
 pub mod learning_record;
 pub mod llm_client;
+pub mod prompt_template;
 pub mod retry;
 pub mod settings;
 pub mod stronghold_store;
 pub mod text_insight;
 pub mod tts_client;
+pub mod usage;
 pub mod vision_capture;
 
 use std::sync::Arc;
@@ -39,6 +44,7 @@ pub fn run() {
 
             app.manage(database);
             app.manage(stronghold);
+            app.manage(text_insight::AnalysisRegistry::default());
 
             // --- System Tray ---
             setup_system_tray(app)?;
@@ -58,10 +64,29 @@ pub fn run() {
             api_config::get_api_configs,
             api_config::save_api_config,
             api_config::delete_api_config_cmd,
+            api_config::set_fallback_chain,
+            api_config::set_active_config,
+            api_config::get_feature_models,
+            api_config::set_feature_model,
             api_config::test_api_connection,
             settings::get_settings,
             settings::update_settings,
+            learner_profile::get_learner_profile,
+            learner_profile::update_learner_profile,
+            prompt_template::list_prompt_templates,
+            prompt_template::update_prompt_template,
+            prompt_template::reset_prompt_template,
+            prompt_template::preview_prompt_template,
             text_insight::analyze_text,
+            text_insight::cancel_analysis,
+            text_insight::presets::list_analysis_presets,
+            text_insight::presets::save_analysis_preset,
+            text_insight::presets::delete_analysis_preset,
+            text_insight::cache::clear_analysis_cache,
+            language_detect::detect_text_language,
+            conversation::continue_thread,
+            conversation::list_threads,
+            conversation::delete_thread,
             vision_capture::start_capture,
             vision_capture::get_capture_screenshot,
             vision_capture::process_capture,
@@ -75,6 +100,10 @@ pub fn run() {
             learning_record::get_podcast_history,
             learning_record::get_frequent_words,
             settings::update_capture_shortcut,
+            usage::get_usage_summary,
+            usage::get_model_prices,
+            usage::save_model_price,
+            usage::delete_model_price,
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application");
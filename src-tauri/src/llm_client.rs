@@ -1,10 +1,17 @@
+pub mod content;
+pub mod ollama;
+pub mod sse;
+pub mod structured;
+
 use serde::{Deserialize, Serialize};
-use tauri::AppHandle;
-use tauri::Emitter;
+use std::ops::ControlFlow;
 
-use crate::api_config::ApiProvider;
+use crate::api_config::{ApiConfig, ApiProvider};
 use crate::error::VeyaError;
 use crate::retry::RetryPolicy;
+use crate::usage::{TokenUsage, UsageRecorder};
+use content::ImagePart;
+use structured::{JsonSchema, StructuredOutput};
 
 // ── Message types ────────────────────────────────────────────────
 
@@ -12,24 +19,100 @@ use crate::retry::RetryPolicy;
 pub struct Message {
     pub role: String,
     pub content: String,
+    /// Images for vision models, sent along with `content`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImagePart>,
 }
 
 /// Configuration needed to make LLM requests.
 #[derive(Debug, Clone)]
 pub struct LlmConfig {
+    /// ID of the API config this was built from, used for usage accounting.
+    pub api_config_id: String,
     pub provider: ApiProvider,
     pub base_url: String,
     pub model_name: String,
     pub api_key: String,
+    /// Extra options used by the native Ollama path.
+    pub ollama: ollama::OllamaOptions,
+    /// Sampling / length parameters sent with every request.
+    pub generation: GenerationParams,
+}
+
+/// Per-config generation parameters. Unset fields use the provider's default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GenerationParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+}
+
+impl GenerationParams {
+    /// Anthropic requires `max_tokens`; this is used when none is configured.
+    pub const ANTHROPIC_DEFAULT_MAX_TOKENS: u32 = 4096;
+
+    pub fn with_temperature(temperature: f32) -> Self {
+        Self {
+            temperature: Some(temperature),
+            ..Self::default()
+        }
+    }
+
+    /// Fill every unset field from `defaults`; explicitly configured values win.
+    pub fn or(self, defaults: GenerationParams) -> Self {
+        Self {
+            temperature: self.temperature.or(defaults.temperature),
+            max_tokens: self.max_tokens.or(defaults.max_tokens),
+            top_p: self.top_p.or(defaults.top_p),
+            stop: self.stop.or(defaults.stop),
+        }
+    }
+}
+
+impl LlmConfig {
+    /// Build an LLM config from a stored API config and its resolved key.
+    pub fn from_api_config(config: ApiConfig, api_key: String) -> Self {
+        Self {
+            api_config_id: config.id,
+            provider: config.provider,
+            base_url: config.base_url,
+            model_name: config.model_name,
+            api_key,
+            ollama: config.ollama_options.unwrap_or_default(),
+            generation: config.generation_params.unwrap_or_default(),
+        }
+    }
 }
 
 /// A chunk emitted during streaming.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamChunk {
     #[serde(rename = "type")]
-    pub chunk_type: String,
+    pub chunk_type: String, // "start" | "delta" | "retrying" | "fallback" | "done" | "error"
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    /// Retry attempt number (1-based), set on `retrying` chunks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempt: Option<u32>,
+    /// Model that took over (`fallback`) or that produced the answer (`done`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl StreamChunk {
+    fn new(chunk_type: &str, content: Option<String>) -> Self {
+        Self {
+            chunk_type: chunk_type.into(),
+            content,
+            attempt: None,
+            model: None,
+        }
+    }
 }
 
 // ── OpenAI-compatible request/response types ─────────────────────
@@ -37,14 +120,71 @@ pub struct StreamChunk {
 #[derive(Serialize)]
 struct ChatRequest {
     model: String,
+    #[serde(serialize_with = "content::openai_messages")]
     messages: Vec<ChatMessage>,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
+/// Asks OpenAI to append a final chunk with token usage. Only sent to
+/// OpenAI itself: some compatible servers reject unknown fields with a 400.
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+impl ChatRequest {
+    fn new(config: &LlmConfig, messages: &[ChatMessage], stream: bool) -> Self {
+        let params = config.generation.clone();
+        Self {
+            model: config.model_name.clone(),
+            messages: messages.to_vec(),
+            stream,
+            temperature: params.temperature,
+            max_tokens: params.max_tokens,
+            top_p: params.top_p,
+            stop: params.stop,
+            stream_options: (stream && config.provider == ApiProvider::Openai)
+                .then_some(StreamOptions { include_usage: true }),
+            response_format: None,
+        }
+    }
+
+    fn with_json_schema(mut self, schema: Option<&JsonSchema>) -> Self {
+        self.response_format = schema.map(JsonSchema::openai_response_format);
+        self
+    }
+}
+
+/// A message on its way to a provider. Serializes in Ollama's shape; the
+/// OpenAI and Anthropic requests use their own (see `content`).
 #[derive(Clone, Serialize)]
 struct ChatMessage {
     role: String,
     content: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "content::ollama_images")]
+    images: Vec<ImagePart>,
+}
+
+impl From<&Message> for ChatMessage {
+    fn from(m: &Message) -> Self {
+        Self {
+            role: m.role.clone(),
+            content: m.content.clone(),
+            images: m.images.clone(),
+        }
+    }
 }
 
 // Anthropic uses a different request format
@@ -52,13 +192,102 @@ struct ChatMessage {
 struct AnthropicRequest {
     model: String,
     max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    #[serde(serialize_with = "content::anthropic_messages")]
     messages: Vec<ChatMessage>,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<structured::AnthropicTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+}
+
+impl AnthropicRequest {
+    fn new(config: &LlmConfig, messages: &[ChatMessage], stream: bool) -> Self {
+        let params = config.generation.clone();
+        let (system, messages) = Self::split_system(messages);
+        Self {
+            model: config.model_name.clone(),
+            max_tokens: params
+                .max_tokens
+                .unwrap_or(GenerationParams::ANTHROPIC_DEFAULT_MAX_TOKENS),
+            system,
+            messages,
+            stream,
+            temperature: params.temperature,
+            top_p: params.top_p,
+            stop_sequences: params.stop,
+            tools: None,
+            tool_choice: None,
+        }
+    }
+
+    /// JSON mode on Anthropic: force a call to a tool whose input is the object.
+    fn with_json_schema(mut self, schema: Option<&JsonSchema>) -> Self {
+        if let Some(schema) = schema {
+            self.tools = Some(vec![schema.anthropic_tool()]);
+            self.tool_choice = Some(schema.anthropic_tool_choice());
+        }
+        self
+    }
+
+    /// The Messages API only accepts `user`/`assistant` turns, strictly
+    /// alternating. Lift every `system` message into the top-level `system`
+    /// field and merge consecutive turns of the same role.
+    fn split_system(messages: &[ChatMessage]) -> (Option<String>, Vec<ChatMessage>) {
+        let mut system_parts: Vec<&str> = Vec::new();
+        let mut turns: Vec<ChatMessage> = Vec::new();
+
+        for m in messages {
+            if m.role == "system" {
+                system_parts.push(&m.content);
+                continue;
+            }
+            match turns.last_mut() {
+                Some(last) if last.role == m.role => {
+                    last.content.push_str("\n\n");
+                    last.content.push_str(&m.content);
+                    last.images.extend(m.images.iter().cloned());
+                }
+                _ => turns.push(m.clone()),
+            }
+        }
+
+        let system = (!system_parts.is_empty()).then(|| system_parts.join("\n\n"));
+        (system, turns)
+    }
 }
 
 #[derive(Deserialize)]
 struct ChatCompletionResponse {
     choices: Vec<ChatChoice>,
+    #[serde(default)]
+    usage: Option<OpenAiUsage>,
+}
+
+/// `usage` object of OpenAI-compatible responses (and the final stream chunk).
+#[derive(Deserialize)]
+struct OpenAiUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+impl From<OpenAiUsage> for TokenUsage {
+    fn from(u: OpenAiUsage) -> Self {
+        TokenUsage {
+            input_tokens: u.prompt_tokens,
+            output_tokens: u.completion_tokens,
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -75,113 +304,270 @@ struct ChatChoiceMessage {
 #[derive(Deserialize)]
 struct AnthropicResponse {
     content: Vec<AnthropicContent>,
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
 }
 
+#[derive(Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+impl From<AnthropicUsage> for TokenUsage {
+    fn from(u: AnthropicUsage) -> Self {
+        TokenUsage {
+            input_tokens: u.input_tokens,
+            output_tokens: u.output_tokens,
+        }
+    }
+}
+
+/// A content block: `text`, or `tool_use` (whose `input` is the JSON-mode object).
 #[derive(Deserialize)]
 struct AnthropicContent {
-    text: String,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    input: Option<serde_json::Value>,
 }
 
 // ── LlmClient ────────────────────────────────────────────────────
 
 pub struct LlmClient {
-    config: LlmConfig,
+    /// The primary config followed by its fallbacks, in the order they are tried.
+    configs: Vec<LlmConfig>,
     http_client: reqwest::Client,
     retry_policy: RetryPolicy,
+    usage_recorder: Option<UsageRecorder>,
 }
 
 impl LlmClient {
+    /// Apply task-specific generation defaults (e.g. a low temperature for OCR
+    /// correction). Parameters set on the API config take precedence.
+    pub fn with_task_defaults(mut self, defaults: GenerationParams) -> Self {
+        for config in &mut self.configs {
+            config.generation = config.generation.clone().or(defaults.clone());
+        }
+        self
+    }
+
+    /// Configs to try, in order, when the primary one fails for a reason
+    /// other than authentication (e.g. insufficient balance or an outage).
+    pub fn with_fallbacks(mut self, fallbacks: Vec<LlmConfig>) -> Self {
+        self.configs.extend(fallbacks);
+        self
+    }
+
+    /// Record token usage of every successful request.
+    pub fn with_usage_recorder(mut self, recorder: UsageRecorder) -> Self {
+        self.usage_recorder = Some(recorder);
+        self
+    }
+
     pub fn new(config: LlmConfig, retry_policy: RetryPolicy) -> Self {
         let http_client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(60))
             .build()
             .unwrap_or_default();
         Self {
-            config,
+            configs: vec![config],
             http_client,
             retry_policy,
+            usage_recorder: None,
         }
     }
 
+    /// Build a client that tries `chain` in order. Returns None for an empty chain.
+    pub fn from_chain(mut chain: Vec<LlmConfig>, retry_policy: RetryPolicy) -> Option<Self> {
+        if chain.is_empty() {
+            return None;
+        }
+        let primary = chain.remove(0);
+        Some(Self::new(primary, retry_policy).with_fallbacks(chain))
+    }
+
+    /// The config tried first.
+    pub fn primary_config(&self) -> &LlmConfig {
+        &self.configs[0]
+    }
+
     /// Non-streaming chat: returns the full response text.
+    ///
+    /// Each config is retried per the retry policy before falling back to the next.
     pub async fn chat(&self, messages: Vec<Message>) -> Result<String, VeyaError> {
-        let config = self.config.clone();
-        let client = self.http_client.clone();
-        let msgs = messages.clone();
+        let (text, _) = self.chat_formatted(&messages, None).await?;
+        Ok(text)
+    }
 
-        self.retry_policy
-            .execute(|| {
-                let config = config.clone();
-                let client = client.clone();
-                let msgs = msgs.clone();
-                async move { Self::chat_once(&config, &client, &msgs).await }
-            })
-            .await
+    /// JSON-mode chat: asks for an object matching `T::json_schema()` and
+    /// parses it into `T`, along with the name of the model that answered.
+    ///
+    /// Malformed or invalid replies are re-requested up to
+    /// `MAX_STRUCTURED_REPAIRS` times, telling the model what was wrong.
+    pub async fn chat_structured<T: StructuredOutput>(
+        &self,
+        mut messages: Vec<Message>,
+    ) -> Result<(T, String), VeyaError> {
+        let schema = T::json_schema();
+        let mut repairs = 0;
+        loop {
+            let (raw, config) = self.chat_formatted(&messages, Some(&schema)).await?;
+            match structured::parse_output::<T>(&raw) {
+                Ok(value) => return Ok((value, config.model_name.clone())),
+                Err(e) if repairs < Self::MAX_STRUCTURED_REPAIRS => {
+                    log::warn!("Model {} returned unusable JSON, re-requesting: {e}", config.model_name);
+                    messages.push(Message { role: "assistant".into(), content: raw, images: Vec::new() });
+                    messages.push(Message {
+                        role: "user".into(),
+                        content: structured::repair_prompt(&e),
+                        images: Vec::new(),
+                    });
+                    repairs += 1;
+                }
+                Err(e) => {
+                    return Err(VeyaError::ModelUnavailable(format!(
+                        "Model returned invalid structured output: {e}"
+                    )))
+                }
+            }
+        }
     }
 
-    /// Streaming chat: emits StreamChunk events via Tauri Event system.
-    pub async fn stream_chat(
+    /// Streaming chat: reports StreamChunk values (start/delta/done/error) to `on_chunk`.
+    ///
+    /// When a config fails, a `fallback` chunk names the model that takes over;
+    /// as with `retrying`, partial output received so far should be discarded.
+    /// The `done` chunk names the model that answered.
+    ///
+    /// The HTTP stream is tied to the returned future, so dropping the future
+    /// (e.g. via `futures_util::future::abortable`) aborts the request.
+    pub async fn stream_chat<F>(
         &self,
         messages: Vec<Message>,
-        app: &AppHandle,
-        event_name: &str,
-    ) -> Result<(), VeyaError> {
-        // Emit start
-        let _ = app.emit(
-            event_name,
-            StreamChunk {
-                chunk_type: "start".into(),
-                content: None,
-            },
-        );
+        mut on_chunk: F,
+    ) -> Result<(), VeyaError>
+    where
+        F: FnMut(StreamChunk) + Send,
+    {
+        on_chunk(StreamChunk::new("start", None));
 
-        let result = self.stream_chat_inner(messages, app, event_name).await;
-
-        match &result {
-            Ok(()) => {
-                let _ = app.emit(
-                    event_name,
-                    StreamChunk {
-                        chunk_type: "done".into(),
-                        content: None,
-                    },
-                );
+        let mut index = 0;
+        loop {
+            let config = &self.configs[index];
+            match self.stream_chat_inner(config, &messages, &mut on_chunk).await {
+                Ok(usage) => {
+                    self.record_usage(config, &usage);
+                    on_chunk(StreamChunk {
+                        model: Some(config.model_name.clone()),
+                        ..StreamChunk::new("done", None)
+                    });
+                    return Ok(());
+                }
+                Err(e) if Self::should_fall_back(&e) && index + 1 < self.configs.len() => {
+                    let next = &self.configs[index + 1];
+                    log::warn!(
+                        "Model {} failed, falling back to {}: {e}",
+                        config.model_name,
+                        next.model_name
+                    );
+                    on_chunk(StreamChunk {
+                        model: Some(next.model_name.clone()),
+                        ..StreamChunk::new("fallback", Some(e.to_string()))
+                    });
+                    index += 1;
+                }
+                Err(e) => {
+                    on_chunk(StreamChunk::new("error", Some(e.to_string())));
+                    return Err(e);
+                }
             }
-            Err(e) => {
-                let _ = app.emit(
-                    event_name,
-                    StreamChunk {
-                        chunk_type: "error".into(),
-                        content: Some(e.to_string()),
-                    },
-                );
+        }
+    }
+
+    // ── Internal helpers ──────────────────────────────────────────
+
+    const MAX_STRUCTURED_REPAIRS: u32 = 2;
+
+    /// Non-streaming chat along the fallback chain, optionally in JSON mode.
+    /// Returns the reply and the config that produced it.
+    async fn chat_formatted(
+        &self,
+        messages: &[Message],
+        schema: Option<&JsonSchema>,
+    ) -> Result<(String, &LlmConfig), VeyaError> {
+        let mut index = 0;
+        loop {
+            let config = &self.configs[index];
+            match self.chat_with(config, messages, schema).await {
+                Ok((text, usage)) => {
+                    self.record_usage(config, &usage);
+                    return Ok((text, config));
+                }
+                Err(e) if Self::should_fall_back(&e) && index + 1 < self.configs.len() => {
+                    log::warn!(
+                        "Model {} failed, falling back to {}: {e}",
+                        config.model_name,
+                        self.configs[index + 1].model_name
+                    );
+                    index += 1;
+                }
+                Err(e) => return Err(e),
             }
         }
+    }
 
-        result
+    /// Authentication failures need the user's attention, so they are reported
+    /// instead of silently switching to another provider.
+    fn should_fall_back(error: &VeyaError) -> bool {
+        !matches!(error, VeyaError::InvalidApiKey(_))
     }
 
-    // ── Internal helpers ──────────────────────────────────────────
+    fn record_usage(&self, config: &LlmConfig, usage: &TokenUsage) {
+        if let Some(recorder) = &self.usage_recorder {
+            recorder.record_llm(&config.api_config_id, &config.model_name, usage);
+        }
+    }
+
+    async fn chat_with(
+        &self,
+        config: &LlmConfig,
+        messages: &[Message],
+        schema: Option<&JsonSchema>,
+    ) -> Result<(String, TokenUsage), VeyaError> {
+        let config = config.clone();
+        let client = self.http_client.clone();
+        let msgs = messages.to_vec();
+
+        self.retry_policy
+            .execute(|| {
+                let config = config.clone();
+                let client = client.clone();
+                let msgs = msgs.clone();
+                async move { Self::chat_once(&config, &client, &msgs, schema).await }
+            })
+            .await
+    }
 
     async fn chat_once(
         config: &LlmConfig,
         client: &reqwest::Client,
         messages: &[Message],
-    ) -> Result<String, VeyaError> {
-        let chat_messages: Vec<ChatMessage> = messages
-            .iter()
-            .map(|m| ChatMessage {
-                role: m.role.clone(),
-                content: m.content.clone(),
-            })
-            .collect();
+        schema: Option<&JsonSchema>,
+    ) -> Result<(String, TokenUsage), VeyaError> {
+        let chat_messages: Vec<ChatMessage> = messages.iter().map(ChatMessage::from).collect();
 
         match config.provider {
             ApiProvider::Anthropic => {
-                Self::chat_once_anthropic(config, client, &chat_messages).await
+                Self::chat_once_anthropic(config, client, &chat_messages, schema).await
             }
-            // OpenAI, Ollama, ElevenLabs, Custom all use OpenAI-compatible format
-            _ => Self::chat_once_openai(config, client, &chat_messages).await,
+            ApiProvider::Ollama => {
+                Self::chat_once_ollama(config, client, &chat_messages, schema).await
+            }
+            // OpenAI, ElevenLabs, Custom all use OpenAI-compatible format
+            _ => Self::chat_once_openai(config, client, &chat_messages, schema).await,
         }
     }
 
@@ -189,16 +575,13 @@ impl LlmClient {
         config: &LlmConfig,
         client: &reqwest::Client,
         messages: &[ChatMessage],
-    ) -> Result<String, VeyaError> {
+        schema: Option<&JsonSchema>,
+    ) -> Result<(String, TokenUsage), VeyaError> {
         let url = format!(
             "{}/chat/completions",
             config.base_url.trim_end_matches('/')
         );
-        let body = ChatRequest {
-            model: config.model_name.clone(),
-            messages: messages.to_vec(),
-            stream: false,
-        };
+        let body = ChatRequest::new(config, messages, false).with_json_schema(schema);
 
         let mut req = client.post(&url).json(&body);
         if !config.api_key.is_empty() {
@@ -217,27 +600,25 @@ impl LlmClient {
             .await
             .map_err(|e| VeyaError::ModelUnavailable(format!("Invalid response: {e}")))?;
 
-        data.choices
+        let text = data
+            .choices
             .first()
             .map(|c| c.message.content.clone())
-            .ok_or_else(|| VeyaError::ModelUnavailable("Empty response from model".into()))
+            .ok_or_else(|| VeyaError::ModelUnavailable("Empty response from model".into()))?;
+        Ok((text, data.usage.map(TokenUsage::from).unwrap_or_default()))
     }
 
     async fn chat_once_anthropic(
         config: &LlmConfig,
         client: &reqwest::Client,
         messages: &[ChatMessage],
-    ) -> Result<String, VeyaError> {
+        schema: Option<&JsonSchema>,
+    ) -> Result<(String, TokenUsage), VeyaError> {
         let url = format!(
             "{}/messages",
             config.base_url.trim_end_matches('/')
         );
-        let body = AnthropicRequest {
-            model: config.model_name.clone(),
-            max_tokens: 4096,
-            messages: messages.to_vec(),
-            stream: false,
-        };
+        let body = AnthropicRequest::new(config, messages, false).with_json_schema(schema);
 
         let resp = client
             .post(&url)
@@ -259,58 +640,129 @@ impl LlmClient {
             .await
             .map_err(|e| VeyaError::ModelUnavailable(format!("Invalid Anthropic response: {e}")))?;
 
-        data.content
-            .first()
-            .map(|c| c.text.clone())
-            .ok_or_else(|| VeyaError::ModelUnavailable("Empty Anthropic response".into()))
+        // In JSON mode the object arrives as the input of the forced tool call.
+        let block = match schema {
+            Some(_) => data.content.iter().find(|c| c.input.is_some()),
+            None => data.content.iter().find(|c| c.text.is_some()),
+        };
+        let text = block
+            .and_then(|c| c.input.as_ref().map(|i| i.to_string()).or_else(|| c.text.clone()))
+            .ok_or_else(|| VeyaError::ModelUnavailable("Empty Anthropic response".into()))?;
+        Ok((text, data.usage.map(TokenUsage::from).unwrap_or_default()))
+    }
+
+    async fn chat_once_ollama(
+        config: &LlmConfig,
+        client: &reqwest::Client,
+        messages: &[ChatMessage],
+        schema: Option<&JsonSchema>,
+    ) -> Result<(String, TokenUsage), VeyaError> {
+        let url = ollama::api_url(&config.base_url, "chat");
+        let mut body = Self::ollama_request(config, messages, false);
+        body.format = schema.map(|s| s.schema.clone());
+
+        let resp = client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(Self::classify_reqwest_error)?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(Self::classify_http_status(status.as_u16(), &resp.text().await.unwrap_or_default()));
+        }
+
+        let text = resp
+            .text()
+            .await
+            .map_err(|e| VeyaError::ModelUnavailable(format!("Invalid Ollama response: {e}")))?;
+
+        let data = ollama::OllamaChatResponse::parse_line(&text)?;
+        let usage = data.token_usage();
+        let text = data
+            .message
+            .map(|m| m.content)
+            .ok_or_else(|| VeyaError::ModelUnavailable("Empty Ollama response".into()))?;
+        Ok((text, usage))
+    }
+
+    fn ollama_request(
+        config: &LlmConfig,
+        messages: &[ChatMessage],
+        stream: bool,
+    ) -> ollama::OllamaChatRequest<ChatMessage> {
+        ollama::OllamaChatRequest {
+            model: config.model_name.clone(),
+            messages: messages.to_vec(),
+            stream,
+            options: ollama::OllamaModelOptions {
+                num_ctx: config.ollama.num_ctx,
+                temperature: config.generation.temperature,
+                top_p: config.generation.top_p,
+                num_predict: config.generation.max_tokens,
+                stop: config.generation.stop.clone(),
+            },
+            keep_alive: config.ollama.keep_alive.as_deref().map(ollama::keep_alive_value),
+            format: None,
+        }
     }
 
     /// Internal streaming implementation (without start/done envelope).
+    ///
+    /// Retryable failures restart the whole stream, whether they happen before
+    /// the first delta or mid-way. Each restart is announced with a `retrying`
+    /// chunk so consumers can discard any partial output received so far.
     async fn stream_chat_inner(
         &self,
-        messages: Vec<Message>,
-        app: &AppHandle,
-        event_name: &str,
-    ) -> Result<(), VeyaError> {
-        let chat_messages: Vec<ChatMessage> = messages
-            .iter()
-            .map(|m| ChatMessage {
-                role: m.role.clone(),
-                content: m.content.clone(),
-            })
-            .collect();
+        config: &LlmConfig,
+        messages: &[Message],
+        on_chunk: &mut (dyn FnMut(StreamChunk) + Send),
+    ) -> Result<TokenUsage, VeyaError> {
+        let chat_messages: Vec<ChatMessage> = messages.iter().map(ChatMessage::from).collect();
 
-        match self.config.provider {
-            ApiProvider::Anthropic => {
-                self.stream_anthropic(&chat_messages, app, event_name).await
-            }
-            _ => {
-                self.stream_openai(&chat_messages, app, event_name).await
+        let mut attempt = 0;
+        loop {
+            let result = match config.provider {
+                ApiProvider::Anthropic => {
+                    self.stream_anthropic(config, &chat_messages, on_chunk).await
+                }
+                ApiProvider::Ollama => self.stream_ollama(config, &chat_messages, on_chunk).await,
+                _ => {
+                    self.stream_openai(config, &chat_messages, on_chunk).await
+                }
+            };
+
+            match result {
+                Err(e) if e.is_retryable() && attempt < self.retry_policy.max_retries => {
+                    log::warn!("LLM stream failed (attempt {}): {e}", attempt + 1);
+                    on_chunk(StreamChunk {
+                        attempt: Some(attempt + 1),
+                        ..StreamChunk::new("retrying", Some(e.to_string()))
+                    });
+                    tokio::time::sleep(self.retry_policy.delay_for(attempt)).await;
+                    attempt += 1;
+                }
+                other => return other,
             }
         }
     }
 
     async fn stream_openai(
         &self,
+        config: &LlmConfig,
         messages: &[ChatMessage],
-        app: &AppHandle,
-        event_name: &str,
-    ) -> Result<(), VeyaError> {
-        use futures_util::StreamExt;
-
+        on_chunk: &mut (dyn FnMut(StreamChunk) + Send),
+    ) -> Result<TokenUsage, VeyaError> {
         let url = format!(
             "{}/chat/completions",
-            self.config.base_url.trim_end_matches('/')
+            config.base_url.trim_end_matches('/')
         );
-        let body = ChatRequest {
-            model: self.config.model_name.clone(),
-            messages: messages.to_vec(),
-            stream: true,
-        };
+        let body = ChatRequest::new(config, messages, true);
 
         let mut req = self.http_client.post(&url).json(&body);
-        if !self.config.api_key.is_empty() {
-            req = req.header("Authorization", format!("Bearer {}", self.config.api_key));
+        if !config.api_key.is_empty() {
+            req = req.header("Authorization", format!("Bearer {}", config.api_key));
         }
 
         let resp = req.send().await.map_err(|e| Self::classify_reqwest_error(e))?;
@@ -319,63 +771,40 @@ impl LlmClient {
             return Err(Self::classify_http_status(status.as_u16(), &resp.text().await.unwrap_or_default()));
         }
 
-        let mut stream = resp.bytes_stream();
-        let mut buffer = String::new();
-
-        while let Some(chunk) = stream.next().await {
-            let bytes = chunk.map_err(|e| VeyaError::NetworkTimeout(format!("Stream error: {e}")))?;
-            buffer.push_str(&String::from_utf8_lossy(&bytes));
-
-            // Process complete SSE lines
-            while let Some(pos) = buffer.find("\n\n") {
-                let event_block = buffer[..pos].to_string();
-                buffer = buffer[pos + 2..].to_string();
-
-                for line in event_block.lines() {
-                    if let Some(data) = line.strip_prefix("data: ") {
-                        if data.trim() == "[DONE]" {
-                            return Ok(());
-                        }
-                        if let Some(content) = Self::parse_openai_sse_delta(data) {
-                            let _ = app.emit(
-                                event_name,
-                                StreamChunk {
-                                    chunk_type: "delta".into(),
-                                    content: Some(content),
-                                },
-                            );
-                        }
-                    }
-                }
+        let mut usage = TokenUsage::default();
+        Self::read_sse(resp, |event| {
+            if event.is_done_marker() {
+                return ControlFlow::Break(());
             }
-        }
+            if let Some(content) = Self::parse_openai_sse_delta(&event.data) {
+                on_chunk(StreamChunk::new("delta", Some(content)));
+            }
+            if let Some(u) = Self::parse_openai_sse_usage(&event.data) {
+                usage = u;
+            }
+            ControlFlow::Continue(())
+        })
+        .await?;
 
-        Ok(())
+        Ok(usage)
     }
 
     async fn stream_anthropic(
         &self,
+        config: &LlmConfig,
         messages: &[ChatMessage],
-        app: &AppHandle,
-        event_name: &str,
-    ) -> Result<(), VeyaError> {
-        use futures_util::StreamExt;
-
+        on_chunk: &mut (dyn FnMut(StreamChunk) + Send),
+    ) -> Result<TokenUsage, VeyaError> {
         let url = format!(
             "{}/messages",
-            self.config.base_url.trim_end_matches('/')
+            config.base_url.trim_end_matches('/')
         );
-        let body = AnthropicRequest {
-            model: self.config.model_name.clone(),
-            max_tokens: 4096,
-            messages: messages.to_vec(),
-            stream: true,
-        };
+        let body = AnthropicRequest::new(config, messages, true);
 
         let resp = self
             .http_client
             .post(&url)
-            .header("x-api-key", &self.config.api_key)
+            .header("x-api-key", &config.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("content-type", "application/json")
             .json(&body)
@@ -388,33 +817,107 @@ impl LlmClient {
             return Err(Self::classify_http_status(status.as_u16(), &resp.text().await.unwrap_or_default()));
         }
 
+        let mut usage = TokenUsage::default();
+        Self::read_sse(resp, |event| {
+            if event.event.as_deref() == Some("message_stop") {
+                return ControlFlow::Break(());
+            }
+            if let Some(content) = Self::parse_anthropic_sse_delta(&event.data) {
+                on_chunk(StreamChunk::new("delta", Some(content)));
+            }
+            Self::update_anthropic_sse_usage(&event.data, &mut usage);
+            ControlFlow::Continue(())
+        })
+        .await?;
+
+        Ok(usage)
+    }
+
+    /// Stream from Ollama's native `/api/chat`, which emits one JSON object per line.
+    async fn stream_ollama(
+        &self,
+        config: &LlmConfig,
+        messages: &[ChatMessage],
+        on_chunk: &mut (dyn FnMut(StreamChunk) + Send),
+    ) -> Result<TokenUsage, VeyaError> {
+        use futures_util::StreamExt;
+
+        let url = ollama::api_url(&config.base_url, "chat");
+        let body = Self::ollama_request(config, messages, true);
+
+        let resp = self
+            .http_client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(Self::classify_reqwest_error)?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(Self::classify_http_status(status.as_u16(), &resp.text().await.unwrap_or_default()));
+        }
+
+        let mut stream = resp.bytes_stream();
+        let mut decoder = ollama::NdjsonDecoder::new();
+        // Returns the token usage once the final (`done`) line arrives.
+        let mut handle_line = |line: &str| -> Result<Option<TokenUsage>, VeyaError> {
+            let resp = ollama::OllamaChatResponse::parse_line(line)?;
+            let usage = resp.done.then(|| resp.token_usage());
+            if let Some(message) = resp.message.filter(|m| !m.content.is_empty()) {
+                on_chunk(StreamChunk::new("delta", Some(message.content)));
+            }
+            Ok(usage)
+        };
+
+        while let Some(chunk) = stream.next().await {
+            let bytes = chunk.map_err(|e| VeyaError::NetworkTimeout(format!("Stream error: {e}")))?;
+            for line in decoder.push(&bytes) {
+                if let Some(usage) = handle_line(&line)? {
+                    return Ok(usage);
+                }
+            }
+        }
+        if let Some(line) = decoder.finish() {
+            if let Some(usage) = handle_line(&line)? {
+                return Ok(usage);
+            }
+        }
+
+        Ok(TokenUsage::default())
+    }
+
+    /// Drive an SSE response body through `sse::SseDecoder`, handing each event
+    /// to `on_event` until it breaks or the stream ends. In-stream error events
+    /// are turned into `VeyaError`s.
+    async fn read_sse<F>(resp: reqwest::Response, mut on_event: F) -> Result<(), VeyaError>
+    where
+        F: FnMut(&sse::SseEvent) -> ControlFlow<()>,
+    {
+        use futures_util::StreamExt;
+
         let mut stream = resp.bytes_stream();
-        let mut buffer = String::new();
+        let mut decoder = sse::SseDecoder::new();
 
         while let Some(chunk) = stream.next().await {
             let bytes = chunk.map_err(|e| VeyaError::NetworkTimeout(format!("Stream error: {e}")))?;
-            buffer.push_str(&String::from_utf8_lossy(&bytes));
-
-            while let Some(pos) = buffer.find("\n\n") {
-                let event_block = buffer[..pos].to_string();
-                buffer = buffer[pos + 2..].to_string();
-
-                for line in event_block.lines() {
-                    if let Some(data) = line.strip_prefix("data: ") {
-                        if let Some(content) = Self::parse_anthropic_sse_delta(data) {
-                            let _ = app.emit(
-                                event_name,
-                                StreamChunk {
-                                    chunk_type: "delta".into(),
-                                    content: Some(content),
-                                },
-                            );
-                        }
-                    }
+            for event in decoder.push(&bytes) {
+                if let Some(e) = sse::error_from_event(&event) {
+                    return Err(e);
+                }
+                if on_event(&event).is_break() {
+                    return Ok(());
                 }
             }
         }
 
+        if let Some(event) = decoder.finish() {
+            if let Some(e) = sse::error_from_event(&event) {
+                return Err(e);
+            }
+            let _ = on_event(&event);
+        }
+
         Ok(())
     }
 
@@ -430,6 +933,35 @@ impl LlmClient {
             .map(|s| s.to_string())
     }
 
+    /// The final chunk of a stream requested with `include_usage` carries `usage`.
+    fn parse_openai_sse_usage(data: &str) -> Option<TokenUsage> {
+        let v: serde_json::Value = serde_json::from_str(data).ok()?;
+        let usage: OpenAiUsage = serde_json::from_value(v.get("usage")?.clone()).ok()?;
+        Some(usage.into())
+    }
+
+    /// Anthropic reports input tokens in `message_start` and the cumulative
+    /// output token count in `message_delta`.
+    fn update_anthropic_sse_usage(data: &str, usage: &mut TokenUsage) {
+        let Ok(v) = serde_json::from_str::<serde_json::Value>(data) else {
+            return;
+        };
+        let reported = match v.get("type").and_then(|t| t.as_str()) {
+            Some("message_start") => v.get("message").and_then(|m| m.get("usage")),
+            Some("message_delta") => v.get("usage"),
+            _ => None,
+        };
+        let Some(reported) = reported else {
+            return;
+        };
+        if let Some(input) = reported.get("input_tokens").and_then(|n| n.as_u64()) {
+            usage.input_tokens = input;
+        }
+        if let Some(output) = reported.get("output_tokens").and_then(|n| n.as_u64()) {
+            usage.output_tokens = output;
+        }
+    }
+
     fn parse_anthropic_sse_delta(data: &str) -> Option<String> {
         let v: serde_json::Value = serde_json::from_str(data).ok()?;
         // Anthropic SSE: event type "content_block_delta" has delta.text
@@ -470,3 +1002,261 @@ impl LlmClient {
         }
     }
 }
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config(provider: ApiProvider, generation: GenerationParams) -> LlmConfig {
+        LlmConfig {
+            api_config_id: String::new(),
+            provider,
+            base_url: "http://localhost".into(),
+            model_name: "test-model".into(),
+            api_key: String::new(),
+            ollama: ollama::OllamaOptions::default(),
+            generation,
+        }
+    }
+
+    fn chat_message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage { role: role.into(), content: content.into(), images: Vec::new() }
+    }
+
+    fn user_message(content: &str) -> Vec<ChatMessage> {
+        vec![chat_message("user", content)]
+    }
+
+    /// A user turn with text and a one-byte "image" (base64 "AA==").
+    fn image_message() -> Vec<ChatMessage> {
+        let mut message = chat_message("user", "Transcribe this");
+        message.images.push(ImagePart::png(&[0]));
+        vec![message]
+    }
+
+    #[test]
+    fn generation_params_or_prefers_configured_values() {
+        let configured = GenerationParams { temperature: Some(0.5), ..Default::default() };
+        let merged = configured.or(GenerationParams {
+            temperature: Some(0.1),
+            max_tokens: Some(256),
+            ..Default::default()
+        });
+        assert_eq!(merged.temperature, Some(0.5));
+        assert_eq!(merged.max_tokens, Some(256));
+        assert_eq!(merged.top_p, None);
+    }
+
+    #[test]
+    fn openai_request_omits_unset_params() {
+        let config = test_config(ApiProvider::Openai, GenerationParams::default());
+        let json = serde_json::to_value(ChatRequest::new(&config, &user_message("hi"), true)).unwrap();
+        assert!(json.get("temperature").is_none());
+        assert!(json.get("stop").is_none());
+
+        let config = test_config(ApiProvider::Openai, GenerationParams {
+            temperature: Some(0.25),
+            stop: Some(vec!["END".into()]),
+            ..Default::default()
+        });
+        let json = serde_json::to_value(ChatRequest::new(&config, &user_message("hi"), true)).unwrap();
+        assert_eq!(json["temperature"], serde_json::json!(0.25));
+        assert_eq!(json["stop"], serde_json::json!(["END"]));
+    }
+
+    #[test]
+    fn anthropic_request_defaults_max_tokens_and_maps_stop() {
+        let config = test_config(ApiProvider::Anthropic, GenerationParams::default());
+        let json = serde_json::to_value(AnthropicRequest::new(&config, &user_message("hi"), false)).unwrap();
+        assert_eq!(json["max_tokens"], serde_json::json!(GenerationParams::ANTHROPIC_DEFAULT_MAX_TOKENS));
+
+        let config = test_config(ApiProvider::Anthropic, GenerationParams {
+            max_tokens: Some(512),
+            stop: Some(vec!["\n\n".into()]),
+            ..Default::default()
+        });
+        let json = serde_json::to_value(AnthropicRequest::new(&config, &user_message("hi"), false)).unwrap();
+        assert_eq!(json["max_tokens"], serde_json::json!(512));
+        assert_eq!(json["stop_sequences"], serde_json::json!(["\n\n"]));
+    }
+
+    #[test]
+    fn anthropic_request_lifts_system_and_merges_turns() {
+        let config = test_config(ApiProvider::Anthropic, GenerationParams::default());
+        let messages = vec![
+            chat_message("system", "Be brief."),
+            chat_message("user", "Hello"),
+            chat_message("user", "World"),
+            chat_message("assistant", "Hi"),
+            chat_message("system", "Use English."),
+            chat_message("user", "Again"),
+        ];
+        let req = AnthropicRequest::new(&config, &messages, false);
+        assert_eq!(req.system.as_deref(), Some("Be brief.\n\nUse English."));
+        let roles: Vec<_> = req.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["user", "assistant", "user"]);
+        assert_eq!(req.messages[0].content, "Hello\n\nWorld");
+    }
+
+    #[test]
+    fn text_only_messages_keep_plain_string_content() {
+        let config = test_config(ApiProvider::Openai, GenerationParams::default());
+        let openai = serde_json::to_value(ChatRequest::new(&config, &user_message("hi"), false)).unwrap();
+        let anthropic = serde_json::to_value(AnthropicRequest::new(&config, &user_message("hi"), false)).unwrap();
+        let ollama = serde_json::to_value(LlmClient::ollama_request(&config, &user_message("hi"), false)).unwrap();
+        for json in [openai, anthropic, ollama] {
+            assert_eq!(json["messages"][0], serde_json::json!({ "role": "user", "content": "hi" }));
+        }
+    }
+
+    #[test]
+    fn images_use_each_providers_format() {
+        let config = test_config(ApiProvider::Openai, GenerationParams::default());
+
+        let json = serde_json::to_value(ChatRequest::new(&config, &image_message(), false)).unwrap();
+        assert_eq!(
+            json["messages"][0]["content"],
+            serde_json::json!([
+                { "type": "text", "text": "Transcribe this" },
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,AA==" } },
+            ])
+        );
+
+        let json = serde_json::to_value(AnthropicRequest::new(&config, &image_message(), false)).unwrap();
+        assert_eq!(
+            json["messages"][0]["content"],
+            serde_json::json!([
+                { "type": "image", "source": { "type": "base64", "media_type": "image/png", "data": "AA==" } },
+                { "type": "text", "text": "Transcribe this" },
+            ])
+        );
+
+        let json = serde_json::to_value(LlmClient::ollama_request(&config, &image_message(), false)).unwrap();
+        assert_eq!(
+            json["messages"][0],
+            serde_json::json!({ "role": "user", "content": "Transcribe this", "images": ["AA=="] })
+        );
+    }
+
+    #[test]
+    fn anthropic_merged_turns_keep_their_images() {
+        let config = test_config(ApiProvider::Anthropic, GenerationParams::default());
+        let mut messages = image_message();
+        messages.push(chat_message("user", "Also explain it"));
+        let req = AnthropicRequest::new(&config, &messages, false);
+        assert_eq!(req.messages.len(), 1);
+        assert_eq!(req.messages[0].images.len(), 1);
+        assert_eq!(req.messages[0].content, "Transcribe this\n\nAlso explain it");
+    }
+
+    #[test]
+    fn anthropic_request_without_system_omits_field() {
+        let config = test_config(ApiProvider::Anthropic, GenerationParams::default());
+        let json = serde_json::to_value(AnthropicRequest::new(&config, &user_message("hi"), true)).unwrap();
+        assert!(json.get("system").is_none());
+        assert_eq!(json["messages"][0]["role"], "user");
+    }
+
+    #[test]
+    fn ollama_request_maps_max_tokens_to_num_predict() {
+        let config = test_config(ApiProvider::Ollama, GenerationParams {
+            temperature: Some(0.5),
+            max_tokens: Some(128),
+            ..Default::default()
+        });
+        let json = serde_json::to_value(LlmClient::ollama_request(&config, &user_message("hi"), true)).unwrap();
+        assert_eq!(json["options"]["num_predict"], serde_json::json!(128));
+        assert_eq!(json["options"]["temperature"], serde_json::json!(0.5));
+    }
+
+    #[test]
+    fn openai_stream_request_asks_for_usage() {
+        let config = test_config(ApiProvider::Openai, GenerationParams::default());
+        let json = serde_json::to_value(ChatRequest::new(&config, &user_message("hi"), true)).unwrap();
+        assert_eq!(json["stream_options"]["include_usage"], serde_json::json!(true));
+        let json = serde_json::to_value(ChatRequest::new(&config, &user_message("hi"), false)).unwrap();
+        assert!(json.get("stream_options").is_none());
+    }
+
+    #[test]
+    fn compatible_stream_request_omits_stream_options() {
+        let config = test_config(ApiProvider::Custom, GenerationParams::default());
+        let json = serde_json::to_value(ChatRequest::new(&config, &user_message("hi"), true)).unwrap();
+        assert!(json.get("stream_options").is_none());
+    }
+
+    #[test]
+    fn parses_openai_usage_chunk() {
+        let data = r#"{"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":34,"total_tokens":46}}"#;
+        let usage = LlmClient::parse_openai_sse_usage(data).unwrap();
+        assert_eq!(usage, TokenUsage { input_tokens: 12, output_tokens: 34 });
+        assert!(LlmClient::parse_openai_sse_usage(r#"{"choices":[{"delta":{"content":"x"}}]}"#).is_none());
+    }
+
+    #[test]
+    fn accumulates_anthropic_stream_usage() {
+        let mut usage = TokenUsage::default();
+        LlmClient::update_anthropic_sse_usage(
+            r#"{"type":"message_start","message":{"usage":{"input_tokens":25,"output_tokens":1}}}"#,
+            &mut usage,
+        );
+        LlmClient::update_anthropic_sse_usage(
+            r#"{"type":"content_block_delta","delta":{"text":"hi"}}"#,
+            &mut usage,
+        );
+        LlmClient::update_anthropic_sse_usage(
+            r#"{"type":"message_delta","usage":{"output_tokens":15}}"#,
+            &mut usage,
+        );
+        assert_eq!(usage, TokenUsage { input_tokens: 25, output_tokens: 15 });
+    }
+
+    #[test]
+    fn json_schema_maps_to_each_provider() {
+        let schema = JsonSchema {
+            name: "analysis".into(),
+            description: String::new(),
+            schema: serde_json::json!({ "type": "object" }),
+        };
+
+        let config = test_config(ApiProvider::Openai, GenerationParams::default());
+        let json = serde_json::to_value(ChatRequest::new(&config, &user_message("hi"), false).with_json_schema(Some(&schema))).unwrap();
+        assert_eq!(json["response_format"]["json_schema"]["name"], "analysis");
+        let json = serde_json::to_value(ChatRequest::new(&config, &user_message("hi"), false).with_json_schema(None)).unwrap();
+        assert!(json.get("response_format").is_none());
+
+        let config = test_config(ApiProvider::Anthropic, GenerationParams::default());
+        let json = serde_json::to_value(AnthropicRequest::new(&config, &user_message("hi"), false).with_json_schema(Some(&schema))).unwrap();
+        assert_eq!(json["tools"][0]["input_schema"], schema.schema);
+        assert_eq!(json["tool_choice"], serde_json::json!({ "type": "tool", "name": "analysis" }));
+    }
+
+    #[test]
+    fn anthropic_tool_use_block_deserializes() {
+        let data: AnthropicResponse = serde_json::from_str(
+            r#"{"content":[{"type":"tool_use","id":"t1","name":"analysis","input":{"a":1}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(data.content[0].input, Some(serde_json::json!({ "a": 1 })));
+        assert!(data.content[0].text.is_none());
+    }
+
+    #[test]
+    fn falls_back_on_everything_but_auth_errors() {
+        assert!(LlmClient::should_fall_back(&VeyaError::InsufficientBalance("quota".into())));
+        assert!(LlmClient::should_fall_back(&VeyaError::ModelUnavailable("down".into())));
+        assert!(LlmClient::should_fall_back(&VeyaError::NetworkTimeout("timeout".into())));
+        assert!(!LlmClient::should_fall_back(&VeyaError::InvalidApiKey("bad key".into())));
+    }
+
+    #[test]
+    fn task_defaults_apply_to_fallbacks() {
+        let client = LlmClient::new(test_config(ApiProvider::Openai, GenerationParams::default()), RetryPolicy::new(0, 0, 0))
+            .with_fallbacks(vec![test_config(ApiProvider::Ollama, GenerationParams::with_temperature(0.5))])
+            .with_task_defaults(GenerationParams::with_temperature(0.25));
+        assert_eq!(client.configs[0].generation.temperature, Some(0.25));
+        assert_eq!(client.configs[1].generation.temperature, Some(0.5));
+    }
+}
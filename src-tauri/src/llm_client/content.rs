@@ -0,0 +1,133 @@
+//! Images attached to messages, and how each provider expects them.
+//!
+//! Text-only messages serialize exactly as before (`content` is a plain
+//! string everywhere). With images, OpenAI-compatible servers take a list
+//! of `text` / `image_url` parts with data URIs, Anthropic takes `image`
+//! blocks with base64 sources, and Ollama takes a bare `images` array next
+//! to the text.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize, Serializer};
+
+use super::ChatMessage;
+
+/// An image sent to a vision model.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImagePart {
+    /// MIME type, e.g. "image/png".
+    pub media_type: String,
+    /// Base64 of the image bytes, without a `data:` prefix.
+    pub data: String,
+}
+
+impl ImagePart {
+    pub fn png(bytes: &[u8]) -> Self {
+        Self {
+            media_type: "image/png".into(),
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+
+    fn data_uri(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.data)
+    }
+}
+
+// ── OpenAI-compatible ────────────────────────────────────────────
+
+#[derive(Serialize)]
+struct OpenAiMessage<'a> {
+    role: &'a str,
+    content: OpenAiContent<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum OpenAiContent<'a> {
+    Text(&'a str),
+    Parts(Vec<OpenAiPart<'a>>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OpenAiPart<'a> {
+    Text { text: &'a str },
+    ImageUrl { image_url: OpenAiImageUrl },
+}
+
+#[derive(Serialize)]
+struct OpenAiImageUrl {
+    url: String,
+}
+
+pub(super) fn openai_messages<S: Serializer>(messages: &[ChatMessage], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(messages.iter().map(|m| OpenAiMessage {
+        role: &m.role,
+        content: if m.images.is_empty() {
+            OpenAiContent::Text(&m.content)
+        } else {
+            let text = (!m.content.is_empty()).then_some(OpenAiPart::Text { text: &m.content });
+            let images = m.images.iter().map(|image| OpenAiPart::ImageUrl {
+                image_url: OpenAiImageUrl { url: image.data_uri() },
+            });
+            OpenAiContent::Parts(text.into_iter().chain(images).collect())
+        },
+    }))
+}
+
+// ── Anthropic ────────────────────────────────────────────────────
+
+#[derive(Serialize)]
+struct AnthropicMessage<'a> {
+    role: &'a str,
+    content: AnthropicContent<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum AnthropicContent<'a> {
+    Text(&'a str),
+    Blocks(Vec<AnthropicBlock<'a>>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicBlock<'a> {
+    Text { text: &'a str },
+    Image { source: AnthropicImageSource<'a> },
+}
+
+#[derive(Serialize)]
+struct AnthropicImageSource<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    media_type: &'a str,
+    data: &'a str,
+}
+
+/// Images go before the text, as Anthropic recommends.
+pub(super) fn anthropic_messages<S: Serializer>(messages: &[ChatMessage], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(messages.iter().map(|m| AnthropicMessage {
+        role: &m.role,
+        content: if m.images.is_empty() {
+            AnthropicContent::Text(&m.content)
+        } else {
+            let images = m.images.iter().map(|image| AnthropicBlock::Image {
+                source: AnthropicImageSource {
+                    kind: "base64",
+                    media_type: &image.media_type,
+                    data: &image.data,
+                },
+            });
+            let text = (!m.content.is_empty()).then_some(AnthropicBlock::Text { text: &m.content });
+            AnthropicContent::Blocks(images.chain(text).collect())
+        },
+    }))
+}
+
+// ── Ollama ───────────────────────────────────────────────────────
+
+/// Ollama's `images` field: base64 strings without MIME types.
+pub(super) fn ollama_images<S: Serializer>(images: &[ImagePart], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(images.iter().map(|image| &image.data))
+}
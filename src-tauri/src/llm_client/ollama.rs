@@ -0,0 +1,219 @@
+//! Wire types and NDJSON stream decoding for Ollama's native `/api/chat`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::VeyaError;
+use crate::usage::TokenUsage;
+
+/// Ollama-specific options stored per API config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct OllamaOptions {
+    /// Context window size in tokens (`options.num_ctx`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    /// How long the model stays loaded, e.g. "5m", "1h" or "-1" (forever).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+}
+
+/// Build a native API URL (`{root}/api/{endpoint}`) from a configured base URL.
+/// Accepts both the server root and the OpenAI-compatible `/v1` base.
+pub fn api_url(base_url: &str, endpoint: &str) -> String {
+    let root = base_url.trim_end_matches('/');
+    let root = root.strip_suffix("/v1").unwrap_or(root);
+    format!("{root}/api/{endpoint}")
+}
+
+// ── Request / response types ─────────────────────────────────────
+
+#[derive(Serialize)]
+pub(super) struct OllamaChatRequest<M: Serialize> {
+    pub model: String,
+    pub messages: Vec<M>,
+    pub stream: bool,
+    #[serde(skip_serializing_if = "OllamaModelOptions::is_empty")]
+    pub options: OllamaModelOptions,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<serde_json::Value>,
+    /// JSON schema the reply must follow (JSON mode).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<serde_json::Value>,
+}
+
+/// The `options` object of an Ollama request (model parameters).
+#[derive(Debug, Default, Serialize)]
+pub(super) struct OllamaModelOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Maximum number of tokens to generate (Ollama's name for `max_tokens`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+}
+
+impl OllamaModelOptions {
+    pub fn is_empty(&self) -> bool {
+        self.num_ctx.is_none()
+            && self.temperature.is_none()
+            && self.top_p.is_none()
+            && self.num_predict.is_none()
+            && self.stop.is_none()
+    }
+}
+
+/// Ollama accepts `keep_alive` as a duration string ("5m") or a number of
+/// seconds (-1 keeps the model loaded indefinitely).
+pub(super) fn keep_alive_value(keep_alive: &str) -> serde_json::Value {
+    match keep_alive.trim().parse::<i64>() {
+        Ok(secs) => serde_json::json!(secs),
+        Err(_) => serde_json::json!(keep_alive.trim()),
+    }
+}
+
+/// One response object: the full reply when `stream: false`, or one NDJSON line.
+#[derive(Debug, Deserialize)]
+pub(super) struct OllamaChatResponse {
+    #[serde(default)]
+    pub message: Option<OllamaResponseMessage>,
+    #[serde(default)]
+    pub done: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Prompt / completion token counts, present on the final object.
+    #[serde(default)]
+    pub prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    pub eval_count: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct OllamaResponseMessage {
+    #[serde(default)]
+    pub content: String,
+}
+
+impl OllamaChatResponse {
+    /// Parse one NDJSON line, turning `{"error": "..."}` objects into `VeyaError`s.
+    pub fn parse_line(line: &str) -> Result<Self, VeyaError> {
+        let resp: Self = serde_json::from_str(line)
+            .map_err(|e| VeyaError::ModelUnavailable(format!("Invalid Ollama response: {e}")))?;
+        match resp.error {
+            Some(error) => Err(VeyaError::ModelUnavailable(format!("Ollama error: {error}"))),
+            None => Ok(resp),
+        }
+    }
+
+    pub fn token_usage(&self) -> TokenUsage {
+        TokenUsage {
+            input_tokens: self.prompt_eval_count.unwrap_or(0),
+            output_tokens: self.eval_count.unwrap_or(0),
+        }
+    }
+}
+
+// ── NDJSON decoder ───────────────────────────────────────────────
+
+/// Splits a byte stream into complete lines, decoding UTF-8 only per line so
+/// multibyte characters split across network chunks survive.
+#[derive(Debug, Default)]
+pub struct NdjsonDecoder {
+    buffer: Vec<u8>,
+}
+
+impl NdjsonDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a network chunk and return every non-empty line completed by it.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if !line.is_empty() {
+                lines.push(line.to_string());
+            }
+        }
+        lines
+    }
+
+    /// Return the trailing line if the stream ended without a newline.
+    pub fn finish(&mut self) -> Option<String> {
+        let buffer = std::mem::take(&mut self.buffer);
+        let line = String::from_utf8_lossy(&buffer);
+        let line = line.trim();
+        (!line.is_empty()).then(|| line.to_string())
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_url_accepts_root_and_v1_base() {
+        assert_eq!(api_url("http://localhost:11434", "chat"), "http://localhost:11434/api/chat");
+        assert_eq!(api_url("http://localhost:11434/", "tags"), "http://localhost:11434/api/tags");
+        assert_eq!(api_url("http://localhost:11434/v1/", "chat"), "http://localhost:11434/api/chat");
+    }
+
+    #[test]
+    fn keep_alive_numbers_become_json_numbers() {
+        assert_eq!(keep_alive_value("-1"), serde_json::json!(-1));
+        assert_eq!(keep_alive_value("10m"), serde_json::json!("10m"));
+    }
+
+    #[test]
+    fn request_omits_unset_options() {
+        let req = OllamaChatRequest::<serde_json::Value> {
+            model: "llama3".into(),
+            messages: vec![],
+            stream: true,
+            options: OllamaModelOptions::default(),
+            keep_alive: None,
+            format: None,
+        };
+        let json = serde_json::to_string(&req).unwrap();
+        assert!(!json.contains("options"));
+        assert!(!json.contains("keep_alive"));
+        assert!(!json.contains("format"));
+    }
+
+    #[test]
+    fn ndjson_decoder_handles_split_lines_and_multibyte() {
+        let input = "{\"message\":{\"content\":\"你好\"},\"done\":false}\n{\"done\":true}\n".as_bytes();
+        let mut decoder = NdjsonDecoder::new();
+        let mut lines: Vec<String> = input.chunks(5).flat_map(|c| decoder.push(c)).collect();
+        lines.extend(decoder.finish());
+        assert_eq!(lines.len(), 2);
+
+        let first = OllamaChatResponse::parse_line(&lines[0]).unwrap();
+        assert_eq!(first.message.unwrap().content, "你好");
+        assert!(!first.done);
+        assert!(OllamaChatResponse::parse_line(&lines[1]).unwrap().done);
+    }
+
+    #[test]
+    fn ndjson_error_line_is_an_error() {
+        let err = OllamaChatResponse::parse_line(r#"{"error":"model 'x' not found"}"#).unwrap_err();
+        assert!(matches!(err, VeyaError::ModelUnavailable(_)));
+        assert!(err.to_string().contains("not found"));
+    }
+
+    #[test]
+    fn final_line_reports_token_usage() {
+        let line = r#"{"done":true,"prompt_eval_count":26,"eval_count":290}"#;
+        let usage = OllamaChatResponse::parse_line(line).unwrap().token_usage();
+        assert_eq!(usage, TokenUsage { input_tokens: 26, output_tokens: 290 });
+    }
+}
@@ -0,0 +1,279 @@
+//! Incremental Server-Sent Events decoder shared by the streaming providers.
+//!
+//! Network chunks are buffered as raw bytes and only decoded once a full line
+//! is available, so multibyte characters split across chunks stay intact.
+//! Supports `\n`, `\r\n` and `\r` line endings, multi-line `data:` fields,
+//! `event:` lines and comments, per the WHATWG event-stream format.
+
+use crate::error::VeyaError;
+
+/// A single dispatched SSE event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    /// Value of the `event:` field, if any (the spec's default is "message").
+    pub event: Option<String>,
+    /// All `data:` lines of the event joined with `\n`.
+    pub data: String,
+}
+
+impl SseEvent {
+    /// Returns true for the OpenAI-style `[DONE]` terminator.
+    pub fn is_done_marker(&self) -> bool {
+        self.data.trim() == "[DONE]"
+    }
+}
+
+/// Stateful decoder: feed it raw bytes with `push`, call `finish` at end of stream.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a network chunk and return every event completed by it.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        let mut start = 0;
+
+        while let Some(offset) = self.buffer[start..]
+            .iter()
+            .position(|&b| b == b'\n' || b == b'\r')
+        {
+            let end = start + offset;
+            let terminator_len = if self.buffer[end] == b'\r' {
+                match self.buffer.get(end + 1) {
+                    Some(b'\n') => 2,
+                    Some(_) => 1,
+                    // A trailing '\r' may be the first half of "\r\n"; wait for more data.
+                    None => break,
+                }
+            } else {
+                1
+            };
+
+            let line = String::from_utf8_lossy(&self.buffer[start..end]).into_owned();
+            start = end + terminator_len;
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+        }
+
+        self.buffer.drain(..start);
+        events
+    }
+
+    /// Flush any buffered line and pending event at end of stream.
+    ///
+    /// Strictly, an event without a trailing blank line should be discarded,
+    /// but some servers close the connection right after the last `data:` line.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let buffer = std::mem::take(&mut self.buffer);
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.trim_end_matches('\r').to_string();
+            if let Some(event) = self.process_line(&line) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            // Comment / keep-alive
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            // `id` and `retry` are irrelevant for one-shot LLM streams.
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(SseEvent {
+            event,
+            data: std::mem::take(&mut self.data).join("\n"),
+        })
+    }
+}
+
+// ── In-stream error events ───────────────────────────────────────
+
+/// Convert an in-stream error event into a `VeyaError`.
+///
+/// Handles Anthropic `event: error` payloads (`{"type":"error","error":{...}}`)
+/// and OpenAI-compatible `{"error":{...}}` objects. Returns None for regular events.
+pub fn error_from_event(event: &SseEvent) -> Option<VeyaError> {
+    let value: Option<serde_json::Value> = serde_json::from_str(&event.data).ok();
+    let error = value.as_ref().and_then(|v| v.get("error"));
+
+    if error.is_none() && event.event.as_deref() != Some("error") {
+        return None;
+    }
+
+    let error_type = error
+        .and_then(|e| e.get("type").or_else(|| e.get("code")))
+        .and_then(|t| t.as_str())
+        .unwrap_or("");
+    let code = error
+        .and_then(|e| e.get("code"))
+        .and_then(|c| c.as_str())
+        .unwrap_or(error_type);
+    let message = error
+        .and_then(|e| e.get("message"))
+        .and_then(|m| m.as_str())
+        .map(|m| m.to_string())
+        .unwrap_or_else(|| event.data.clone());
+
+    Some(classify_stream_error(error_type, code, &message))
+}
+
+fn classify_stream_error(error_type: &str, code: &str, message: &str) -> VeyaError {
+    let detail = if error_type.is_empty() {
+        message.to_string()
+    } else {
+        format!("{error_type}: {message}")
+    };
+
+    match (error_type, code) {
+        ("authentication_error" | "permission_error", _) | (_, "invalid_api_key") => {
+            VeyaError::InvalidApiKey(format!("Stream error: {detail}"))
+        }
+        ("billing_error" | "insufficient_quota", _) | (_, "insufficient_quota") => {
+            VeyaError::InsufficientBalance(format!("Stream error: {detail}"))
+        }
+        ("rate_limit_error", _) | (_, "rate_limit_exceeded") => {
+            VeyaError::NetworkTimeout(format!("Rate limited: {detail}"))
+        }
+        _ => VeyaError::ModelUnavailable(format!("Stream error: {detail}")),
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(chunks: &[&[u8]]) -> Vec<SseEvent> {
+        let mut decoder = SseDecoder::new();
+        let mut events: Vec<SseEvent> = chunks.iter().flat_map(|c| decoder.push(c)).collect();
+        events.extend(decoder.finish());
+        events
+    }
+
+    #[test]
+    fn decodes_lf_and_crlf_framing() {
+        let events = decode_all(&[b"data: one\n\ndata: two\r\n\r\ndata: three\r\r"]);
+        let data: Vec<_> = events.iter().map(|e| e.data.as_str()).collect();
+        assert_eq!(data, vec!["one", "two", "three"]);
+    }
+
+    #[test]
+    fn crlf_split_across_chunks() {
+        let events = decode_all(&[b"data: one\r", b"\n\r", b"\ndata: two\r\n\r\n"]);
+        let data: Vec<_> = events.iter().map(|e| e.data.as_str()).collect();
+        assert_eq!(data, vec!["one", "two"]);
+    }
+
+    #[test]
+    fn joins_multiline_data() {
+        let events = decode_all(&[b"data: first\ndata:second\ndata\n\n"]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "first\nsecond\n");
+    }
+
+    #[test]
+    fn records_event_field_and_skips_comments() {
+        let events = decode_all(&[
+            b": keep-alive\n\nevent: content_block_delta\nid: 7\ndata: {}\n\ndata: x\n\n",
+        ]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.as_deref(), Some("content_block_delta"));
+        assert_eq!(events[1].event, None);
+    }
+
+    #[test]
+    fn preserves_multibyte_chars_split_across_chunks() {
+        let bytes = "data: 你好世界\n\n".as_bytes();
+        // Split in the middle of "好" (3-byte UTF-8 sequence)
+        let events = decode_all(&[&bytes[..10], &bytes[10..]]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "你好世界");
+    }
+
+    #[test]
+    fn byte_by_byte_feed_matches_whole_feed() {
+        let input = "event: a\r\ndata: 日本語\r\n\r\ndata: [DONE]\n\n".as_bytes();
+        let whole = decode_all(&[input]);
+        let chunks: Vec<&[u8]> = input.chunks(1).collect();
+        assert_eq!(decode_all(&chunks), whole);
+        assert!(whole[1].is_done_marker());
+    }
+
+    #[test]
+    fn finish_flushes_unterminated_event() {
+        let events = decode_all(&[b"data: tail"]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "tail");
+    }
+
+    #[test]
+    fn anthropic_error_event_maps_to_veya_error() {
+        let event = SseEvent {
+            event: Some("error".into()),
+            data: r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#.into(),
+        };
+        let err = error_from_event(&event).unwrap();
+        assert!(matches!(err, VeyaError::ModelUnavailable(_)));
+        assert!(err.is_retryable());
+        assert!(err.to_string().contains("Overloaded"));
+    }
+
+    #[test]
+    fn auth_and_quota_errors_are_not_retryable() {
+        let auth = SseEvent {
+            event: Some("error".into()),
+            data: r#"{"type":"error","error":{"type":"authentication_error","message":"bad key"}}"#.into(),
+        };
+        assert!(matches!(error_from_event(&auth), Some(VeyaError::InvalidApiKey(_))));
+
+        let quota = SseEvent {
+            event: None,
+            data: r#"{"error":{"message":"You exceeded your quota","type":"insufficient_quota","code":"insufficient_quota"}}"#.into(),
+        };
+        assert!(matches!(error_from_event(&quota), Some(VeyaError::InsufficientBalance(_))));
+    }
+
+    #[test]
+    fn regular_events_are_not_errors() {
+        let event = SseEvent {
+            event: Some("content_block_delta".into()),
+            data: r#"{"type":"content_block_delta","delta":{"text":"hi"}}"#.into(),
+        };
+        assert!(error_from_event(&event).is_none());
+    }
+}
@@ -0,0 +1,152 @@
+//! JSON-mode requests: a schema the provider is asked to follow and the
+//! typed result it is parsed into.
+//!
+//! Each provider constrains output differently — OpenAI via
+//! `response_format: json_schema`, Anthropic by forcing a single tool call
+//! whose input is the object, Ollama via `format`. Whatever comes back is
+//! still parsed and validated here, since not every OpenAI-compatible server
+//! or local model honours the schema.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A named JSON schema sent with a JSON-mode request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonSchema {
+    /// Schema / tool name, e.g. "text_analysis" (`[a-zA-Z0-9_-]` only).
+    pub name: String,
+    pub description: String,
+    pub schema: serde_json::Value,
+}
+
+impl JsonSchema {
+    /// OpenAI `response_format` value.
+    pub(super) fn openai_response_format(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": self.name,
+                "schema": self.schema,
+                "strict": true,
+            },
+        })
+    }
+
+    /// Anthropic tool definition whose input is the requested object.
+    pub(super) fn anthropic_tool(&self) -> AnthropicTool {
+        AnthropicTool {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            input_schema: self.schema.clone(),
+        }
+    }
+
+    /// Anthropic `tool_choice` forcing a call to the tool above.
+    pub(super) fn anthropic_tool_choice(&self) -> serde_json::Value {
+        serde_json::json!({ "type": "tool", "name": self.name })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(super) struct AnthropicTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: serde_json::Value,
+}
+
+/// A type that can be requested from a model in JSON mode.
+pub trait StructuredOutput: DeserializeOwned {
+    fn json_schema() -> JsonSchema;
+
+    /// Semantic checks beyond what deserialization enforces. The message is
+    /// sent back to the model when re-requesting.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Parse and validate a model reply. Tolerates a Markdown code fence around
+/// the object, which some models add even in JSON mode.
+pub fn parse_output<T: StructuredOutput>(raw: &str) -> Result<T, String> {
+    let trimmed = raw.trim();
+    let json = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed);
+
+    let value: T = serde_json::from_str(json.trim()).map_err(|e| format!("invalid JSON: {e}"))?;
+    value.validate()?;
+    Ok(value)
+}
+
+/// Follow-up turn asking the model to fix a malformed reply.
+pub(super) fn repair_prompt(error: &str) -> String {
+    format!(
+        "Your previous reply could not be used ({error}). \
+         Reply again with only a JSON object that matches the schema."
+    )
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Greeting {
+        text: String,
+    }
+
+    impl StructuredOutput for Greeting {
+        fn json_schema() -> JsonSchema {
+            JsonSchema {
+                name: "greeting".into(),
+                description: "A greeting".into(),
+                schema: serde_json::json!({
+                    "type": "object",
+                    "properties": { "text": { "type": "string" } },
+                    "required": ["text"],
+                    "additionalProperties": false,
+                }),
+            }
+        }
+
+        fn validate(&self) -> Result<(), String> {
+            if self.text.trim().is_empty() {
+                return Err("text is empty".into());
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn parses_plain_and_fenced_json() {
+        let plain: Greeting = parse_output(r#"{"text":"hi"}"#).unwrap();
+        assert_eq!(plain.text, "hi");
+        let fenced: Greeting = parse_output("```json\n{\"text\":\"hi\"}\n```").unwrap();
+        assert_eq!(fenced.text, "hi");
+    }
+
+    #[test]
+    fn rejects_malformed_and_invalid_output() {
+        assert!(parse_output::<Greeting>("Sure! {\"text\":").unwrap_err().starts_with("invalid JSON"));
+        assert!(parse_output::<Greeting>(r#"{"txt":"hi"}"#).is_err());
+        assert_eq!(parse_output::<Greeting>(r#"{"text":" "}"#).unwrap_err(), "text is empty");
+    }
+
+    #[test]
+    fn provider_formats_carry_schema() {
+        let schema = Greeting::json_schema();
+        let format = schema.openai_response_format();
+        assert_eq!(format["type"], "json_schema");
+        assert_eq!(format["json_schema"]["name"], "greeting");
+        assert_eq!(format["json_schema"]["schema"], schema.schema);
+
+        let tool = serde_json::to_value(schema.anthropic_tool()).unwrap();
+        assert_eq!(tool["input_schema"], schema.schema);
+        assert_eq!(schema.anthropic_tool_choice()["name"], "greeting");
+    }
+}
@@ -0,0 +1,501 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::db::{Database, PromptTemplateRow};
+use crate::error::VeyaError;
+use crate::language_detect::{detect, DetectionHints};
+use crate::learner_profile::{describe_language, LearnerProfile};
+use crate::llm_client::Message;
+use crate::text_insight::presets::AnalysisPreset;
+
+// ── Types ────────────────────────────────────────────────────────
+
+/// The prompts that can be customized.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptId {
+    /// Tag-prefixed text analysis.
+    TextAnalysis,
+    /// JSON-mode text analysis.
+    StructuredAnalysis,
+    OcrCompletion,
+    PodcastScript,
+    /// Transcribing and analyzing a captured image with a vision model.
+    VisionTranscription,
+}
+
+/// Variables every template can use.
+const LEARNER_VARIABLES: &[&str] = &[
+    "text",
+    "detected_language",
+    "native_language",
+    "target_language",
+    "level",
+    "learner_profile",
+];
+
+const PODCAST_VARIABLES: &[&str] = &["mode_instruction", "speed_instruction"];
+
+/// The tagged analysis also lists the sections of the chosen preset.
+const ANALYSIS_VARIABLES: &[&str] = &["sections"];
+
+/// Learner variables that don't exist for image input.
+const IMAGE_INPUT_EXCLUDED: &[&str] = &["text", "detected_language"];
+
+impl PromptId {
+    pub const ALL: [PromptId; 5] = [
+        Self::TextAnalysis,
+        Self::StructuredAnalysis,
+        Self::OcrCompletion,
+        Self::PodcastScript,
+        Self::VisionTranscription,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::TextAnalysis => "text_analysis",
+            Self::StructuredAnalysis => "structured_analysis",
+            Self::OcrCompletion => "ocr_completion",
+            Self::PodcastScript => "podcast_script",
+            Self::VisionTranscription => "vision_transcription",
+        }
+    }
+
+    /// Names of the variables the template may reference, without braces.
+    pub fn variables(&self) -> Vec<&'static str> {
+        let mut variables = LEARNER_VARIABLES.to_vec();
+        match self {
+            Self::TextAnalysis => variables.extend_from_slice(ANALYSIS_VARIABLES),
+            Self::PodcastScript => variables.extend_from_slice(PODCAST_VARIABLES),
+            // The input is an image: there is no text, or language detected from it.
+            Self::VisionTranscription => variables.retain(|v| !IMAGE_INPUT_EXCLUDED.contains(v)),
+            Self::StructuredAnalysis | Self::OcrCompletion => {}
+        }
+        variables
+    }
+
+    /// Built-in (system, user) template.
+    fn default_template(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::TextAnalysis => (DEFAULT_TEXT_ANALYSIS_SYSTEM, DEFAULT_TEXT_ANALYSIS_USER),
+            Self::StructuredAnalysis => (DEFAULT_STRUCTURED_ANALYSIS_SYSTEM, DEFAULT_TEXT_ANALYSIS_USER),
+            Self::OcrCompletion => (DEFAULT_OCR_COMPLETION_SYSTEM, DEFAULT_OCR_COMPLETION_USER),
+            Self::PodcastScript => (DEFAULT_PODCAST_SCRIPT_SYSTEM, "{{text}}"),
+            Self::VisionTranscription => (DEFAULT_VISION_TRANSCRIPTION_SYSTEM, DEFAULT_VISION_TRANSCRIPTION_USER),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PromptTemplate {
+    pub id: PromptId,
+    pub system_prompt: String,
+    pub user_prompt: String,
+    /// Variables the template may reference, without braces.
+    pub variables: Vec<String>,
+    /// True when the user has edited the template; reset restores the default.
+    pub customized: bool,
+    /// When the template was last edited (None for the default).
+    pub updated_at: Option<String>,
+}
+
+impl PromptTemplate {
+    pub fn default_for(id: PromptId) -> Self {
+        let (system, user) = id.default_template();
+        Self {
+            id,
+            system_prompt: system.into(),
+            user_prompt: user.into(),
+            variables: id.variables().into_iter().map(String::from).collect(),
+            customized: false,
+            updated_at: None,
+        }
+    }
+
+    fn from_row(id: PromptId, row: PromptTemplateRow) -> Self {
+        Self {
+            system_prompt: row.system_prompt,
+            user_prompt: row.user_prompt,
+            customized: true,
+            updated_at: Some(row.updated_at),
+            ..Self::default_for(id)
+        }
+    }
+
+    /// The user's version of the template, or the default if it was never edited.
+    pub fn load(db: &Database, id: PromptId) -> Result<Self, VeyaError> {
+        Ok(match db.get_prompt_template(id.as_str())? {
+            Some(row) => Self::from_row(id, row),
+            None => Self::default_for(id),
+        })
+    }
+
+    /// Render into chat messages. The system message is left out when empty.
+    pub fn render(&self, vars: &PromptVars) -> Vec<Message> {
+        let system = render(&self.system_prompt, vars);
+        let user = render(&self.user_prompt, vars);
+
+        let mut messages = Vec::with_capacity(2);
+        if !system.trim().is_empty() {
+            messages.push(Message { role: "system".into(), content: system, images: Vec::new() });
+        }
+        messages.push(Message { role: "user".into(), content: user, images: Vec::new() });
+        messages
+    }
+}
+
+/// Values for template variables.
+#[derive(Debug, Clone, Default)]
+pub struct PromptVars(HashMap<&'static str, String>);
+
+impl PromptVars {
+    /// The shared variables: `text`, plus the learner profile as it applies to
+    /// material in `language`.
+    pub fn for_learner(text: &str, language: &str, profile: &LearnerProfile) -> Self {
+        let level = profile
+            .level_for(language)
+            .map(|level| format!("{} ({})", level.as_str(), level.description()))
+            .unwrap_or_else(|| "not set".into());
+
+        Self::default()
+            .set("text", text)
+            .set("detected_language", describe_language(language))
+            .set("native_language", describe_language(&profile.native_language))
+            .set("target_language", describe_language(profile.translation_language(language)))
+            .set("level", level)
+            .set("learner_profile", profile.prompt_context(Some(language)))
+    }
+
+    pub fn set(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.0.insert(name, value.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+// ── Rendering ────────────────────────────────────────────────────
+
+/// Replace every `{{name}}` (inner whitespace allowed) with its value.
+/// Unknown variables are left as written; substituted values are not
+/// scanned again, so `{{` in the user's text is harmless.
+pub fn render(template: &str, vars: &PromptVars) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some((before, name, after)) = next_variable(rest) {
+        out.push_str(before);
+        match vars.get(name.trim()) {
+            Some(value) => out.push_str(value),
+            None => {
+                out.push_str("{{");
+                out.push_str(name);
+                out.push_str("}}");
+            }
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Names of all variables referenced by `template`, in order of appearance.
+pub fn referenced_variables(template: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some((_, name, after)) = next_variable(rest) {
+        names.push(name.trim().to_string());
+        rest = after;
+    }
+    names
+}
+
+/// Split at the first `{{name}}`: (text before, raw name, text after).
+fn next_variable(text: &str) -> Option<(&str, &str, &str)> {
+    let start = text.find("{{")?;
+    let inner = &text[start + 2..];
+    let end = inner.find("}}")?;
+    Some((&text[..start], &inner[..end], &inner[end + 2..]))
+}
+
+// ── Core logic (testable without Tauri) ──────────────────────────
+
+pub fn list_templates(db: &Database) -> Result<Vec<PromptTemplate>, VeyaError> {
+    PromptId::ALL.iter().map(|id| PromptTemplate::load(db, *id)).collect()
+}
+
+/// Store an edited template after checking that it only uses known variables.
+pub fn save_template(
+    db: &Database,
+    id: PromptId,
+    system_prompt: &str,
+    user_prompt: &str,
+) -> Result<PromptTemplate, VeyaError> {
+    validate_template(id, system_prompt, user_prompt)?;
+    db.upsert_prompt_template(id.as_str(), system_prompt, user_prompt)?;
+    PromptTemplate::load(db, id)
+}
+
+pub fn reset_template(db: &Database, id: PromptId) -> Result<PromptTemplate, VeyaError> {
+    db.delete_prompt_template(id.as_str())?;
+    Ok(PromptTemplate::default_for(id))
+}
+
+pub fn validate_template(id: PromptId, system_prompt: &str, user_prompt: &str) -> Result<(), VeyaError> {
+    if user_prompt.trim().is_empty() {
+        return Err(VeyaError::Generic("The user prompt cannot be empty".into()));
+    }
+
+    let allowed = id.variables();
+    let mut referenced = referenced_variables(system_prompt);
+    referenced.extend(referenced_variables(user_prompt));
+    if let Some(unknown) = referenced.iter().find(|name| !allowed.contains(&name.as_str())) {
+        let available: Vec<String> = allowed.iter().map(|name| format!("{{{{{name}}}}}")).collect();
+        return Err(VeyaError::Generic(format!(
+            "Unknown template variable {{{{{unknown}}}}}. Available: {}",
+            available.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Text previews are rendered with when the caller supplies none.
+const PREVIEW_SAMPLE_TEXT: &str = "The early bird catches the worm.";
+
+/// Render a template (the given draft, or the stored one) with sample values
+/// and the user's learner profile, exactly as it would be sent.
+pub fn preview_template(
+    db: &Database,
+    id: PromptId,
+    draft: Option<(String, String)>,
+    sample_text: Option<String>,
+) -> Result<Vec<Message>, VeyaError> {
+    let mut template = PromptTemplate::load(db, id)?;
+    if let Some((system_prompt, user_prompt)) = draft {
+        validate_template(id, &system_prompt, &user_prompt)?;
+        template.system_prompt = system_prompt;
+        template.user_prompt = user_prompt;
+    }
+
+    let text = sample_text
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| PREVIEW_SAMPLE_TEXT.into());
+    let profile = LearnerProfile::load(db)?;
+    let language = detect(&text, &DetectionHints::for_learner(&profile)).language;
+
+    let mut vars = PromptVars::for_learner(&text, &language, &profile);
+    match id {
+        PromptId::TextAnalysis => vars = vars.set("sections", AnalysisPreset::standard().prompt_sections()),
+        PromptId::PodcastScript => vars = crate::cast_engine::sample_podcast_vars(vars, &language),
+        PromptId::StructuredAnalysis | PromptId::OcrCompletion | PromptId::VisionTranscription => {}
+    }
+    Ok(template.render(&vars))
+}
+
+// ── Tauri Commands ───────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn list_prompt_templates(
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<Vec<PromptTemplate>, VeyaError> {
+    list_templates(&db)
+}
+
+#[tauri::command]
+pub async fn update_prompt_template(
+    id: PromptId,
+    system_prompt: String,
+    user_prompt: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<PromptTemplate, VeyaError> {
+    save_template(&db, id, &system_prompt, &user_prompt)
+}
+
+#[tauri::command]
+pub async fn reset_prompt_template(
+    id: PromptId,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<PromptTemplate, VeyaError> {
+    reset_template(&db, id)
+}
+
+/// Preview the messages a template produces. Pass `system_prompt` and
+/// `user_prompt` to preview an unsaved draft.
+#[tauri::command]
+pub async fn preview_prompt_template(
+    id: PromptId,
+    system_prompt: Option<String>,
+    user_prompt: Option<String>,
+    sample_text: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<Vec<Message>, VeyaError> {
+    let draft = system_prompt.zip(user_prompt);
+    preview_template(&db, id, draft, sample_text)
+}
+
+// ── Default templates ────────────────────────────────────────────
+
+const DEFAULT_TEXT_ANALYSIS_SYSTEM: &str = r#"You are a language analysis assistant. Analyze the given text and provide a structured response with exactly these sections, each on its own line prefixed by the section tag:
+
+{{sections}}
+
+Keep each section concise but informative. Output all sections in order.
+Do not add any extra commentary outside the section tags.
+
+{{learner_profile}}
+Write all explanations in {{native_language}}.
+Target language for the translation: {{target_language}}"#;
+
+const DEFAULT_TEXT_ANALYSIS_USER: &str = "Detected language: {{detected_language}}\n\nText to analyze:\n{{text}}";
+
+const DEFAULT_STRUCTURED_ANALYSIS_SYSTEM: &str = r#"You are a language analysis assistant. Analyze the given text and reply with a single JSON object:
+
+- original: the original text as-is
+- tokens: every word (or character, for Chinese and Japanese) in order, with its dictionary form (lemma), part of speech and meaning
+- grammarNotes: the grammatical patterns used, each with a short explanation
+- translation: an accurate translation to the user's target language (named below)
+- colloquial: a more colloquial/conversational version of the same meaning
+- simplified: a simplified version using easier vocabulary
+
+Keep explanations concise. Output only the JSON object.
+
+{{learner_profile}}
+Write all explanations in {{native_language}}.
+Target language for the translation: {{target_language}}"#;
+
+const DEFAULT_OCR_COMPLETION_SYSTEM: &str = r#"You are an OCR post-processing assistant. The user will provide text recognized by OCR from a screenshot. Your job is to:
+
+1. Fix any obvious OCR errors (misrecognized characters, broken words)
+2. Infer and complete any truncated or partially visible text
+3. Preserve the original structure and formatting
+
+Output your response in this exact format:
+[CORRECTED] The corrected/completed full text
+[INFERRED] A comma-separated list of phrases or words that you inferred or corrected (that were NOT in the original OCR output). If nothing was inferred, write "none".
+
+Be conservative — only infer content when you have high confidence.
+
+{{learner_profile}}
+The text is most likely in one of these languages. Do not translate it."#;
+
+const DEFAULT_OCR_COMPLETION_USER: &str = "OCR recognized text:\n{{text}}";
+
+const DEFAULT_VISION_TRANSCRIPTION_SYSTEM: &str = r#"You are a reading assistant for language learners. The user will send a screenshot region that may contain handwriting, stylized fonts or mathematical notation. Your job is to:
+
+1. Transcribe all text in the image exactly as written, preserving line breaks. Write math in LaTeX.
+2. Briefly explain the text for the learner: its meaning, notable vocabulary or grammar, and a translation to {{target_language}} if it is in another language.
+
+Output your response in this exact format:
+[TRANSCRIPTION] The transcribed text
+[ANALYSIS] The explanation
+
+If the image contains no text, write "none" after [TRANSCRIPTION].
+
+{{learner_profile}}
+Write the explanation in {{native_language}}."#;
+
+const DEFAULT_VISION_TRANSCRIPTION_USER: &str = "Transcribe and explain the text in this image.";
+
+const DEFAULT_PODCAST_SCRIPT_SYSTEM: &str = r#"You are a language learning podcast host. Your job is to transform the given content into an engaging spoken explanation that helps learners understand the material.
+
+Target language: {{target_language}}
+{{mode_instruction}}
+{{speed_instruction}}
+
+{{learner_profile}}
+
+Output ONLY the podcast script text, ready to be read aloud. Use paragraph breaks to separate segments. Do not include stage directions or metadata."#;
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_db() -> (Database, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let db = Database::open(dir.path().to_path_buf()).unwrap();
+        (db, dir)
+    }
+
+    #[test]
+    fn render_substitutes_known_variables_once() {
+        let vars = PromptVars::default()
+            .set("text", "{{level}} stays literal")
+            .set("level", "B1");
+        assert_eq!(
+            render("Text: {{ text }} at {{level}}, {{unknown}}", &vars),
+            "Text: {{level}} stays literal at B1, {{unknown}}"
+        );
+        assert_eq!(render("unterminated {{text", &vars), "unterminated {{text");
+    }
+
+    #[test]
+    fn defaults_only_use_their_variables() {
+        for id in PromptId::ALL {
+            let template = PromptTemplate::default_for(id);
+            validate_template(id, &template.system_prompt, &template.user_prompt).unwrap();
+            if id.variables().contains(&"text") {
+                assert!(referenced_variables(&template.user_prompt).contains(&"text".to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn save_rejects_unknown_variables() {
+        let (db, _dir) = test_db();
+        let err = save_template(&db, PromptId::OcrCompletion, "Fix {{mode_instruction}}", "{{text}}").unwrap_err();
+        assert!(err.to_string().contains("{{mode_instruction}}"));
+        assert!(save_template(&db, PromptId::OcrCompletion, "", " ").is_err());
+    }
+
+    #[test]
+    fn edit_and_reset_roundtrip() {
+        let (db, _dir) = test_db();
+        let saved = save_template(&db, PromptId::TextAnalysis, "Be brief.", "Analyze: {{text}}").unwrap();
+        assert!(saved.customized);
+        assert!(saved.updated_at.is_some());
+
+        let listed = list_templates(&db).unwrap();
+        assert_eq!(listed.len(), PromptId::ALL.len());
+        assert_eq!(listed[0].system_prompt, "Be brief.");
+        assert!(!listed[1].customized);
+
+        let reset = reset_template(&db, PromptId::TextAnalysis).unwrap();
+        assert_eq!(reset, PromptTemplate::default_for(PromptId::TextAnalysis));
+        assert!(!PromptTemplate::load(&db, PromptId::TextAnalysis).unwrap().customized);
+    }
+
+    #[test]
+    fn render_skips_empty_system_prompt() {
+        let template = PromptTemplate {
+            system_prompt: "  ".into(),
+            user_prompt: "{{text}}".into(),
+            ..PromptTemplate::default_for(PromptId::TextAnalysis)
+        };
+        let messages = template.render(&PromptVars::default().set("text", "hola"));
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "hola");
+    }
+
+    #[test]
+    fn preview_renders_draft_with_sample_text() {
+        let (db, _dir) = test_db();
+        let messages = preview_template(
+            &db,
+            PromptId::TextAnalysis,
+            Some(("Explain in {{native_language}}.".into(), "{{text}}".into())),
+            Some("Bonjour tout le monde".into()),
+        )
+        .unwrap();
+        assert_eq!(messages[0].content, "Explain in Chinese (zh).");
+        assert_eq!(messages[1].content, "Bonjour tout le monde");
+
+        let messages = preview_template(&db, PromptId::PodcastScript, None, None).unwrap();
+        assert!(!messages[0].content.contains("{{"));
+        assert!(messages[1].content.contains(PREVIEW_SAMPLE_TEXT));
+    }
+}
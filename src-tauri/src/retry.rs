@@ -18,6 +18,15 @@ impl RetryPolicy {
         }
     }
 
+    /// Backoff delay before the retry that follows the given (0-based) attempt.
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let delay = std::cmp::min(
+            self.base_delay_ms.saturating_mul(2u64.saturating_pow(attempt)),
+            self.max_delay_ms,
+        );
+        Duration::from_millis(delay)
+    }
+
     /// Execute an async operation with exponential backoff retry.
     ///
     /// The operation is called once initially, then up to `max_retries` additional
@@ -35,11 +44,7 @@ impl RetryPolicy {
                 Ok(result) => return Ok(result),
                 Err(e) => {
                     if e.is_retryable() && attempt < self.max_retries {
-                        let delay = std::cmp::min(
-                            self.base_delay_ms.saturating_mul(2u64.saturating_pow(attempt)),
-                            self.max_delay_ms,
-                        );
-                        tokio::time::sleep(Duration::from_millis(delay)).await;
+                        tokio::time::sleep(self.delay_for(attempt)).await;
                         last_error = Some(e);
                     } else {
                         return Err(e);
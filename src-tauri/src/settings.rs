@@ -3,10 +3,12 @@ use std::sync::Arc;
 
 use crate::db::Database;
 use crate::error::VeyaError;
+use crate::vision_capture::raster::OcrPreprocess;
 
 // ── AppSettings struct ───────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct AppSettings {
     pub ai_completion_enabled: bool,
     pub cache_max_size_mb: u64,
@@ -14,6 +16,100 @@ pub struct AppSettings {
     pub retry_count: u32,
     pub shortcut_capture: String,
     pub locale: String,
+    /// Request text analyses as a JSON object validated against a schema
+    /// instead of tag-prefixed sections.
+    pub structured_analysis: bool,
+    /// Analyze text copied to the clipboard, for apps where selection
+    /// events never arrive.
+    pub clipboard_watch_enabled: bool,
+    pub clipboard_watch_trigger: ClipboardTrigger,
+    /// How long the clipboard has to stay unchanged after a triggering copy.
+    pub clipboard_watch_debounce_ms: u32,
+    /// Apps whose copies are never analyzed, matched case-insensitively
+    /// against the app's name or identifier (e.g. password managers).
+    pub clipboard_watch_excluded_apps: Vec<String>,
+    pub capture_mode: CaptureMode,
+    pub ocr_engine: OcrEngineKind,
+    /// Clean-up passes applied to captured regions before OCR.
+    pub ocr_preprocess: OcrPreprocess,
+}
+
+/// Which copies trigger an analysis in clipboard-watch mode.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardTrigger {
+    /// Every copy.
+    AnyCopy,
+    /// Copies made while Alt (Option on macOS) is held.
+    WithModifier,
+    /// Copying the same text twice in quick succession.
+    DoubleCopy,
+}
+
+impl ClipboardTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AnyCopy => "any_copy",
+            Self::WithModifier => "with_modifier",
+            Self::DoubleCopy => "double_copy",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [Self::AnyCopy, Self::WithModifier, Self::DoubleCopy]
+            .into_iter()
+            .find(|t| t.as_str() == value)
+    }
+}
+
+/// How captured regions are read.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    /// The OCR engine, optionally followed by AI completion.
+    Ocr,
+    /// The cropped image goes straight to a vision model, which transcribes
+    /// and explains it. Handles handwriting, stylized fonts and math.
+    VisionModel,
+}
+
+impl CaptureMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ocr => "ocr",
+            Self::VisionModel => "vision_model",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [Self::Ocr, Self::VisionModel].into_iter().find(|m| m.as_str() == value)
+    }
+}
+
+/// Which engine recognizes text in captured regions.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrEngineKind {
+    /// Apple Vision on macOS, Tesseract elsewhere.
+    Auto,
+    AppleVision,
+    Tesseract,
+}
+
+impl OcrEngineKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::AppleVision => "apple_vision",
+            Self::Tesseract => "tesseract",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [Self::Auto, Self::AppleVision, Self::Tesseract]
+            .into_iter()
+            .find(|k| k.as_str() == value)
+    }
 }
 
 impl Default for AppSettings {
@@ -25,6 +121,19 @@ impl Default for AppSettings {
             retry_count: 3,
             shortcut_capture: "CommandOrControl+Shift+S".into(),
             locale: "zh-CN".into(),
+            structured_analysis: false,
+            clipboard_watch_enabled: false,
+            clipboard_watch_trigger: ClipboardTrigger::DoubleCopy,
+            clipboard_watch_debounce_ms: 300,
+            clipboard_watch_excluded_apps: vec![
+                "1Password".into(),
+                "Bitwarden".into(),
+                "KeePassXC".into(),
+                "Keychain Access".into(),
+            ],
+            capture_mode: CaptureMode::Ocr,
+            ocr_engine: OcrEngineKind::Auto,
+            ocr_preprocess: OcrPreprocess::default(),
         }
     }
 }
@@ -36,6 +145,14 @@ const KEY_CACHE_CLEAN_DAYS: &str = "cache_auto_clean_days";
 const KEY_RETRY_COUNT: &str = "retry_count";
 const KEY_SHORTCUT_CAPTURE: &str = "shortcut_capture";
 const KEY_LOCALE: &str = "locale";
+const KEY_STRUCTURED_ANALYSIS: &str = "structured_analysis";
+const KEY_CLIPBOARD_WATCH: &str = "clipboard_watch_enabled";
+const KEY_CLIPBOARD_TRIGGER: &str = "clipboard_watch_trigger";
+const KEY_CLIPBOARD_DEBOUNCE: &str = "clipboard_watch_debounce_ms";
+const KEY_CLIPBOARD_EXCLUDED_APPS: &str = "clipboard_watch_excluded_apps";
+const KEY_CAPTURE_MODE: &str = "capture_mode";
+const KEY_OCR_ENGINE: &str = "ocr_engine";
+const KEY_OCR_PREPROCESS: &str = "ocr_preprocess";
 
 impl AppSettings {
     /// Load settings from the database, falling back to defaults for missing keys.
@@ -70,6 +187,46 @@ impl AppSettings {
             .get_setting(KEY_LOCALE)?
             .unwrap_or(defaults.locale);
 
+        let structured_analysis = db
+            .get_setting(KEY_STRUCTURED_ANALYSIS)?
+            .map(|v| v == "true")
+            .unwrap_or(defaults.structured_analysis);
+
+        let clipboard_watch_enabled = db
+            .get_setting(KEY_CLIPBOARD_WATCH)?
+            .map(|v| v == "true")
+            .unwrap_or(defaults.clipboard_watch_enabled);
+
+        let clipboard_watch_trigger = db
+            .get_setting(KEY_CLIPBOARD_TRIGGER)?
+            .and_then(|v| ClipboardTrigger::parse(&v))
+            .unwrap_or(defaults.clipboard_watch_trigger);
+
+        let clipboard_watch_debounce_ms = db
+            .get_setting(KEY_CLIPBOARD_DEBOUNCE)?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(defaults.clipboard_watch_debounce_ms);
+
+        let clipboard_watch_excluded_apps = db
+            .get_setting(KEY_CLIPBOARD_EXCLUDED_APPS)?
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or(defaults.clipboard_watch_excluded_apps);
+
+        let capture_mode = db
+            .get_setting(KEY_CAPTURE_MODE)?
+            .and_then(|v| CaptureMode::parse(&v))
+            .unwrap_or(defaults.capture_mode);
+
+        let ocr_engine = db
+            .get_setting(KEY_OCR_ENGINE)?
+            .and_then(|v| OcrEngineKind::parse(&v))
+            .unwrap_or(defaults.ocr_engine);
+
+        let ocr_preprocess = db
+            .get_setting(KEY_OCR_PREPROCESS)?
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or(defaults.ocr_preprocess);
+
         Ok(Self {
             ai_completion_enabled,
             cache_max_size_mb,
@@ -77,6 +234,14 @@ impl AppSettings {
             retry_count,
             shortcut_capture,
             locale,
+            structured_analysis,
+            clipboard_watch_enabled,
+            clipboard_watch_trigger,
+            clipboard_watch_debounce_ms,
+            clipboard_watch_excluded_apps,
+            capture_mode,
+            ocr_engine,
+            ocr_preprocess,
         })
     }
 
@@ -88,6 +253,18 @@ impl AppSettings {
         db.set_setting(KEY_RETRY_COUNT, &self.retry_count.to_string())?;
         db.set_setting(KEY_SHORTCUT_CAPTURE, &self.shortcut_capture)?;
         db.set_setting(KEY_LOCALE, &self.locale)?;
+        db.set_setting(KEY_STRUCTURED_ANALYSIS, &self.structured_analysis.to_string())?;
+        db.set_setting(KEY_CLIPBOARD_WATCH, &self.clipboard_watch_enabled.to_string())?;
+        db.set_setting(KEY_CLIPBOARD_TRIGGER, self.clipboard_watch_trigger.as_str())?;
+        db.set_setting(KEY_CLIPBOARD_DEBOUNCE, &self.clipboard_watch_debounce_ms.to_string())?;
+        let excluded_apps = serde_json::to_string(&self.clipboard_watch_excluded_apps)
+            .map_err(|e| VeyaError::StorageError(format!("Failed to encode excluded apps: {e}")))?;
+        db.set_setting(KEY_CLIPBOARD_EXCLUDED_APPS, &excluded_apps)?;
+        db.set_setting(KEY_CAPTURE_MODE, self.capture_mode.as_str())?;
+        db.set_setting(KEY_OCR_ENGINE, self.ocr_engine.as_str())?;
+        let ocr_preprocess = serde_json::to_string(&self.ocr_preprocess)
+            .map_err(|e| VeyaError::StorageError(format!("Failed to encode OCR preprocessing: {e}")))?;
+        db.set_setting(KEY_OCR_PREPROCESS, &ocr_preprocess)?;
         Ok(())
     }
 }
@@ -139,6 +316,19 @@ mod tests {
             retry_count: 5,
             shortcut_capture: "Ctrl+Alt+X".into(),
             locale: "en-US".into(),
+            structured_analysis: true,
+            clipboard_watch_enabled: true,
+            clipboard_watch_trigger: ClipboardTrigger::WithModifier,
+            clipboard_watch_debounce_ms: 500,
+            clipboard_watch_excluded_apps: vec!["Terminal".into()],
+            capture_mode: CaptureMode::VisionModel,
+            ocr_engine: OcrEngineKind::Tesseract,
+            ocr_preprocess: OcrPreprocess {
+                upscale: 2,
+                binarize: true,
+                invert_dark: true,
+                ..OcrPreprocess::default()
+            },
         };
         settings.save(&db).unwrap();
         let loaded = AppSettings::load(&db).unwrap();
@@ -155,6 +345,45 @@ mod tests {
         assert_eq!(loaded.ai_completion_enabled, true);
         assert_eq!(loaded.retry_count, 3);
     }
+
+    #[test]
+    fn json_uses_frontend_key_names() {
+        let settings = AppSettings {
+            clipboard_watch_enabled: true,
+            capture_mode: CaptureMode::VisionModel,
+            ocr_engine: OcrEngineKind::Tesseract,
+            ocr_preprocess: OcrPreprocess { invert_dark: true, ..OcrPreprocess::default() },
+            ..AppSettings::default()
+        };
+        let json = serde_json::to_value(&settings).unwrap();
+        assert_eq!(json["clipboardWatchEnabled"], true);
+        assert_eq!(json["clipboardWatchExcludedApps"][0], "1Password");
+        assert_eq!(json["captureMode"], "vision_model");
+        assert_eq!(json["ocrEngine"], "tesseract");
+        assert_eq!(json["ocrPreprocess"]["invertDark"], true);
+        assert!(json.get("ocr_preprocess").is_none());
+
+        let parsed: AppSettings = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, settings);
+    }
+
+    #[test]
+    fn snake_case_payload_is_rejected() {
+        let mut json = serde_json::to_value(AppSettings::default()).unwrap();
+        let map = json.as_object_mut().unwrap();
+        let enabled = map.remove("clipboardWatchEnabled").unwrap();
+        map.insert("clipboard_watch_enabled".into(), enabled);
+        assert!(serde_json::from_value::<AppSettings>(json).is_err());
+    }
+
+    #[test]
+    fn stored_preprocess_accepts_legacy_snake_case() {
+        let (db, _dir) = test_db();
+        db.set_setting("ocr_preprocess", r#"{"upscale":2,"invert_dark":true}"#).unwrap();
+        let loaded = AppSettings::load(&db).unwrap();
+        assert_eq!(loaded.ocr_preprocess.upscale, 2);
+        assert!(loaded.ocr_preprocess.invert_dark);
+    }
 }
 
 /// Re-register the capture shortcut after settings change.
@@ -1,14 +1,38 @@
+pub mod cache;
+pub mod clipboard_watch;
+#[cfg(target_os = "linux")]
+pub(crate) mod linux_selection;
+pub mod presets;
+pub mod sections;
+pub mod selection;
+pub mod structured;
+
+use futures_util::future::{AbortHandle, AbortRegistration, Abortable};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
 
-use crate::api_config::ApiConfig;
+use crate::api_config::{resolve_feature_chain, ModelFeature};
 use crate::db::Database;
 use crate::error::VeyaError;
-use crate::llm_client::{LlmClient, LlmConfig, Message};
+use crate::language_detect::{self, DetectionHints, LanguageCandidate};
+use crate::learner_profile::LearnerProfile;
+use crate::learning_record::{self, SaveQueryInput};
+use crate::llm_client::{LlmClient, Message};
+use crate::prompt_template::{PromptId, PromptTemplate, PromptVars};
 use crate::retry::RetryPolicy;
 use crate::settings::AppSettings;
 use crate::stronghold_store::StrongholdStore;
+use crate::usage::UsageRecorder;
+use cache::{CacheTarget, CachedAnalysis};
+use presets::{AnalysisPreset, PresetSection, STANDARD_PRESET_ID};
+use sections::{SectionEvent, SectionParser};
+use structured::StructuredAnalysis;
+
+// Compatibility re-export for the integration tests and older callers.
+pub use crate::language_detect::detect_language;
 
 // ── Event types ──────────────────────────────────────────────────
 
@@ -17,165 +41,513 @@ const EVENT_STREAM_CHUNK: &str = "veya://text-insight/stream-chunk";
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextInsightChunk {
     #[serde(rename = "type")]
-    pub chunk_type: String, // "start" | "delta" | "done" | "error"
+    pub chunk_type: String, // "start" | "section_start" | "delta" | "section_end" | "retrying" | "fallback" | "done" | "error" | "cancelled"
+    /// Identifies the analysis this chunk belongs to, so stale streams can be ignored.
+    pub request_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub section: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub language: Option<String>,
+    /// Detected language candidates, most likely first, set on `start`.
+    /// The first is the user's override when one was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_candidates: Option<Vec<LanguageCandidate>>,
+    /// Retry attempt number on `retrying` chunks; partial output should be discarded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempt: Option<u32>,
+    /// Model that took over (`fallback`) or answered (`done`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// ID of the query record the parsed result was saved as, set on `done`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_id: Option<String>,
+    /// Sections of the analysis preset in display order, set on `start`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sections: Option<Vec<PresetSection>>,
+    /// True on `start` when the result is replayed from the analysis cache.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached: Option<bool>,
 }
 
-// ── Language detection ───────────────────────────────────────────
+impl TextInsightChunk {
+    fn new(request_id: &str, chunk_type: &str, content: Option<String>) -> Self {
+        Self {
+            chunk_type: chunk_type.into(),
+            request_id: request_id.into(),
+            section: None,
+            content,
+            language: None,
+            language_candidates: None,
+            attempt: None,
+            model: None,
+            record_id: None,
+            sections: None,
+            cached: None,
+        }
+    }
 
-/// Detect the language of the given text using whatlang.
-/// Returns a language code string (e.g. "en", "zh", "ja").
-pub fn detect_language(text: &str) -> String {
-    whatlang::detect_lang(text)
-        .map(|lang| whatlang_to_code(lang))
-        .unwrap_or_else(|| "unknown".to_string())
+    fn from_section_event(request_id: &str, event: SectionEvent) -> Self {
+        match event {
+            SectionEvent::Start(key) => Self {
+                section: Some(key),
+                ..Self::new(request_id, "section_start", None)
+            },
+            SectionEvent::Delta(key, text) => Self {
+                section: key,
+                ..Self::new(request_id, "delta", Some(text))
+            },
+            SectionEvent::End(key) => Self {
+                section: Some(key),
+                ..Self::new(request_id, "section_end", None)
+            },
+        }
+    }
 }
 
-fn whatlang_to_code(lang: whatlang::Lang) -> String {
-    use whatlang::Lang;
-    match lang {
-        Lang::Eng => "en",
-        Lang::Cmn => "zh",
-        Lang::Jpn => "ja",
-        Lang::Kor => "ko",
-        Lang::Fra => "fr",
-        Lang::Deu => "de",
-        Lang::Spa => "es",
-        Lang::Por => "pt",
-        Lang::Rus => "ru",
-        Lang::Ita => "it",
-        _ => lang.code(),
-    }
-    .to_string()
-}
+// ── In-flight analysis registry ──────────────────────────────────
 
-// ── Structured analysis prompt ───────────────────────────────────
+/// Tracks in-flight analyses so they can be cancelled by request ID.
+/// Only one analysis runs at a time: starting a new one supersedes the rest.
+#[derive(Default)]
+pub struct AnalysisRegistry {
+    in_flight: Mutex<HashMap<String, AbortHandle>>,
+}
 
-fn build_analysis_prompt(text: &str, detected_lang: &str) -> Vec<Message> {
-    let system_prompt = r#"You are a language analysis assistant. Analyze the given text and provide a structured response with exactly these six sections, each on its own line prefixed by the section tag:
+impl AnalysisRegistry {
+    /// Register a new analysis, aborting every analysis that is still in flight.
+    /// Returns the abort registration to wrap the new analysis future with.
+    pub fn begin(&self, request_id: &str) -> AbortRegistration {
+        let (handle, registration) = AbortHandle::new_pair();
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        for (_, superseded) in in_flight.drain() {
+            superseded.abort();
+        }
+        in_flight.insert(request_id.to_string(), handle);
+        registration
+    }
 
-[ORIGINAL] The original text as-is
-[WORD_BY_WORD] Word-by-word or character-by-character explanation with meanings
-[STRUCTURE] Grammatical structure analysis (sentence patterns, parts of speech)
-[TRANSLATION] Accurate translation to the user's target language
-[COLLOQUIAL] A more colloquial/conversational version of the same meaning
-[SIMPLIFIED] A simplified version using easier vocabulary
+    /// Abort the analysis with the given ID. Returns false if it is not in flight.
+    pub fn cancel(&self, request_id: &str) -> bool {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        match in_flight.remove(request_id) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
 
-Keep each section concise but informative. Output all six sections in order.
-Do not add any extra commentary outside the section tags."#;
+    /// Remove a finished analysis from the registry.
+    pub fn finish(&self, request_id: &str) {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        in_flight.remove(request_id);
+    }
 
-    let user_msg = format!(
-        "Detected language: {detected_lang}\n\nText to analyze:\n{text}"
-    );
+    pub fn is_in_flight(&self, request_id: &str) -> bool {
+        let in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        in_flight.contains_key(request_id)
+    }
+}
 
-    vec![
-        Message {
-            role: "system".into(),
-            content: system_prompt.into(),
-        },
-        Message {
-            role: "user".into(),
-            content: user_msg,
-        },
-    ]
+// ── Analysis prompt ──────────────────────────────────────────────
+
+/// Render the analysis template (tagged or JSON mode) for `text`, listing
+/// the sections of `preset` in the tagged template.
+fn build_analysis_prompt(
+    template: &PromptTemplate,
+    text: &str,
+    detected_lang: &str,
+    profile: &LearnerProfile,
+    preset: &AnalysisPreset,
+) -> Vec<Message> {
+    let vars = PromptVars::for_learner(text, detected_lang, profile).set("sections", preset.prompt_sections());
+    template.render(&vars)
 }
 
-// ── Helper: resolve active text model config ─────────────────────
+// ── Helper: resolve text model client ────────────────────────────
 
-fn resolve_text_llm_config(
+/// Build a client for the insight model, falling back along the text chain.
+pub(crate) fn resolve_text_llm_client(
     db: &Database,
     store: &StrongholdStore,
     settings: &AppSettings,
-) -> Result<(LlmConfig, RetryPolicy), VeyaError> {
-    let rows = db.get_api_configs()?;
-    let config_row = rows
-        .iter()
-        .find(|r| r.model_type == "text" && r.is_active)
-        .ok_or_else(|| {
-            VeyaError::ModelUnavailable(
-                "No active text model configured. Please add one in Settings.".into(),
-            )
-        })?;
-
-    let api_config = ApiConfig::from_row(config_row)?;
-    let api_key = if api_config.is_local {
-        String::new()
+) -> Result<LlmClient, VeyaError> {
+    let chain = resolve_feature_chain(db, store, ModelFeature::Insight)?;
+    let retry_policy = RetryPolicy::new(settings.retry_count, 500, 10_000);
+
+    LlmClient::from_chain(chain, retry_policy).ok_or_else(|| {
+        VeyaError::ModelUnavailable(
+            "No active text model configured. Please add one in Settings.".into(),
+        )
+    })
+}
+
+// ── Analysis flow ────────────────────────────────────────────────
+
+/// Everything an analysis needs, resolved before the start chunk is sent.
+struct AnalysisPlan {
+    client: LlmClient,
+    settings: AppSettings,
+    preset: AnalysisPreset,
+    /// JSON mode instead of the tagged stream (standard preset only).
+    structured: bool,
+    messages: Vec<Message>,
+    /// Where to cache the result; None when caching is off.
+    cache: Option<CacheTarget>,
+}
+
+fn plan_analysis(
+    db: &Database,
+    store: &StrongholdStore,
+    text: &str,
+    detected_lang: &str,
+    preset_id: &str,
+) -> Result<AnalysisPlan, VeyaError> {
+    let preset = AnalysisPreset::load(db, preset_id)?;
+    let settings = AppSettings::load(db)?;
+    let client = resolve_text_llm_client(db, store, &settings)?;
+    // JSON mode has a fixed schema, so only the standard preset can use it.
+    let structured = settings.structured_analysis && preset.id == STANDARD_PRESET_ID;
+    let prompt_id = if structured {
+        PromptId::StructuredAnalysis
     } else {
-        store
-            .get_api_key(&api_config.id)?
-            .unwrap_or_default()
+        PromptId::TextAnalysis
     };
+    let template = PromptTemplate::load(db, prompt_id)?;
+    let profile = LearnerProfile::load(db)?;
+    let messages = build_analysis_prompt(&template, text, detected_lang, &profile, &preset);
+
+    let cache = cache::is_enabled(&settings).then(|| {
+        let without_text = build_analysis_prompt(&template, "", detected_lang, &profile, &preset);
+        let model = client.primary_config().model_name.clone();
+        CacheTarget {
+            key: cache::cache_key(text, detected_lang, &cache::prompt_fingerprint(&without_text), &model),
+            model,
+        }
+    });
+
+    Ok(AnalysisPlan { client, settings, preset, structured, messages, cache })
+}
 
-    let llm_config = LlmConfig {
-        provider: api_config.provider,
-        base_url: api_config.base_url,
-        model_name: api_config.model_name,
-        api_key,
+/// Run a full analysis for `request_id`: detect language (unless the user
+/// chose one), call the LLM with the sections of the given preset, and stream
+/// chunks tagged with the request ID. A cached result for the same text,
+/// prompt and model is replayed instead.
+///
+/// `registration` comes from `AnalysisRegistry::begin`; cancelling the
+/// request through the registry drops the HTTP stream.
+async fn run_analysis(
+    app: &AppHandle,
+    request_id: &str,
+    registration: AbortRegistration,
+    text: &str,
+    preset_id: &str,
+    language: Option<String>,
+) -> Result<(), VeyaError> {
+    let db = app.state::<Arc<Database>>();
+    let store = app.state::<Arc<StrongholdStore>>();
+    let registry = app.state::<AnalysisRegistry>();
+
+    let hints = LearnerProfile::load(&db)
+        .map(|profile| DetectionHints::for_learner(&profile))
+        .unwrap_or_default()
+        .with_override(language);
+    let detection = language_detect::detect(text, &hints);
+    let detected_lang = detection.language;
+    let plan = plan_analysis(&db, &store, text, &detected_lang, preset_id);
+    let cached = match &plan {
+        Ok(AnalysisPlan { cache: Some(target), .. }) => cache::lookup(&db, &target.key).unwrap_or_else(|e| {
+            log::warn!("Analysis cache lookup failed: {e}");
+            None
+        }),
+        _ => None,
     };
 
-    let retry_policy = RetryPolicy::new(settings.retry_count, 500, 10_000);
+    // Emit start event with detected language and the sections to expect
+    let _ = app.emit(
+        EVENT_STREAM_CHUNK,
+        TextInsightChunk {
+            language: Some(detected_lang.clone()),
+            language_candidates: Some(detection.candidates),
+            sections: plan.as_ref().ok().map(|p| p.preset.sections.clone()),
+            cached: cached.is_some().then_some(true),
+            ..TextInsightChunk::new(request_id, "start", None)
+        },
+    );
+
+    let plan = match plan {
+        Ok(plan) => plan,
+        Err(e) => {
+            registry.finish(request_id);
+            let _ = app.emit(
+                EVENT_STREAM_CHUNK,
+                TextInsightChunk::new(request_id, "error", Some(e.to_string())),
+            );
+            return Err(e);
+        }
+    };
+
+    let run = AnalysisRun {
+        app,
+        db: &db,
+        request_id,
+        text,
+        detected_lang: &detected_lang,
+        settings: plan.settings,
+        cache: plan.cache,
+    };
+    if let Some(cached) = cached {
+        registry.finish(request_id);
+        run.replay(cached);
+        return Ok(());
+    }
+
+    let client = plan
+        .client
+        .with_usage_recorder(UsageRecorder::new(db.inner().clone()));
+    let analysis = async {
+        if plan.structured {
+            run_structured_analysis(&run, &client, plan.messages).await
+        } else {
+            let parser = SectionParser::new(plan.preset.section_specs());
+            run_tagged_analysis(&run, &client, plan.messages, parser).await
+        }
+    };
 
-    Ok((llm_config, retry_policy))
+    match Abortable::new(analysis, registration).await {
+        Ok(result) => {
+            registry.finish(request_id);
+            result
+        }
+        Err(_aborted) => {
+            let _ = app.emit(
+                EVENT_STREAM_CHUNK,
+                TextInsightChunk::new(request_id, "cancelled", None),
+            );
+            Ok(())
+        }
+    }
 }
 
-// ── Tauri Command ────────────────────────────────────────────────
+/// The running analysis: where chunks go and what the result is saved as.
+struct AnalysisRun<'a> {
+    app: &'a AppHandle,
+    db: &'a Database,
+    request_id: &'a str,
+    text: &'a str,
+    detected_lang: &'a str,
+    settings: AppSettings,
+    cache: Option<CacheTarget>,
+}
+
+impl AnalysisRun<'_> {
+    fn emit(&self, chunk: TextInsightChunk) {
+        let _ = self.app.emit(EVENT_STREAM_CHUNK, chunk);
+    }
+
+    /// Emit finished sections as start/delta/end chunks, as if streamed.
+    fn emit_sections(&self, sections: &[(String, String)]) {
+        for (key, content) in sections {
+            for event in [
+                SectionEvent::Start(key.clone()),
+                SectionEvent::Delta(Some(key.clone()), content.clone()),
+                SectionEvent::End(key.clone()),
+            ] {
+                self.emit(TextInsightChunk::from_section_event(self.request_id, event));
+            }
+        }
+    }
+
+    /// Cache the finished analysis (if it is complete and from the requested
+    /// model), save it as a query record and emit `done`.
+    fn finish(&self, model: Option<String>, sections: Vec<(String, String)>, result: serde_json::Value, complete: bool) {
+        if let (Some(target), Some(model)) = (&self.cache, &model) {
+            if let Some(entry) = cache_entry(target, model, sections, &result, complete) {
+                if let Err(e) = cache::store(self.db, &target.key, &entry, &self.settings) {
+                    log::warn!("Failed to cache analysis result: {e}");
+                }
+            }
+        }
+        self.done(model, &result);
+    }
+
+    /// Replay a cached analysis; it is saved as a new query record all the same.
+    fn replay(&self, cached: CachedAnalysis) {
+        self.emit_sections(&cached.sections);
+        self.done(Some(cached.model), &cached.result);
+    }
+
+    fn done(&self, model: Option<String>, result: &serde_json::Value) {
+        self.emit(TextInsightChunk {
+            model,
+            record_id: save_structured_result(self.db, self.text, self.detected_lang, result),
+            ..TextInsightChunk::new(self.request_id, "done", None)
+        });
+    }
+}
+
+/// The cache entry for a finished run, if it should be cached. Truncated or
+/// malformed answers (missing sections) and fallback answers are not, so a
+/// bad reply is never replayed.
+fn cache_entry(
+    target: &CacheTarget,
+    model: &str,
+    sections: Vec<(String, String)>,
+    result: &serde_json::Value,
+    complete: bool,
+) -> Option<CachedAnalysis> {
+    (complete && model == target.model).then(|| CachedAnalysis {
+        model: model.to_string(),
+        sections,
+        result: result.clone(),
+    })
+}
+
+/// Stream the tag-prefixed analysis, emitting section chunks as tags arrive.
+/// `parser` knows the preset's sections; the saved result is keyed by them.
+async fn run_tagged_analysis(
+    run: &AnalysisRun<'_>,
+    client: &LlmClient,
+    messages: Vec<Message>,
+    mut parser: SectionParser,
+) -> Result<(), VeyaError> {
+    let request_id = run.request_id;
+
+    client
+        .stream_chat(messages, |chunk| match chunk.chunk_type.as_str() {
+            // The start chunk (with language) has already been emitted.
+            "start" => {}
+            "delta" => {
+                for event in parser.push(chunk.content.as_deref().unwrap_or_default()) {
+                    run.emit(TextInsightChunk::from_section_event(request_id, event));
+                }
+            }
+            "done" => {
+                for event in parser.finish() {
+                    run.emit(TextInsightChunk::from_section_event(request_id, event));
+                }
+                run.finish(chunk.model, parser.produced_sections(), parser.to_json(), parser.is_complete());
+            }
+            chunk_type => {
+                // Retries and fallbacks restart the stream from scratch.
+                if matches!(chunk_type, "retrying" | "fallback") {
+                    parser.reset();
+                }
+                run.emit(TextInsightChunk {
+                    attempt: chunk.attempt,
+                    model: chunk.model,
+                    ..TextInsightChunk::new(request_id, chunk_type, chunk.content)
+                });
+            }
+        })
+        .await
+}
+
+/// Request the analysis in JSON mode. The validated result arrives whole and
+/// is emitted as the same section chunks the tagged stream produces.
+async fn run_structured_analysis(
+    run: &AnalysisRun<'_>,
+    client: &LlmClient,
+    messages: Vec<Message>,
+) -> Result<(), VeyaError> {
+    let (analysis, model) = match client.chat_structured::<StructuredAnalysis>(messages).await {
+        Ok(result) => result,
+        Err(e) => {
+            run.emit(TextInsightChunk::new(run.request_id, "error", Some(e.to_string())));
+            return Err(e);
+        }
+    };
+
+    let sections: Vec<(String, String)> = analysis
+        .to_sections()
+        .into_iter()
+        .map(|(key, content)| (key.to_string(), content))
+        .collect();
+    run.emit_sections(&sections);
+    // The result passed schema validation.
+    run.finish(Some(model), sections, serde_json::to_value(&analysis).unwrap_or_default(), true);
+    Ok(())
+}
+
+/// Save an analysis as a query record with a structured JSON result: the
+/// parsed sections keyed by the preset's section keys, or the full
+/// `StructuredAnalysis` in JSON mode.
+/// Returns the record ID, or None if saving failed (the analysis itself succeeded).
+fn save_structured_result(
+    db: &Database,
+    text: &str,
+    detected_lang: &str,
+    result: &serde_json::Value,
+) -> Option<String> {
+    let input = SaveQueryInput {
+        input_text: text.to_string(),
+        source: "text_insight".into(),
+        detected_language: Some(detected_lang.to_string()),
+        analysis_result: result.to_string(),
+    };
+    match learning_record::save_query(db, &input) {
+        Ok(record) => Some(record.id),
+        Err(e) => {
+            log::warn!("Failed to save analysis result: {e}");
+            None
+        }
+    }
+}
+
+/// Start an analysis in the background and return its request ID. It
+/// supersedes any analysis still in flight, and is registered before this
+/// returns, so the ID can be cancelled right away.
+fn spawn_analysis(app: &AppHandle, text: String, preset_id: String, language: Option<String>) -> String {
+    let request_id = Uuid::new_v4().to_string();
+    let registration = app.state::<AnalysisRegistry>().begin(&request_id);
+
+    let app = app.clone();
+    let id = request_id.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = run_analysis(&app, &id, registration, &text, &preset_id, language).await {
+            log::warn!("Text insight analysis {id} failed: {e}");
+        }
+    });
+    request_id
+}
+
+// ── Tauri Commands ───────────────────────────────────────────────
 
 /// Analyze the given text: detect language, call LLM with structured prompt,
-/// and stream results back via Tauri events.
+/// and stream results back via Tauri events. `preset_id` selects the analysis
+/// preset (its sections and their order); defaults to the standard preset.
+/// `language` overrides detection, e.g. after the user corrected it.
+///
+/// Returns the request ID carried on every emitted chunk immediately; the
+/// analysis streams in the background and failures end it with an `error`
+/// chunk. A newer analysis supersedes this one; a cancelled analysis ends
+/// with a `cancelled` chunk.
 #[tauri::command]
 pub async fn analyze_text(
     text: String,
+    preset_id: Option<String>,
+    language: Option<String>,
     app: AppHandle,
-    db: tauri::State<'_, Arc<Database>>,
-    store: tauri::State<'_, Arc<StrongholdStore>>,
-) -> Result<(), VeyaError> {
+) -> Result<String, VeyaError> {
     if text.trim().is_empty() {
         return Err(VeyaError::OcrFailed("Empty text provided".into()));
     }
 
-    let detected_lang = detect_language(&text);
-
-    // Emit start event with detected language
-    let _ = app.emit(
-        EVENT_STREAM_CHUNK,
-        TextInsightChunk {
-            chunk_type: "start".into(),
-            section: None,
-            content: None,
-            language: Some(detected_lang.clone()),
-        },
-    );
-
-    let settings = AppSettings::load(&db)?;
-    let (llm_config, retry_policy) = resolve_text_llm_config(&db, &store, &settings)?;
-
-    let messages = build_analysis_prompt(&text, &detected_lang);
-    let client = LlmClient::new(llm_config, retry_policy);
-
-    // Use stream_chat which handles start/delta/done/error envelope
-    // We use a dedicated event name for text insight
-    let result = client
-        .stream_chat(messages, &app, EVENT_STREAM_CHUNK)
-        .await;
-
-    if let Err(ref e) = result {
-        let _ = app.emit(
-            EVENT_STREAM_CHUNK,
-            TextInsightChunk {
-                chunk_type: "error".into(),
-                section: None,
-                content: Some(e.to_string()),
-                language: None,
-            },
-        );
-    }
+    let preset_id = preset_id.unwrap_or_else(|| STANDARD_PRESET_ID.to_string());
+    Ok(spawn_analysis(&app, text, preset_id, language))
+}
 
-    result
+/// Abort the in-flight analysis with the given request ID.
+/// Returns false if the analysis has already finished or been superseded.
+#[tauri::command]
+pub async fn cancel_analysis(
+    request_id: String,
+    registry: tauri::State<'_, AnalysisRegistry>,
+) -> Result<bool, VeyaError> {
+    Ok(registry.cancel(&request_id))
 }
 
 // ── Accessibility Listener ───────────────────────────────────────
@@ -192,14 +564,27 @@ impl TextInsightListener {
 
     /// Start listening for text selection events.
     /// On macOS, uses Accessibility API (AXSelectedTextChanged).
+    /// On Linux, follows the X11 / Wayland primary selection.
     /// On other platforms, this is a no-op stub for now.
+    /// Clipboard watching starts first on macOS and Linux, so it works even
+    /// when the selection listener can't (e.g. no Accessibility permission).
     pub fn start_listening(&self) -> Result<(), VeyaError> {
+        #[cfg(any(target_os = "macos", target_os = "linux"))]
+        {
+            self.start_clipboard_watch()?;
+        }
+
         #[cfg(target_os = "macos")]
         {
             self.start_macos_listener()?;
         }
 
-        #[cfg(not(target_os = "macos"))]
+        #[cfg(target_os = "linux")]
+        {
+            self.start_linux_listener()?;
+        }
+
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
         {
             log::warn!("Text selection listening is not yet implemented on this platform");
         }
@@ -214,74 +599,37 @@ impl TextInsightListener {
             return;
         }
 
-        let app = self.app_handle.clone();
-        tauri::async_runtime::spawn(async move {
-            let db = app.state::<Arc<Database>>();
-            let store = app.state::<Arc<StrongholdStore>>();
-
-            let detected_lang = detect_language(&text);
-
-            let _ = app.emit(
-                EVENT_STREAM_CHUNK,
-                TextInsightChunk {
-                    chunk_type: "start".into(),
-                    section: None,
-                    content: None,
-                    language: Some(detected_lang.clone()),
-                },
-            );
+        spawn_analysis(&self.app_handle, text, STANDARD_PRESET_ID.to_string(), None);
+    }
+}
 
-            let settings = match AppSettings::load(&db) {
-                Ok(s) => s,
-                Err(e) => {
-                    let _ = app.emit(
-                        EVENT_STREAM_CHUNK,
-                        TextInsightChunk {
-                            chunk_type: "error".into(),
-                            section: None,
-                            content: Some(e.to_string()),
-                            language: None,
-                        },
-                    );
-                    return;
-                }
-            };
-
-            let (llm_config, retry_policy) = match resolve_text_llm_config(&db, &store, &settings)
-            {
-                Ok(v) => v,
-                Err(e) => {
-                    let _ = app.emit(
-                        EVENT_STREAM_CHUNK,
-                        TextInsightChunk {
-                            chunk_type: "error".into(),
-                            section: None,
-                            content: Some(e.to_string()),
-                            language: None,
-                        },
-                    );
-                    return;
+/// Watch the clipboard on a background thread. The watcher idles unless
+/// clipboard-watch mode is enabled in the settings.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+impl TextInsightListener {
+    fn start_clipboard_watch(&self) -> Result<(), VeyaError> {
+        let config = self.app_handle.config();
+        let own_app_ids = std::iter::once(config.identifier.clone())
+            .chain(config.product_name.clone())
+            .collect();
+        let mut watcher = clipboard_watch::ClipboardWatcher::new(
+            self.app_handle.state::<Arc<Database>>().inner().clone(),
+            own_app_ids,
+        );
+        let listener = TextInsightListener {
+            app_handle: self.app_handle.clone(),
+        };
+
+        std::thread::Builder::new()
+            .name("veya-clipboard".into())
+            .spawn(move || loop {
+                std::thread::sleep(clipboard_watch::WATCH_TICK);
+                if let Some(text) = watcher.tick() {
+                    listener.on_text_selected(text);
                 }
-            };
-
-            let messages = build_analysis_prompt(&text, &detected_lang);
-            let client = LlmClient::new(llm_config, retry_policy);
-
-            if let Err(e) = client
-                .stream_chat(messages, &app, EVENT_STREAM_CHUNK)
-                .await
-            {
-                let _ = app.emit(
-                    EVENT_STREAM_CHUNK,
-                    TextInsightChunk {
-                        chunk_type: "error".into(),
-                        section: None,
-                        content: Some(e.to_string()),
-                        language: None,
-                    },
-                );
-            }
-        });
+            })
+            .map_err(|e| VeyaError::Generic(format!("Failed to start clipboard watch: {e}")))?;
+        Ok(())
     }
 }
 
@@ -480,10 +828,130 @@ mod tests {
 
     #[test]
     fn build_prompt_contains_text() {
-        let messages = build_analysis_prompt("Hello world", "en");
+        let template = PromptTemplate::default_for(PromptId::TextAnalysis);
+        let profile = LearnerProfile::for_locale("zh-CN");
+        let messages = build_analysis_prompt(&template, "Hello world", "en", &profile, &AnalysisPreset::standard());
         assert_eq!(messages.len(), 2);
         assert_eq!(messages[0].role, "system");
+        assert!(messages[0].content.contains("[WORD_BY_WORD] Word-by-word"));
         assert!(messages[1].content.contains("Hello world"));
         assert!(messages[1].content.contains("en"));
     }
+
+    #[test]
+    fn prompt_lists_preset_sections() {
+        let template = PromptTemplate::default_for(PromptId::TextAnalysis);
+        let preset = AnalysisPreset {
+            sections: vec![PresetSection {
+                key: "commonMistakes".into(),
+                title: "Common mistakes".into(),
+                instruction: "Typical learner errors".into(),
+            }],
+            ..AnalysisPreset::standard()
+        };
+        let system = &build_analysis_prompt(&template, "Hola", "es", &LearnerProfile::for_locale("zh-CN"), &preset)[0].content;
+        assert!(system.contains("[COMMON_MISTAKES] Typical learner errors"));
+        assert!(!system.contains("[ORIGINAL]"));
+    }
+
+    #[test]
+    fn structured_prompt_contains_text_and_fields() {
+        let template = PromptTemplate::default_for(PromptId::StructuredAnalysis);
+        let profile = LearnerProfile::for_locale("zh-CN");
+        let messages = build_analysis_prompt(&template, "Ojalá que llueva", "es", &profile, &AnalysisPreset::standard());
+        assert!(messages[0].content.contains("grammarNotes"));
+        assert!(messages[1].content.contains("Ojalá que llueva"));
+    }
+
+    #[test]
+    fn prompt_names_translation_language_and_level() {
+        let profile = LearnerProfile {
+            native_language: "zh".into(),
+            target_languages: vec![crate::learner_profile::TargetLanguage {
+                language: "es".into(),
+                level: crate::learner_profile::CefrLevel::A2,
+            }],
+        };
+        let template = PromptTemplate::default_for(PromptId::TextAnalysis);
+        let preset = AnalysisPreset::standard();
+        let system = &build_analysis_prompt(&template, "Hola", "es", &profile, &preset)[0].content;
+        assert!(system.contains("Target language for the translation: Chinese (zh)"));
+        assert!(system.contains("CEFR A2"));
+
+        let system = &build_analysis_prompt(&template, "你好", "zh", &profile, &preset)[0].content;
+        assert!(system.contains("Target language for the translation: Spanish (es)"));
+    }
+
+    #[test]
+    fn registry_begin_supersedes_in_flight() {
+        let registry = AnalysisRegistry::default();
+        let _first = registry.begin("req-1");
+        assert!(registry.is_in_flight("req-1"));
+
+        let _second = registry.begin("req-2");
+        assert!(!registry.is_in_flight("req-1"));
+        assert!(registry.is_in_flight("req-2"));
+    }
+
+    #[test]
+    fn registry_cancel_aborts_analysis() {
+        let registry = AnalysisRegistry::default();
+        let registration = registry.begin("req-1");
+        assert!(registry.cancel("req-1"));
+        assert!(!registry.cancel("req-1"));
+
+        let result = tauri::async_runtime::block_on(Abortable::new(async {}, registration));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn text_insight_chunk_carries_request_id() {
+        let chunk = TextInsightChunk::new("req-1", "delta", Some("hi".into()));
+        let json = serde_json::to_string(&chunk).unwrap();
+        assert!(json.contains("\"request_id\":\"req-1\""));
+        assert!(!json.contains("section"));
+        assert!(!json.contains("attempt"));
+    }
+
+    #[test]
+    fn section_events_map_to_chunks() {
+        let start = TextInsightChunk::from_section_event("req-1", SectionEvent::Start("wordByWord".into()));
+        assert_eq!(start.chunk_type, "section_start");
+        assert_eq!(start.section.as_deref(), Some("wordByWord"));
+
+        let delta = TextInsightChunk::from_section_event(
+            "req-1",
+            SectionEvent::Delta(Some("wordByWord".into()), "hola = hello".into()),
+        );
+        let json = serde_json::to_value(&delta).unwrap();
+        assert_eq!(json["type"], "delta");
+        assert_eq!(json["section"], "wordByWord");
+        assert_eq!(json["content"], "hola = hello");
+
+        let end = TextInsightChunk::from_section_event("req-1", SectionEvent::End("wordByWord".into()));
+        assert_eq!(end.chunk_type, "section_end");
+        assert!(end.content.is_none());
+    }
+
+    #[test]
+    fn only_complete_runs_are_cached() {
+        let target = CacheTarget { key: "k".into(), model: "gpt-4o".into() };
+        let run = |reply: &str| {
+            let mut parser = SectionParser::new(AnalysisPreset::standard().section_specs());
+            parser.push(reply);
+            parser.finish();
+            cache_entry(&target, "gpt-4o", parser.produced_sections(), &parser.to_json(), parser.is_complete())
+        };
+
+        assert!(run("Sorry, I can't help with that.").is_none());
+        assert!(run("[ORIGINAL] Hola [TRANSLATION] Hello").is_none());
+
+        let full = "[ORIGINAL] Hola [WORD_BY_WORD] hola = hello [STRUCTURE] interjection \
+                    [TRANSLATION] Hello [COLLOQUIAL] Hi [SIMPLIFIED] Hi";
+        let entry = run(full).unwrap();
+        assert_eq!(entry.sections.len(), 6);
+
+        // Fallback answers are not cached either.
+        assert!(cache_entry(&target, "llama3", entry.sections, &entry.result, true).is_none());
+    }
 }
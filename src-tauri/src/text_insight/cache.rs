@@ -0,0 +1,176 @@
+//! Cache of finished analyses, so selecting the same text again replays the
+//! previous result instead of calling the model.
+//!
+//! Entries are content-addressed: the key hashes the normalized text, its
+//! detected language, the prompt as rendered without the text (template,
+//! preset sections and learner profile) and the model. Editing any of those
+//! simply misses the cache. Entries share the size and age limits of the
+//! audio cache in `AppSettings`.
+
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::db::Database;
+use crate::error::VeyaError;
+use crate::llm_client::Message;
+use crate::settings::AppSettings;
+
+/// Bumped when the stored format changes, so older entries stop matching.
+const CACHE_FORMAT_VERSION: &str = "1";
+
+/// A finished analysis as it is replayed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedAnalysis {
+    /// Model that produced the analysis.
+    pub model: String,
+    /// (section key, content) pairs in display order.
+    pub sections: Vec<(String, String)>,
+    /// The result saved to the query record.
+    pub result: serde_json::Value,
+}
+
+/// Where a finished analysis should be cached.
+#[derive(Debug, Clone)]
+pub struct CacheTarget {
+    pub key: String,
+    /// Model the analysis is requested from. Answers from a fallback model
+    /// are not cached, so a hit always reflects the requested model.
+    pub model: String,
+}
+
+/// Collapse whitespace runs (including line breaks) so that reselecting the
+/// same sentence with different wrapping still hits.
+pub fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Hash of the prompt rendered with an empty text: changes whenever the
+/// template, preset, language or learner profile does.
+pub fn prompt_fingerprint(messages: &[Message]) -> String {
+    let mut hasher = Sha256::new();
+    for message in messages {
+        hasher.update(message.role.as_bytes());
+        hasher.update([0]);
+        hasher.update(message.content.as_bytes());
+        hasher.update([0]);
+        for image in &message.images {
+            hasher.update(image.data.as_bytes());
+            hasher.update([0]);
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+pub fn cache_key(text: &str, language: &str, prompt_fingerprint: &str, model: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in [CACHE_FORMAT_VERSION, &normalize_text(text), language, prompt_fingerprint, model] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Caching is off when the size limit is zero.
+pub fn is_enabled(settings: &AppSettings) -> bool {
+    settings.cache_max_size_mb > 0
+}
+
+pub fn lookup(db: &Database, key: &str) -> Result<Option<CachedAnalysis>, VeyaError> {
+    let Some(row) = db.get_analysis_cache_entry(key)? else {
+        return Ok(None);
+    };
+    let decode_err = |e: serde_json::Error| VeyaError::StorageError(format!("Corrupt analysis cache entry: {e}"));
+    Ok(Some(CachedAnalysis {
+        model: row.model,
+        sections: serde_json::from_str(&row.sections).map_err(decode_err)?,
+        result: serde_json::from_str(&row.result).map_err(decode_err)?,
+    }))
+}
+
+/// Store an analysis, then prune the cache to the configured limits.
+pub fn store(db: &Database, key: &str, entry: &CachedAnalysis, settings: &AppSettings) -> Result<(), VeyaError> {
+    let sections = serde_json::to_string(&entry.sections)
+        .map_err(|e| VeyaError::StorageError(format!("Failed to encode cached analysis: {e}")))?;
+    db.put_analysis_cache_entry(key, &entry.model, &sections, &entry.result.to_string())?;
+    db.prune_analysis_cache(settings.cache_max_size_mb.saturating_mul(1_024 * 1_024), settings.cache_auto_clean_days)?;
+    Ok(())
+}
+
+// ── Tauri Commands ───────────────────────────────────────────────
+
+/// Drop every cached analysis. Returns the number of entries removed.
+#[tauri::command]
+pub async fn clear_analysis_cache(db: tauri::State<'_, Arc<Database>>) -> Result<usize, VeyaError> {
+    db.clear_analysis_cache()
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_db() -> (Database, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let db = Database::open(dir.path().to_path_buf()).unwrap();
+        (db, dir)
+    }
+
+    fn entry(content: &str) -> CachedAnalysis {
+        CachedAnalysis {
+            model: "gpt-4o".into(),
+            sections: vec![("original".into(), content.into()), ("translation".into(), "hi".into())],
+            result: serde_json::json!({ "original": content, "translation": "hi" }),
+        }
+    }
+
+    #[test]
+    fn key_ignores_whitespace_but_not_prompt_or_model() {
+        let fingerprint = prompt_fingerprint(&[Message { role: "system".into(), content: "Analyze".into(), images: Vec::new() }]);
+        let key = cache_key("Hola  mundo\n", "es", &fingerprint, "gpt-4o");
+        assert_eq!(key, cache_key(" Hola\nmundo", "es", &fingerprint, "gpt-4o"));
+        assert_ne!(key, cache_key("Hola mundo", "es", &fingerprint, "gpt-4o-mini"));
+        assert_ne!(key, cache_key("Hola mundo", "pt", &fingerprint, "gpt-4o"));
+
+        let edited = prompt_fingerprint(&[Message { role: "system".into(), content: "Analyze briefly".into(), images: Vec::new() }]);
+        assert_ne!(key, cache_key("Hola mundo", "es", &edited, "gpt-4o"));
+    }
+
+    #[test]
+    fn store_and_lookup_roundtrip() {
+        let (db, _dir) = test_db();
+        let settings = AppSettings::default();
+        assert_eq!(lookup(&db, "k1").unwrap(), None);
+
+        store(&db, "k1", &entry("Hola"), &settings).unwrap();
+        assert_eq!(lookup(&db, "k1").unwrap(), Some(entry("Hola")));
+        assert_eq!(db.clear_analysis_cache().unwrap(), 1);
+        assert_eq!(lookup(&db, "k1").unwrap(), None);
+    }
+
+    #[test]
+    fn prune_evicts_least_recently_used_over_size_limit() {
+        let (db, _dir) = test_db();
+        store(&db, "old", &entry("Hola"), &AppSettings::default()).unwrap();
+        store(&db, "new", &entry("Adiós"), &AppSettings::default()).unwrap();
+        db.with_conn(|conn| {
+            conn.execute("UPDATE analysis_cache SET last_hit_at = datetime('now', '-1 hour') WHERE key = 'old'", [])
+        })
+        .unwrap();
+
+        let newest_size = db.get_analysis_cache_entry("new").unwrap().unwrap().size_bytes as u64;
+        assert_eq!(db.prune_analysis_cache(newest_size, 30).unwrap(), 1);
+        assert!(lookup(&db, "old").unwrap().is_none());
+        assert!(lookup(&db, "new").unwrap().is_some());
+    }
+
+    #[test]
+    fn prune_removes_expired_entries() {
+        let (db, _dir) = test_db();
+        store(&db, "k1", &entry("Hola"), &AppSettings::default()).unwrap();
+        db.with_conn(|conn| conn.execute("UPDATE analysis_cache SET created_at = datetime('now', '-31 days')", []))
+            .unwrap();
+        assert_eq!(db.prune_analysis_cache(u64::MAX, 30).unwrap(), 1);
+    }
+}
@@ -0,0 +1,417 @@
+//! Clipboard-watch trigger mode: analyze text the user copies, for apps where
+//! selection events never arrive.
+//!
+//! The mode is opt-in through `AppSettings` and can require the copy to be
+//! made with Alt (Option) held, or twice in a row ("double copy"). Copies from
+//! excluded apps, password managers by default, and from Veya itself are
+//! ignored. The watcher re-reads the settings every few seconds, so the mode
+//! can be switched on and off without a restart.
+
+use std::time::{Duration, Instant};
+
+use crate::settings::ClipboardTrigger;
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+pub(super) use watcher::{ClipboardWatcher, WATCH_TICK};
+
+/// A second copy of the same text within this window completes a double copy.
+pub const DOUBLE_COPY_WINDOW: Duration = Duration::from_millis(800);
+/// Apps and clipboard managers often set the clipboard more than once per
+/// copy; identical copies closer together than this count as one.
+const DUPLICATE_COPY_GAP: Duration = Duration::from_millis(100);
+
+/// One copy seen on the system clipboard.
+#[derive(Debug, Clone)]
+pub struct ClipboardCopy {
+    pub text: String,
+    /// Names or identifiers of the app that was frontmost when copying;
+    /// empty when the platform doesn't say.
+    pub app_ids: Vec<String>,
+    /// Whether Alt (Option on macOS) was held.
+    pub modifier_held: bool,
+}
+
+/// Decides which copies trigger an analysis, and when.
+#[derive(Debug)]
+pub struct CopyGesture {
+    trigger: ClipboardTrigger,
+    debounce: Duration,
+    last_copy: Option<(String, Instant)>,
+    pending: Option<(String, Instant)>,
+}
+
+impl CopyGesture {
+    pub fn new(trigger: ClipboardTrigger, debounce: Duration) -> Self {
+        Self {
+            trigger,
+            debounce,
+            last_copy: None,
+            pending: None,
+        }
+    }
+
+    pub fn copied(&mut self, text: &str, modifier_held: bool, now: Instant) {
+        let repeat_gap = self
+            .last_copy
+            .as_ref()
+            .filter(|(last, _)| last == text)
+            .map(|(_, at)| now.duration_since(*at));
+        if repeat_gap.is_some_and(|gap| gap < DUPLICATE_COPY_GAP) {
+            return;
+        }
+        self.last_copy = Some((text.to_string(), now));
+
+        let triggered = match self.trigger {
+            ClipboardTrigger::AnyCopy => true,
+            ClipboardTrigger::WithModifier => modifier_held,
+            ClipboardTrigger::DoubleCopy => repeat_gap.is_some_and(|gap| gap <= DOUBLE_COPY_WINDOW),
+        };
+        if triggered {
+            self.pending = Some((text.to_string(), now));
+            // A third copy starts a new gesture rather than completing another.
+            if self.trigger == ClipboardTrigger::DoubleCopy {
+                self.last_copy = None;
+            }
+        } else if self.pending.as_ref().is_some_and(|(pending, _)| pending != text) {
+            // The clipboard moved on before the debounce ran out.
+            self.pending = None;
+        }
+    }
+
+    /// The triggered text once the debounce interval has passed.
+    pub fn poll(&mut self, now: Instant) -> Option<String> {
+        match &self.pending {
+            Some((_, at)) if now.duration_since(*at) >= self.debounce => self.pending.take().map(|(text, _)| text),
+            _ => None,
+        }
+    }
+}
+
+/// Whether any exclusion entry appears (case-insensitively) in one of the
+/// copying app's names or identifiers.
+pub fn is_excluded(app_ids: &[String], excluded: &[String]) -> bool {
+    let app_ids: Vec<String> = app_ids.iter().map(|id| id.to_lowercase()).collect();
+    excluded
+        .iter()
+        .map(|entry| entry.trim().to_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| app_ids.iter().any(|id| id.contains(&entry)))
+}
+
+// ── Watcher ──────────────────────────────────────────────────────
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+mod watcher {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::db::Database;
+    use crate::settings::AppSettings;
+    use crate::text_insight::selection::SelectionOptions;
+
+    #[cfg(target_os = "linux")]
+    use crate::text_insight::linux_selection::LinuxClipboard as ClipboardSource;
+    #[cfg(target_os = "macos")]
+    use super::macos_pasteboard::MacClipboard as ClipboardSource;
+
+    /// How often the clipboard is checked.
+    pub const WATCH_TICK: Duration = Duration::from_millis(100);
+    const SETTINGS_REFRESH: Duration = Duration::from_secs(2);
+    /// Wait before retrying a clipboard that couldn't be opened.
+    const REOPEN_DELAY: Duration = Duration::from_secs(30);
+
+    pub struct ClipboardWatcher {
+        db: Arc<Database>,
+        /// Veya's own identifiers, so its copy buttons don't trigger analyses.
+        own_app_ids: Vec<String>,
+        options: SelectionOptions,
+        settings: Option<AppSettings>,
+        loaded_at: Instant,
+        gesture: CopyGesture,
+        source: Option<ClipboardSource>,
+        reopen_at: Option<Instant>,
+    }
+
+    impl ClipboardWatcher {
+        pub fn new(db: Arc<Database>, own_app_ids: Vec<String>) -> Self {
+            let defaults = AppSettings::default();
+            Self {
+                db,
+                own_app_ids,
+                options: SelectionOptions::default(),
+                settings: None,
+                loaded_at: Instant::now(),
+                gesture: gesture_for(&defaults),
+                source: None,
+                reopen_at: None,
+            }
+        }
+
+        /// Check the clipboard once; returns text to analyze, if any.
+        pub fn tick(&mut self) -> Option<String> {
+            let now = Instant::now();
+            self.refresh_settings(now);
+            let settings = self.settings.as_ref().filter(|s| s.clipboard_watch_enabled)?;
+
+            if self.source.is_none() && self.reopen_at.is_none_or(|at| now >= at) {
+                match ClipboardSource::open() {
+                    Ok(source) => {
+                        log::info!("Clipboard watch started");
+                        self.source = Some(source);
+                    }
+                    Err(e) => {
+                        log::warn!("Clipboard watch unavailable: {e}");
+                        self.reopen_at = Some(now + REOPEN_DELAY);
+                    }
+                }
+            }
+            let source = self.source.as_mut()?;
+
+            match source.poll(self.options.max_chars * 4) {
+                Ok(Some(copy)) => {
+                    let excluded = is_excluded(&copy.app_ids, &settings.clipboard_watch_excluded_apps)
+                        || is_excluded(&copy.app_ids, &self.own_app_ids);
+                    if !excluded {
+                        self.gesture.copied(&copy.text, copy.modifier_held, now);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log::warn!("Clipboard watch stopped: {e}");
+                    self.source = None;
+                    self.reopen_at = Some(now + REOPEN_DELAY);
+                }
+            }
+
+            let text = self.gesture.poll(now)?;
+            self.options.within_length(&text).map(str::to_string)
+        }
+
+        fn refresh_settings(&mut self, now: Instant) {
+            if self.settings.is_some() && now.duration_since(self.loaded_at) < SETTINGS_REFRESH {
+                return;
+            }
+            self.loaded_at = now;
+            let latest = match AppSettings::load(&self.db) {
+                Ok(latest) => latest,
+                Err(e) => {
+                    log::warn!("Failed to load clipboard watch settings: {e}");
+                    return;
+                }
+            };
+            let gesture_changed = self.settings.as_ref().is_none_or(|current| {
+                current.clipboard_watch_trigger != latest.clipboard_watch_trigger
+                    || current.clipboard_watch_debounce_ms != latest.clipboard_watch_debounce_ms
+            });
+            if gesture_changed {
+                self.gesture = gesture_for(&latest);
+            }
+            if !latest.clipboard_watch_enabled {
+                // Release the clipboard (and wl-paste) while the mode is off.
+                self.source = None;
+                self.reopen_at = None;
+            }
+            self.settings = Some(latest);
+        }
+    }
+
+    fn gesture_for(settings: &AppSettings) -> CopyGesture {
+        CopyGesture::new(
+            settings.clipboard_watch_trigger,
+            Duration::from_millis(settings.clipboard_watch_debounce_ms.into()),
+        )
+    }
+}
+
+// ── macOS: NSPasteboard ──────────────────────────────────────────
+
+#[cfg(target_os = "macos")]
+mod macos_pasteboard {
+    use super::ClipboardCopy;
+    use crate::error::VeyaError;
+    use objc::rc::autoreleasepool;
+    use objc::runtime::{Class, Object, BOOL, NO};
+    use objc::{msg_send, sel, sel_impl};
+    use std::ffi::CString;
+
+    /// NSEventModifierFlagOption.
+    const OPTION_KEY_FLAG: usize = 1 << 19;
+    const TEXT_TYPE: &str = "public.utf8-plain-text";
+    /// Markers password managers put on their copies (nspasteboard.org).
+    const CONCEALED_TYPES: &[&str] = &["org.nspasteboard.ConcealedType", "org.nspasteboard.TransientType"];
+
+    /// The general pasteboard, polled through its change count, which goes up
+    /// with every copy.
+    pub struct MacClipboard {
+        change_count: isize,
+    }
+
+    impl MacClipboard {
+        pub fn open() -> Result<Self, VeyaError> {
+            let change_count = autoreleasepool(|| unsafe { general_pasteboard().map(|pb| change_count(pb)) })?;
+            Ok(Self { change_count })
+        }
+
+        pub fn poll(&mut self, max_bytes: usize) -> Result<Option<ClipboardCopy>, VeyaError> {
+            autoreleasepool(|| unsafe {
+                let pasteboard = general_pasteboard()?;
+                let count = change_count(pasteboard);
+                if count == self.change_count {
+                    return Ok(None);
+                }
+                self.change_count = count;
+                if CONCEALED_TYPES.iter().any(|t| has_type(pasteboard, t)) {
+                    return Ok(None);
+                }
+
+                let text: *mut Object = msg_send![pasteboard, stringForType: nsstring(TEXT_TYPE)];
+                let text = nsstring_to_rust(text);
+                if text.is_empty() || text.len() > max_bytes {
+                    return Ok(None);
+                }
+                Ok(Some(ClipboardCopy {
+                    text,
+                    app_ids: frontmost_app_ids(),
+                    modifier_held: option_held(),
+                }))
+            })
+        }
+    }
+
+    unsafe fn general_pasteboard() -> Result<*mut Object, VeyaError> {
+        let cls = Class::get("NSPasteboard")
+            .ok_or_else(|| VeyaError::Generic("NSPasteboard class not found".into()))?;
+        let pasteboard: *mut Object = msg_send![cls, generalPasteboard];
+        if pasteboard.is_null() {
+            return Err(VeyaError::Generic("No general pasteboard".into()));
+        }
+        Ok(pasteboard)
+    }
+
+    unsafe fn change_count(pasteboard: *mut Object) -> isize {
+        msg_send![pasteboard, changeCount]
+    }
+
+    unsafe fn has_type(pasteboard: *mut Object, pasteboard_type: &str) -> bool {
+        let types: *mut Object = msg_send![pasteboard, types];
+        if types.is_null() {
+            return false;
+        }
+        let contains: BOOL = msg_send![types, containsObject: nsstring(pasteboard_type)];
+        contains != NO
+    }
+
+    /// Localized name and bundle identifier of the frontmost app.
+    unsafe fn frontmost_app_ids() -> Vec<String> {
+        let Some(cls) = Class::get("NSWorkspace") else {
+            return Vec::new();
+        };
+        let workspace: *mut Object = msg_send![cls, sharedWorkspace];
+        let app: *mut Object = msg_send![workspace, frontmostApplication];
+        if app.is_null() {
+            return Vec::new();
+        }
+        let name: *mut Object = msg_send![app, localizedName];
+        let bundle_id: *mut Object = msg_send![app, bundleIdentifier];
+        [nsstring_to_rust(name), nsstring_to_rust(bundle_id)]
+            .into_iter()
+            .filter(|id| !id.is_empty())
+            .collect()
+    }
+
+    unsafe fn option_held() -> bool {
+        let Some(cls) = Class::get("NSEvent") else {
+            return false;
+        };
+        let flags: usize = msg_send![cls, modifierFlags];
+        flags & OPTION_KEY_FLAG != 0
+    }
+
+    unsafe fn nsstring(value: &str) -> *mut Object {
+        let cls = Class::get("NSString").expect("NSString class");
+        let value = CString::new(value).unwrap_or_default();
+        msg_send![cls, stringWithUTF8String: value.as_ptr()]
+    }
+
+    /// Convert an NSString pointer to a Rust String.
+    unsafe fn nsstring_to_rust(ns: *mut Object) -> String {
+        if ns.is_null() {
+            return String::new();
+        }
+        let utf8: *const std::os::raw::c_char = msg_send![ns, UTF8String];
+        if utf8.is_null() {
+            return String::new();
+        }
+        std::ffi::CStr::from_ptr(utf8).to_string_lossy().into_owned()
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn any_copy_triggers_after_the_debounce() {
+        let start = Instant::now();
+        let mut gesture = CopyGesture::new(ClipboardTrigger::AnyCopy, ms(300));
+        gesture.copied("hello", false, start);
+        assert_eq!(gesture.poll(start + ms(200)), None);
+        assert_eq!(gesture.poll(start + ms(300)), Some("hello".into()));
+        assert_eq!(gesture.poll(start + ms(600)), None);
+
+        // Copying something else before the debounce runs out replaces it.
+        gesture.copied("first", false, start + ms(1000));
+        gesture.copied("second", false, start + ms(1200));
+        assert_eq!(gesture.poll(start + ms(1400)), None);
+        assert_eq!(gesture.poll(start + ms(1500)), Some("second".into()));
+    }
+
+    #[test]
+    fn modifier_trigger_ignores_plain_copies() {
+        let start = Instant::now();
+        let mut gesture = CopyGesture::new(ClipboardTrigger::WithModifier, ms(0));
+        gesture.copied("plain", false, start);
+        assert_eq!(gesture.poll(start + ms(10)), None);
+        gesture.copied("with alt", true, start + ms(500));
+        assert_eq!(gesture.poll(start + ms(500)), Some("with alt".into()));
+    }
+
+    #[test]
+    fn double_copy_needs_the_same_text_twice_in_the_window() {
+        let start = Instant::now();
+        let mut gesture = CopyGesture::new(ClipboardTrigger::DoubleCopy, ms(0));
+
+        gesture.copied("hola", false, start);
+        assert_eq!(gesture.poll(start + ms(300)), None);
+        // A clipboard manager re-setting the clipboard right away isn't a second copy.
+        gesture.copied("hola", false, start + ms(20));
+        assert_eq!(gesture.poll(start + ms(300)), None);
+        gesture.copied("hola", false, start + ms(400));
+        assert_eq!(gesture.poll(start + ms(400)), Some("hola".into()));
+
+        // Too slow.
+        gesture.copied("adiós", false, start + ms(2000));
+        gesture.copied("adiós", false, start + ms(3000));
+        assert_eq!(gesture.poll(start + ms(3000)), None);
+
+        // Different texts.
+        gesture.copied("uno", false, start + ms(5000));
+        gesture.copied("dos", false, start + ms(5200));
+        assert_eq!(gesture.poll(start + ms(5200)), None);
+    }
+
+    #[test]
+    fn exclusions_match_app_names_and_identifiers() {
+        let excluded = vec!["1password".to_string(), " ".to_string()];
+        assert!(is_excluded(&["1Password 7".into(), "com.agilebits.onepassword7".into()], &excluded));
+        assert!(is_excluded(&["com.1password.1password".into()], &excluded));
+        assert!(!is_excluded(&["Safari".into(), "com.apple.Safari".into()], &excluded));
+        assert!(!is_excluded(&[], &excluded));
+    }
+}
@@ -0,0 +1,513 @@
+//! Linux text selection listener.
+//!
+//! On X11 the PRIMARY selection is watched through XFixes selection-owner
+//! events and read with a UTF8_STRING conversion once it has settled. Wayland
+//! only hands the primary selection to focused clients, so there it is polled
+//! through `wl-paste --primary` (wl-clipboard), which uses the data-control
+//! protocol where the compositor offers it. Under XWayland without
+//! wl-clipboard, the X11 path still sees selections made in X clients.
+//!
+//! The same machinery watches the clipboard (the X11 CLIPBOARD selection or
+//! `wl-paste --watch`) for clipboard-watch mode.
+
+use std::cell::Cell;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use x11rb::connection::Connection;
+use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, KeyButMask, Window, WindowClass,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, NONE};
+
+use super::clipboard_watch::ClipboardCopy;
+use super::selection::{SelectionFilter, SelectionOptions, SettleTimer};
+use super::TextInsightListener;
+use crate::error::VeyaError;
+
+/// How often the X11 loop checks for events and settled selections.
+const X11_TICK: Duration = Duration::from_millis(50);
+/// How long the selection owner gets to answer a conversion request.
+const X11_CONVERT_TIMEOUT: Duration = Duration::from_secs(1);
+/// How often the Wayland primary selection is read.
+const WAYLAND_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Changes `wl-paste --watch` reports this soon after starting are the
+/// clipboard it found, not a copy.
+const WAYLAND_WATCH_GRACE: Duration = Duration::from_millis(500);
+
+enum Backend {
+    X11(Box<X11Selection>),
+    Wayland,
+}
+
+impl Backend {
+    /// wl-paste on Wayland sessions, else the X server in `DISPLAY`.
+    fn connect() -> Result<Self, VeyaError> {
+        let wayland = is_wayland_session();
+        if wayland && wl_paste_available() {
+            return Ok(Self::Wayland);
+        }
+        if wayland {
+            log::warn!("wl-paste not found; install wl-clipboard to follow selections in Wayland apps");
+        }
+        X11Selection::connect("PRIMARY").map(|selection| Self::X11(Box::new(selection)))
+    }
+}
+
+impl TextInsightListener {
+    /// Start the Linux selection listener on a background thread.
+    pub(super) fn start_linux_listener(&self) -> Result<(), VeyaError> {
+        let backend = Backend::connect()?;
+        let listener = TextInsightListener {
+            app_handle: self.app_handle.clone(),
+        };
+        let options = SelectionOptions::default();
+
+        std::thread::Builder::new()
+            .name("veya-selection".into())
+            .spawn(move || {
+                let on_text = |text| listener.on_text_selected(text);
+                let result = match backend {
+                    Backend::X11(selection) => {
+                        log::info!("X11 PRIMARY selection listener started");
+                        selection.watch(options, on_text)
+                    }
+                    Backend::Wayland => {
+                        log::info!("Wayland primary selection listener started");
+                        watch_wayland(options, on_text);
+                        Ok(())
+                    }
+                };
+                if let Err(e) = result {
+                    log::error!("Selection listener stopped: {e}");
+                }
+            })
+            .map_err(|e| VeyaError::Generic(format!("Failed to start selection listener: {e}")))?;
+        Ok(())
+    }
+}
+
+// ── X11 ──────────────────────────────────────────────────────────
+
+fn x11_error(e: impl std::fmt::Display) -> VeyaError {
+    VeyaError::Generic(format!("X11 selection: {e}"))
+}
+
+/// An X11 selection (PRIMARY or CLIPBOARD) watched through XFixes.
+pub(super) struct X11Selection {
+    conn: RustConnection,
+    root: Window,
+    /// Invisible window that receives the converted selection.
+    window: Window,
+    selection: Atom,
+    utf8_string: Atom,
+    property: Atom,
+    incr: Atom,
+    net_active_window: Atom,
+    /// Set when the owner changed while a conversion was in flight.
+    missed_change: Cell<bool>,
+}
+
+impl X11Selection {
+    pub(super) fn connect(selection_name: &str) -> Result<Self, VeyaError> {
+        let (conn, screen_num) = x11rb::connect(None).map_err(x11_error)?;
+        conn.xfixes_query_version(5, 0)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(|e| x11_error(format!("XFixes unavailable: {e}")))?;
+
+        let root = conn.setup().roots[screen_num].root;
+        let window = conn.generate_id().map_err(x11_error)?;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )
+        .map_err(x11_error)?;
+
+        let intern = |name: &str| -> Result<Atom, VeyaError> {
+            Ok(conn
+                .intern_atom(false, name.as_bytes())
+                .map_err(x11_error)?
+                .reply()
+                .map_err(x11_error)?
+                .atom)
+        };
+        let selection = intern(selection_name)?;
+        let utf8_string = intern("UTF8_STRING")?;
+        let property = intern("VEYA_SELECTION")?;
+        let incr = intern("INCR")?;
+        let net_active_window = intern("_NET_ACTIVE_WINDOW")?;
+
+        conn.xfixes_select_selection_input(
+            window,
+            selection,
+            SelectionEventMask::SET_SELECTION_OWNER
+                | SelectionEventMask::SELECTION_WINDOW_DESTROY
+                | SelectionEventMask::SELECTION_CLIENT_CLOSE,
+        )
+        .map_err(x11_error)?;
+        conn.flush().map_err(x11_error)?;
+
+        Ok(Self {
+            conn,
+            root,
+            window,
+            selection,
+            utf8_string,
+            property,
+            incr,
+            net_active_window,
+            missed_change: Cell::new(false),
+        })
+    }
+
+    /// Whether the selection changed hands since the last call. Apps take
+    /// ownership again for every new selection or copy, even of the same text.
+    pub(super) fn take_changes(&self) -> Result<bool, VeyaError> {
+        let mut changed = self.missed_change.replace(false);
+        while let Some(event) = self.conn.poll_for_event().map_err(x11_error)? {
+            changed |= matches!(event, Event::XfixesSelectionNotify(_));
+        }
+        Ok(changed)
+    }
+
+    /// Report settled selections until the connection fails.
+    fn watch(&self, options: SelectionOptions, mut on_text: impl FnMut(String)) -> Result<(), VeyaError> {
+        let mut filter = SelectionFilter::new(options);
+        let mut timer = SettleTimer::new(filter.options().debounce);
+        let max_bytes = filter.options().max_chars * 4;
+
+        loop {
+            if self.take_changes()? {
+                timer.changed(Instant::now());
+            }
+            if timer.settled(Instant::now()) {
+                let text = self.read(max_bytes)?;
+                if let Some(text) = text.and_then(|t| filter.accept(&t)) {
+                    on_text(text);
+                }
+            }
+            std::thread::sleep(X11_TICK);
+        }
+    }
+
+    /// Ask the selection owner for its text. `None` when there is no owner,
+    /// it doesn't answer in time, or the text is longer than `max_bytes`.
+    pub(super) fn read(&self, max_bytes: usize) -> Result<Option<String>, VeyaError> {
+        self.conn
+            .convert_selection(self.window, self.selection, self.utf8_string, self.property, x11rb::CURRENT_TIME)
+            .map_err(x11_error)?;
+        self.conn.flush().map_err(x11_error)?;
+
+        let deadline = Instant::now() + X11_CONVERT_TIMEOUT;
+        let notify = loop {
+            match self.conn.poll_for_event().map_err(x11_error)? {
+                Some(Event::SelectionNotify(notify)) if notify.requestor == self.window => break notify,
+                // The selection moved on while we were reading it.
+                Some(Event::XfixesSelectionNotify(_)) => self.missed_change.set(true),
+                Some(_) => {}
+                None if Instant::now() >= deadline => return Ok(None),
+                None => std::thread::sleep(Duration::from_millis(5)),
+            }
+        };
+        if notify.property == NONE {
+            return Ok(None);
+        }
+
+        let length = u32::try_from(max_bytes.div_ceil(4) + 1).unwrap_or(u32::MAX);
+        let reply = self
+            .conn
+            .get_property(true, self.window, self.property, AtomEnum::ANY, 0, length)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?;
+        if reply.type_ == self.incr || reply.bytes_after > 0 {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8_lossy(&reply.value).into_owned()))
+    }
+
+    /// WM_CLASS instance and class names of the focused window.
+    pub(super) fn active_window_class(&self) -> Vec<String> {
+        let lookup = || -> Result<Vec<String>, VeyaError> {
+            let active = self
+                .conn
+                .get_property(false, self.root, self.net_active_window, AtomEnum::WINDOW, 0, 1)
+                .map_err(x11_error)?
+                .reply()
+                .map_err(x11_error)?;
+            let Some(window) = active.value32().and_then(|mut ids| ids.next()).filter(|w| *w != NONE) else {
+                return Ok(Vec::new());
+            };
+            let class = self
+                .conn
+                .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)
+                .map_err(x11_error)?
+                .reply()
+                .map_err(x11_error)?;
+            Ok(class
+                .value
+                .split(|b| *b == 0)
+                .filter(|name| !name.is_empty())
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .collect())
+        };
+        lookup().unwrap_or_default()
+    }
+
+    /// Whether Alt is held down right now.
+    pub(super) fn alt_held(&self) -> bool {
+        self.conn
+            .query_pointer(self.root)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .is_some_and(|pointer| pointer.mask.contains(KeyButMask::MOD1))
+    }
+}
+
+// ── Wayland ──────────────────────────────────────────────────────
+
+fn wl_paste_available() -> bool {
+    Command::new("wl-paste").arg("--version").output().is_ok_and(|o| o.status.success())
+}
+
+pub(crate) fn is_wayland_session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some_and(|v| !v.is_empty())
+}
+
+/// Text of the primary selection or the clipboard, via wl-paste.
+fn read_wayland(primary: bool) -> Option<String> {
+    let mut command = Command::new("wl-paste");
+    if primary {
+        command.arg("--primary");
+    }
+    let output = command.args(["--no-newline", "--type", "text"]).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Poll the primary selection forever. Whatever is selected at startup is
+/// taken as already seen.
+fn watch_wayland(options: SelectionOptions, mut on_text: impl FnMut(String)) {
+    let mut filter = SelectionFilter::new(options);
+    let mut timer = SettleTimer::new(filter.options().debounce);
+    let mut last_seen = read_wayland(true);
+
+    loop {
+        std::thread::sleep(WAYLAND_POLL_INTERVAL);
+        let text = read_wayland(true);
+        let now = Instant::now();
+        if text != last_seen {
+            last_seen = text;
+            timer.changed(now);
+        }
+        if timer.settled(now) {
+            if let Some(text) = last_seen.as_deref().and_then(|t| filter.accept(t)) {
+                on_text(text);
+            }
+        }
+    }
+}
+
+/// Clipboard changes reported by `wl-paste --watch`, which runs a command
+/// (here `echo`) on every copy, even of the same text.
+pub(super) struct WaylandClipboardWatch {
+    child: Child,
+    changes: mpsc::Receiver<()>,
+    started_at: Instant,
+}
+
+impl WaylandClipboardWatch {
+    fn spawn() -> Result<Self, VeyaError> {
+        let mut child = Command::new("wl-paste")
+            .args(["--watch", "echo"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| VeyaError::Generic(format!("Failed to run wl-paste: {e}")))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| VeyaError::Generic("wl-paste has no output".into()))?;
+
+        let (tx, changes) = mpsc::channel();
+        std::thread::spawn(move || {
+            for _ in BufReader::new(stdout).lines() {
+                if tx.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            child,
+            changes,
+            started_at: Instant::now(),
+        })
+    }
+
+    fn take_changes(&self) -> Result<bool, VeyaError> {
+        let mut changed = false;
+        loop {
+            match self.changes.try_recv() {
+                Ok(()) => changed = true,
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    return Err(VeyaError::Generic("wl-paste --watch exited".into()))
+                }
+            }
+        }
+        // wl-paste reports the clipboard it finds at startup as a change.
+        Ok(changed && self.started_at.elapsed() >= WAYLAND_WATCH_GRACE)
+    }
+}
+
+impl Drop for WaylandClipboardWatch {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// ── Clipboard ────────────────────────────────────────────────────
+
+/// The clipboard, for clipboard-watch mode: the X11 CLIPBOARD selection, or
+/// `wl-paste --watch` on Wayland. Wayland doesn't tell which app copied or
+/// whether a modifier was held, so there app exclusions don't apply and the
+/// modifier trigger never fires.
+pub(super) enum LinuxClipboard {
+    X11(Box<X11Selection>),
+    Wayland(WaylandClipboardWatch),
+}
+
+impl LinuxClipboard {
+    pub(super) fn open() -> Result<Self, VeyaError> {
+        if is_wayland_session() && wl_paste_available() {
+            return WaylandClipboardWatch::spawn().map(Self::Wayland);
+        }
+        X11Selection::connect("CLIPBOARD").map(|selection| Self::X11(Box::new(selection)))
+    }
+
+    /// The latest copy since the previous call, if any.
+    pub(super) fn poll(&mut self, max_bytes: usize) -> Result<Option<ClipboardCopy>, VeyaError> {
+        match self {
+            Self::X11(selection) => {
+                if !selection.take_changes()? {
+                    return Ok(None);
+                }
+                // Look at the copying app before the owner is asked for the text.
+                let app_ids = selection.active_window_class();
+                let modifier_held = selection.alt_held();
+                Ok(selection.read(max_bytes)?.map(|text| ClipboardCopy {
+                    text,
+                    app_ids,
+                    modifier_held,
+                }))
+            }
+            Self::Wayland(watch) => {
+                if !watch.take_changes()? {
+                    return Ok(None);
+                }
+                Ok(read_wayland(false).map(|text| ClipboardCopy {
+                    text,
+                    app_ids: Vec::new(),
+                    modifier_held: false,
+                }))
+            }
+        }
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11rb::wrapper::ConnectionExt as _;
+    use x11rb::protocol::xproto::{EventMask, PropMode, SelectionNotifyEvent, SELECTION_NOTIFY_EVENT};
+
+    /// Own PRIMARY with `text` and answer conversion requests until `rounds`
+    /// requests have been served.
+    fn serve_primary(text: &str, rounds: usize) {
+        let (conn, screen_num) = x11rb::connect(None).unwrap();
+        let root = conn.setup().roots[screen_num].root;
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )
+        .unwrap();
+        conn.set_selection_owner(window, AtomEnum::PRIMARY.into(), x11rb::CURRENT_TIME).unwrap();
+        conn.flush().unwrap();
+
+        for _ in 0..rounds {
+            let Event::SelectionRequest(request) = conn.wait_for_event().unwrap() else {
+                continue;
+            };
+            conn.change_property8(PropMode::REPLACE, request.requestor, request.property, request.target, text.as_bytes())
+                .unwrap();
+            let notify = SelectionNotifyEvent {
+                response_type: SELECTION_NOTIFY_EVENT,
+                sequence: 0,
+                time: request.time,
+                requestor: request.requestor,
+                selection: request.selection,
+                target: request.target,
+                property: request.property,
+            };
+            conn.send_event(false, request.requestor, EventMask::NO_EVENT, notify).unwrap();
+            conn.flush().unwrap();
+        }
+    }
+
+    /// Run with an X server, e.g. `xvfb-run cargo test -- --ignored primary_selection`.
+    #[test]
+    #[ignore = "needs an X server (Xvfb)"]
+    fn x11_primary_selection_is_debounced_and_deduplicated() {
+        let selection = X11Selection::connect("PRIMARY").unwrap();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = selection.watch(SelectionOptions::default(), |text| tx.send(text).unwrap());
+        });
+        std::thread::sleep(Duration::from_millis(200));
+
+        std::thread::spawn(|| serve_primary("  rate limit  ", 1));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(3)).unwrap(), "rate limit");
+
+        // Re-selecting the same text is not analyzed again.
+        std::thread::spawn(|| serve_primary("rate limit", 1));
+        assert!(rx.recv_timeout(Duration::from_secs(1)).is_err());
+
+        // Too short to be worth analyzing.
+        std::thread::spawn(|| serve_primary("a", 1));
+        assert!(rx.recv_timeout(Duration::from_secs(1)).is_err());
+
+        std::thread::spawn(|| serve_primary("primary selection", 1));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(3)).unwrap(), "primary selection");
+    }
+}
@@ -0,0 +1,394 @@
+//! Named analysis presets: which sections an analysis produces, in what
+//! order, and what the model is told to write in each.
+//!
+//! Built-in presets live here; user presets are stored in SQLite. The tag the
+//! model writes is derived from the section key ("exampleSentences" →
+//! `[EXAMPLE_SENTENCES]`), so a preset only declares keys, titles and
+//! instructions.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::sections::SectionSpec;
+use crate::db::{AnalysisPresetRow, Database};
+use crate::error::VeyaError;
+
+/// Preset used when none is requested. Its sections match the JSON-mode
+/// analysis, so it is the only preset that can run in JSON mode.
+pub const STANDARD_PRESET_ID: &str = "standard";
+
+const WORD_STUDY_PRESET_ID: &str = "word_study";
+
+/// Upper bound on sections per preset, to keep responses (and prompts) sane.
+const MAX_SECTIONS: usize = 12;
+
+// ── Types ────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PresetSection {
+    /// Key used on chunks and in the stored result, e.g. "exampleSentences".
+    pub key: String,
+    /// Heading shown above the section.
+    pub title: String,
+    /// What the model should write in the section.
+    pub instruction: String,
+}
+
+impl PresetSection {
+    fn new(key: &str, title: &str, instruction: &str) -> Self {
+        Self {
+            key: key.into(),
+            title: title.into(),
+            instruction: instruction.into(),
+        }
+    }
+
+    /// Tag the model writes for this section, without brackets.
+    pub fn tag(&self) -> String {
+        section_tag(&self.key)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisPreset {
+    pub id: String,
+    pub name: String,
+    pub sections: Vec<PresetSection>,
+    /// Built-in presets cannot be edited or deleted.
+    pub builtin: bool,
+    /// When the preset was last edited (None for built-ins).
+    pub updated_at: Option<String>,
+}
+
+impl AnalysisPreset {
+    /// The six sections of the default analysis.
+    pub fn standard() -> Self {
+        Self::builtin(
+            STANDARD_PRESET_ID,
+            "Standard",
+            vec![
+                PresetSection::new("original", "Original", "The original text as-is"),
+                PresetSection::new(
+                    "wordByWord",
+                    "Word by word",
+                    "Word-by-word or character-by-character explanation with meanings",
+                ),
+                PresetSection::new(
+                    "structure",
+                    "Structure",
+                    "Grammatical structure analysis (sentence patterns, parts of speech)",
+                ),
+                PresetSection::new(
+                    "translation",
+                    "Translation",
+                    "Accurate translation to the user's target language (named below)",
+                ),
+                PresetSection::new(
+                    "colloquial",
+                    "Colloquial",
+                    "A more colloquial/conversational version of the same meaning",
+                ),
+                PresetSection::new(
+                    "simplified",
+                    "Simplified",
+                    "A simplified version using easier vocabulary",
+                ),
+            ],
+        )
+    }
+
+    /// Vocabulary-focused sections: where words come from and how they are used.
+    fn word_study() -> Self {
+        Self::builtin(
+            WORD_STUDY_PRESET_ID,
+            "Word study",
+            vec![
+                PresetSection::new("original", "Original", "The original text as-is"),
+                PresetSection::new(
+                    "etymology",
+                    "Etymology",
+                    "Origin and word formation of the key words (roots, affixes, borrowings)",
+                ),
+                PresetSection::new(
+                    "exampleSentences",
+                    "Example sentences",
+                    "Three short example sentences using the key words, each with a translation",
+                ),
+                PresetSection::new(
+                    "register",
+                    "Register",
+                    "How formal the text is and where it would (not) be appropriate",
+                ),
+                PresetSection::new(
+                    "commonMistakes",
+                    "Common mistakes",
+                    "Mistakes learners typically make with these words or patterns, and the correct use",
+                ),
+                PresetSection::new(
+                    "translation",
+                    "Translation",
+                    "Accurate translation to the user's target language (named below)",
+                ),
+            ],
+        )
+    }
+
+    fn builtin(id: &str, name: &str, sections: Vec<PresetSection>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            sections,
+            builtin: true,
+            updated_at: None,
+        }
+    }
+
+    pub fn builtins() -> Vec<Self> {
+        vec![Self::standard(), Self::word_study()]
+    }
+
+    fn from_row(row: AnalysisPresetRow) -> Result<Self, VeyaError> {
+        let sections = serde_json::from_str(&row.sections).map_err(|e| {
+            VeyaError::StorageError(format!("Failed to decode analysis preset {}: {e}", row.id))
+        })?;
+        Ok(Self {
+            id: row.id,
+            name: row.name,
+            sections,
+            builtin: false,
+            updated_at: Some(row.updated_at),
+        })
+    }
+
+    /// A built-in or user preset by ID.
+    pub fn load(db: &Database, id: &str) -> Result<Self, VeyaError> {
+        if let Some(preset) = Self::builtins().into_iter().find(|p| p.id == id) {
+            return Ok(preset);
+        }
+        match db.get_analysis_preset(id)? {
+            Some(row) => Self::from_row(row),
+            None => Err(VeyaError::Generic(format!("Analysis preset {id} not found"))),
+        }
+    }
+
+    /// Sections for the tag parser, in preset order.
+    pub fn section_specs(&self) -> Vec<SectionSpec> {
+        self.sections
+            .iter()
+            .map(|s| SectionSpec::new(&s.key, &s.tag()))
+            .collect()
+    }
+
+    /// The `{{sections}}` prompt variable: one `[TAG] instruction` line per section.
+    pub fn prompt_sections(&self) -> String {
+        self.sections
+            .iter()
+            .map(|s| format!("[{}] {}", s.tag(), s.instruction.trim()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// "exampleSentences" → "EXAMPLE_SENTENCES".
+pub fn section_tag(key: &str) -> String {
+    let mut tag = String::with_capacity(key.len() + 4);
+    for (i, c) in key.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            tag.push('_');
+        }
+        tag.push(c.to_ascii_uppercase());
+    }
+    tag
+}
+
+// ── Core logic (testable without Tauri) ──────────────────────────
+
+/// Built-in presets first, then user presets in creation order.
+pub fn list_presets(db: &Database) -> Result<Vec<AnalysisPreset>, VeyaError> {
+    let mut presets = AnalysisPreset::builtins();
+    for row in db.get_analysis_presets()? {
+        presets.push(AnalysisPreset::from_row(row)?);
+    }
+    Ok(presets)
+}
+
+/// Create a preset (`id` None) or update a user preset.
+pub fn save_preset(
+    db: &Database,
+    id: Option<String>,
+    name: &str,
+    sections: Vec<PresetSection>,
+) -> Result<AnalysisPreset, VeyaError> {
+    let sections: Vec<PresetSection> = sections
+        .into_iter()
+        .map(|s| PresetSection {
+            key: s.key.trim().to_string(),
+            title: s.title.trim().to_string(),
+            instruction: s.instruction.trim().to_string(),
+        })
+        .collect();
+    validate_preset(name, &sections)?;
+
+    let id = match id {
+        Some(id) => {
+            if AnalysisPreset::builtins().iter().any(|p| p.id == id) {
+                return Err(VeyaError::Generic(format!("Built-in preset {id} cannot be edited")));
+            }
+            if db.get_analysis_preset(&id)?.is_none() {
+                return Err(VeyaError::Generic(format!("Analysis preset {id} not found")));
+            }
+            id
+        }
+        None => Uuid::new_v4().to_string(),
+    };
+
+    let json = serde_json::to_string(&sections)
+        .map_err(|e| VeyaError::StorageError(format!("Failed to encode analysis preset: {e}")))?;
+    db.upsert_analysis_preset(&id, name.trim(), &json)?;
+    AnalysisPreset::load(db, &id)
+}
+
+pub fn delete_preset(db: &Database, id: &str) -> Result<(), VeyaError> {
+    if AnalysisPreset::builtins().iter().any(|p| p.id == id) {
+        return Err(VeyaError::Generic(format!("Built-in preset {id} cannot be deleted")));
+    }
+    db.delete_analysis_preset(id)
+}
+
+/// A preset needs a name and 1–12 sections with distinct camelCase keys
+/// (ASCII letters and digits, starting with a lowercase letter), titles and
+/// instructions.
+pub fn validate_preset(name: &str, sections: &[PresetSection]) -> Result<(), VeyaError> {
+    if name.trim().is_empty() {
+        return Err(VeyaError::Generic("Preset name cannot be empty".into()));
+    }
+    if sections.is_empty() || sections.len() > MAX_SECTIONS {
+        return Err(VeyaError::Generic(format!(
+            "A preset needs between 1 and {MAX_SECTIONS} sections"
+        )));
+    }
+
+    for (index, section) in sections.iter().enumerate() {
+        let key = section.key.as_str();
+        let valid_key = key.starts_with(|c: char| c.is_ascii_lowercase())
+            && key.chars().all(|c| c.is_ascii_alphanumeric());
+        if !valid_key {
+            return Err(VeyaError::Generic(format!(
+                "Section key \"{key}\" must be camelCase letters and digits, e.g. exampleSentences"
+            )));
+        }
+        if sections[..index].iter().any(|s| s.tag() == section.tag()) {
+            return Err(VeyaError::Generic(format!("Section key \"{key}\" is used twice")));
+        }
+        if section.title.trim().is_empty() || section.instruction.trim().is_empty() {
+            return Err(VeyaError::Generic(format!(
+                "Section \"{key}\" needs a title and an instruction"
+            )));
+        }
+    }
+    Ok(())
+}
+
+// ── Tauri Commands ───────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn list_analysis_presets(
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<Vec<AnalysisPreset>, VeyaError> {
+    list_presets(&db)
+}
+
+/// Create a preset, or update the user preset with the given `id`.
+#[tauri::command]
+pub async fn save_analysis_preset(
+    id: Option<String>,
+    name: String,
+    sections: Vec<PresetSection>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<AnalysisPreset, VeyaError> {
+    save_preset(&db, id, &name, sections)
+}
+
+#[tauri::command]
+pub async fn delete_analysis_preset(
+    id: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<(), VeyaError> {
+    delete_preset(&db, &id)
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_insight::sections::default_sections;
+    use tempfile::TempDir;
+
+    fn test_db() -> (Database, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let db = Database::open(dir.path().to_path_buf()).unwrap();
+        (db, dir)
+    }
+
+    fn section(key: &str) -> PresetSection {
+        PresetSection::new(key, "Title", "Write something")
+    }
+
+    #[test]
+    fn standard_preset_matches_default_sections() {
+        assert_eq!(AnalysisPreset::standard().section_specs(), default_sections());
+        assert_eq!(section_tag("exampleSentences"), "EXAMPLE_SENTENCES");
+        assert_eq!(section_tag("register"), "REGISTER");
+    }
+
+    #[test]
+    fn prompt_lists_tags_with_instructions() {
+        let prompt = AnalysisPreset::word_study().prompt_sections();
+        let lines: Vec<_> = prompt.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[2].starts_with("[EXAMPLE_SENTENCES] Three short"));
+    }
+
+    #[test]
+    fn validate_rejects_bad_keys_and_duplicates() {
+        assert!(validate_preset("Mine", &[section("etymology")]).is_ok());
+        assert!(validate_preset(" ", &[section("etymology")]).is_err());
+        assert!(validate_preset("Mine", &[]).is_err());
+        assert!(validate_preset("Mine", &[section("Etymology")]).is_err());
+        assert!(validate_preset("Mine", &[section("word_list")]).is_err());
+        assert!(validate_preset("Mine", &[section("notes"), section("notes")]).is_err());
+
+        let mut untitled = section("notes");
+        untitled.title.clear();
+        assert!(validate_preset("Mine", &[untitled]).is_err());
+    }
+
+    #[test]
+    fn save_update_and_delete_user_preset() {
+        let (db, _dir) = test_db();
+        let created = save_preset(&db, None, " Mine ", vec![section("etymology"), section("register")]).unwrap();
+        assert_eq!(created.name, "Mine");
+        assert!(!created.builtin);
+        assert_eq!(AnalysisPreset::load(&db, &created.id).unwrap(), created);
+
+        let updated = save_preset(&db, Some(created.id.clone()), "Mine", vec![section("register")]).unwrap();
+        assert_eq!(updated.sections.len(), 1);
+
+        let listed = list_presets(&db).unwrap();
+        assert_eq!(listed.len(), AnalysisPreset::builtins().len() + 1);
+        assert_eq!(listed[0].id, STANDARD_PRESET_ID);
+
+        delete_preset(&db, &created.id).unwrap();
+        assert!(AnalysisPreset::load(&db, &created.id).is_err());
+    }
+
+    #[test]
+    fn builtins_are_read_only() {
+        let (db, _dir) = test_db();
+        assert!(save_preset(&db, Some(STANDARD_PRESET_ID.into()), "Mine", vec![section("notes")]).is_err());
+        assert!(delete_preset(&db, STANDARD_PRESET_ID).is_err());
+        assert!(save_preset(&db, Some("missing".into()), "Mine", vec![section("notes")]).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+//! Incremental parser for the `[TAG]`-delimited sections of an analysis stream.
+//!
+//! Deltas may split a tag anywhere (`"[WORD_"` + `"BY_WORD]"`), so text that
+//! could still turn into a tag is held back until the next delta decides it.
+//! Whitespace around tags is trimmed so each section can be copied as-is.
+
+/// One section the model is asked to produce.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionSpec {
+    /// Key used on chunks and in the stored result, e.g. "wordByWord".
+    pub key: String,
+    /// Tag the model writes, without brackets, e.g. "WORD_BY_WORD".
+    pub tag: String,
+}
+
+impl SectionSpec {
+    pub fn new(key: &str, tag: &str) -> Self {
+        Self {
+            key: key.into(),
+            tag: tag.into(),
+        }
+    }
+
+    fn marker(&self) -> String {
+        format!("[{}]", self.tag)
+    }
+}
+
+/// The six sections of the standard analysis prompt.
+pub fn default_sections() -> Vec<SectionSpec> {
+    vec![
+        SectionSpec::new("original", "ORIGINAL"),
+        SectionSpec::new("wordByWord", "WORD_BY_WORD"),
+        SectionSpec::new("structure", "STRUCTURE"),
+        SectionSpec::new("translation", "TRANSLATION"),
+        SectionSpec::new("colloquial", "COLLOQUIAL"),
+        SectionSpec::new("simplified", "SIMPLIFIED"),
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SectionEvent {
+    Start(String),
+    /// Section key (None for text before the first tag) and content.
+    Delta(Option<String>, String),
+    End(String),
+}
+
+#[derive(Debug)]
+pub struct SectionParser {
+    sections: Vec<SectionSpec>,
+    markers: Vec<String>,
+    /// Unprocessed text; may end with the beginning of a tag.
+    buffer: String,
+    current: Option<usize>,
+    /// True until the first non-whitespace character of the current section.
+    at_section_start: bool,
+    /// Whitespace held back until more content arrives (dropped at section end).
+    pending_ws: String,
+    /// Accumulated content per section, aligned with `sections`.
+    contents: Vec<Option<String>>,
+}
+
+impl SectionParser {
+    pub fn new(sections: Vec<SectionSpec>) -> Self {
+        let markers = sections.iter().map(SectionSpec::marker).collect();
+        let contents = vec![None; sections.len()];
+        Self {
+            sections,
+            markers,
+            buffer: String::new(),
+            current: None,
+            at_section_start: true,
+            pending_ws: String::new(),
+            contents,
+        }
+    }
+
+    /// Discard all state, e.g. when the stream restarts after a retry.
+    pub fn reset(&mut self) {
+        *self = Self::new(std::mem::take(&mut self.sections));
+    }
+
+    /// Feed a delta and return the events it completes.
+    pub fn push(&mut self, delta: &str) -> Vec<SectionEvent> {
+        self.buffer.push_str(delta);
+        let mut events = Vec::new();
+
+        while let Some((pos, index)) = self.find_marker() {
+            let before: String = self.buffer.drain(..pos).collect();
+            self.emit_text(&before, &mut events);
+            self.end_section(&mut events);
+            self.buffer.drain(..self.markers[index].len());
+
+            self.current = Some(index);
+            self.at_section_start = true;
+            events.push(SectionEvent::Start(self.sections[index].key.clone()));
+        }
+
+        let emit_len = self.buffer.len() - self.partial_marker_len();
+        let text: String = self.buffer.drain(..emit_len).collect();
+        self.emit_text(&text, &mut events);
+        events
+    }
+
+    /// Flush held-back text and close the open section at end of stream.
+    pub fn finish(&mut self) -> Vec<SectionEvent> {
+        let mut events = Vec::new();
+        let text = std::mem::take(&mut self.buffer);
+        self.emit_text(&text, &mut events);
+        self.end_section(&mut events);
+        events
+    }
+
+    /// Parsed sections in spec order, as a JSON object keyed by section key.
+    /// Sections the model never produced are omitted.
+    pub fn to_json(&self) -> serde_json::Value {
+        let map: serde_json::Map<String, serde_json::Value> = self
+            .sections
+            .iter()
+            .zip(&self.contents)
+            .filter_map(|(spec, content)| {
+                content
+                    .as_ref()
+                    .map(|c| (spec.key.clone(), serde_json::Value::String(c.clone())))
+            })
+            .collect();
+        serde_json::Value::Object(map)
+    }
+
+    /// (key, content) of every section the model produced, in spec order.
+    pub fn produced_sections(&self) -> Vec<(String, String)> {
+        self.sections
+            .iter()
+            .zip(&self.contents)
+            .filter_map(|(spec, content)| Some((spec.key.clone(), content.clone()?)))
+            .collect()
+    }
+
+    /// Whether the model produced every section, each with some content.
+    pub fn is_complete(&self) -> bool {
+        self.contents.iter().all(|c| c.as_deref().is_some_and(|c| !c.trim().is_empty()))
+    }
+
+    /// Content of the section with the given key, if it was produced.
+    pub fn section(&self, key: &str) -> Option<&str> {
+        let index = self.sections.iter().position(|s| s.key == key)?;
+        self.contents[index].as_deref()
+    }
+
+    // ── Internal helpers ──────────────────────────────────────────
+
+    /// Earliest complete marker in the buffer: (byte offset, section index).
+    fn find_marker(&self) -> Option<(usize, usize)> {
+        self.markers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, marker)| self.buffer.find(marker.as_str()).map(|pos| (pos, index)))
+            .min()
+    }
+
+    /// Length of a trailing `[...` that is a proper prefix of some marker.
+    /// Markers contain a single '[', so only the last one can start a tag.
+    fn partial_marker_len(&self) -> usize {
+        let Some(pos) = self.buffer.rfind('[') else {
+            return 0;
+        };
+        let tail = &self.buffer[pos..];
+        if self.markers.iter().any(|m| m.len() > tail.len() && m.starts_with(tail)) {
+            tail.len()
+        } else {
+            0
+        }
+    }
+
+    fn emit_text(&mut self, text: &str, events: &mut Vec<SectionEvent>) {
+        let mut text = text;
+        if self.at_section_start {
+            text = text.trim_start();
+            if text.is_empty() {
+                return;
+            }
+            self.at_section_start = false;
+        }
+
+        let trimmed = text.trim_end();
+        if trimmed.is_empty() {
+            self.pending_ws.push_str(text);
+            return;
+        }
+        let mut out = std::mem::take(&mut self.pending_ws);
+        out.push_str(trimmed);
+        self.pending_ws = text[trimmed.len()..].to_string();
+
+        let key = self.current.map(|index| {
+            self.contents[index].get_or_insert_with(String::new).push_str(&out);
+            self.sections[index].key.clone()
+        });
+        events.push(SectionEvent::Delta(key, out));
+    }
+
+    fn end_section(&mut self, events: &mut Vec<SectionEvent>) {
+        self.pending_ws.clear();
+        if let Some(index) = self.current.take() {
+            self.contents[index].get_or_insert_with(String::new);
+            events.push(SectionEvent::End(self.sections[index].key.clone()));
+        }
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = "[ORIGINAL] Ojalá que llueva\n[WORD_BY_WORD] ojalá = hopefully\nque = that\n\n[TRANSLATION] I hope it rains\n";
+
+    fn parse_in_chunks(input: &str, size: usize) -> (SectionParser, Vec<SectionEvent>) {
+        let mut parser = SectionParser::new(default_sections());
+        let chars: Vec<char> = input.chars().collect();
+        let mut events: Vec<SectionEvent> = chars
+            .chunks(size)
+            .flat_map(|c| parser.push(&c.iter().collect::<String>()))
+            .collect();
+        events.extend(parser.finish());
+        (parser, events)
+    }
+
+    #[test]
+    fn splits_sections_and_trims_whitespace() {
+        let (parser, events) = parse_in_chunks(RESPONSE, RESPONSE.len());
+        assert_eq!(parser.section("original"), Some("Ojalá que llueva"));
+        assert_eq!(parser.section("wordByWord"), Some("ojalá = hopefully\nque = that"));
+        assert_eq!(parser.section("translation"), Some("I hope it rains"));
+        assert_eq!(parser.section("structure"), None);
+        let keys: Vec<_> = parser.produced_sections().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["original", "wordByWord", "translation"]);
+
+        assert_eq!(events.first(), Some(&SectionEvent::Start("original".into())));
+        assert_eq!(events.last(), Some(&SectionEvent::End("translation".into())));
+        let ends = events.iter().filter(|e| matches!(e, SectionEvent::End(_))).count();
+        assert_eq!(ends, 3);
+    }
+
+    #[test]
+    fn tags_split_across_deltas_are_detected() {
+        let (whole, _) = parse_in_chunks(RESPONSE, RESPONSE.len());
+        for size in 1..8 {
+            let (parser, events) = parse_in_chunks(RESPONSE, size);
+            assert_eq!(parser.to_json(), whole.to_json(), "chunk size {size}");
+            assert!(events.iter().all(|e| match e {
+                SectionEvent::Delta(_, text) => !text.contains('['),
+                _ => true,
+            }));
+        }
+    }
+
+    #[test]
+    fn unknown_brackets_are_content() {
+        let (parser, _) = parse_in_chunks("[STRUCTURE] S + V [verb] + O [", 3);
+        assert_eq!(parser.section("structure"), Some("S + V [verb] + O ["));
+    }
+
+    #[test]
+    fn text_before_first_tag_has_no_section() {
+        let mut parser = SectionParser::new(default_sections());
+        let events = parser.push("Sure! [ORIGINAL] hi");
+        assert_eq!(events[0], SectionEvent::Delta(None, "Sure!".into()));
+        assert_eq!(events[1], SectionEvent::Start("original".into()));
+        assert_eq!(parser.to_json(), serde_json::json!({ "original": "hi" }));
+    }
+
+    #[test]
+    fn reset_discards_partial_output() {
+        let mut parser = SectionParser::new(default_sections());
+        parser.push("[ORIGINAL] partial");
+        parser.reset();
+        parser.push("[TRANSLATION] again");
+        parser.finish();
+        assert_eq!(parser.to_json(), serde_json::json!({ "translation": "again" }));
+    }
+}
@@ -0,0 +1,152 @@
+//! Turning raw selection changes into analysis triggers.
+//!
+//! Platform listeners report every change they see: X11 fires an event each
+//! time the selection owner re-asserts it (once per drag step in some apps)
+//! and polled sources report the same text over and over. Before a change
+//! reaches `on_text_selected` it has to settle for the debounce interval,
+//! differ from the text analyzed last and have a sensible length.
+
+use std::time::{Duration, Instant};
+
+/// How long a selection has to stay unchanged before it is analyzed.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+/// Shorter selections are usually stray clicks.
+pub const DEFAULT_MIN_CHARS: usize = 2;
+/// Longer selections are usually "select all" rather than something to study.
+pub const DEFAULT_MAX_CHARS: usize = 2_000;
+
+#[derive(Debug, Clone)]
+pub struct SelectionOptions {
+    pub debounce: Duration,
+    /// Bounds on the trimmed text, in characters.
+    pub min_chars: usize,
+    pub max_chars: usize,
+}
+
+impl Default for SelectionOptions {
+    fn default() -> Self {
+        Self {
+            debounce: DEFAULT_DEBOUNCE,
+            min_chars: DEFAULT_MIN_CHARS,
+            max_chars: DEFAULT_MAX_CHARS,
+        }
+    }
+}
+
+impl SelectionOptions {
+    /// The trimmed text, if its length is within bounds.
+    pub fn within_length<'a>(&self, text: &'a str) -> Option<&'a str> {
+        let text = text.trim();
+        let chars = text.chars().count();
+        (chars >= self.min_chars.max(1) && chars <= self.max_chars).then_some(text)
+    }
+}
+
+/// Length filter and dedupe for settled selections.
+#[derive(Debug)]
+pub struct SelectionFilter {
+    options: SelectionOptions,
+    last_accepted: Option<String>,
+}
+
+impl SelectionFilter {
+    pub fn new(options: SelectionOptions) -> Self {
+        Self {
+            options,
+            last_accepted: None,
+        }
+    }
+
+    pub fn options(&self) -> &SelectionOptions {
+        &self.options
+    }
+
+    /// The trimmed text if it should be analyzed: within the length bounds
+    /// and not the same as the previously accepted selection.
+    pub fn accept(&mut self, text: &str) -> Option<String> {
+        let text = self.options.within_length(text)?;
+        if self.last_accepted.as_deref() == Some(text) {
+            return None;
+        }
+        self.last_accepted = Some(text.to_string());
+        Some(text.to_string())
+    }
+}
+
+/// Tracks when the selection last changed and reports, once, when it has
+/// stayed unchanged for the debounce interval.
+#[derive(Debug)]
+pub struct SettleTimer {
+    debounce: Duration,
+    changed_at: Option<Instant>,
+}
+
+impl SettleTimer {
+    pub fn new(debounce: Duration) -> Self {
+        Self {
+            debounce,
+            changed_at: None,
+        }
+    }
+
+    pub fn changed(&mut self, now: Instant) {
+        self.changed_at = Some(now);
+    }
+
+    /// True once per change, as soon as the debounce interval has passed.
+    pub fn settled(&mut self, now: Instant) -> bool {
+        match self.changed_at {
+            Some(at) if now.duration_since(at) >= self.debounce => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_applies_length_bounds_to_trimmed_text() {
+        let mut filter = SelectionFilter::new(SelectionOptions {
+            min_chars: 2,
+            max_chars: 5,
+            ..SelectionOptions::default()
+        });
+        assert_eq!(filter.accept("  a \n"), None);
+        assert_eq!(filter.accept("   "), None);
+        assert_eq!(filter.accept("toolong"), None);
+        assert_eq!(filter.accept(" 你好 "), Some("你好".into()));
+    }
+
+    #[test]
+    fn filter_drops_repeats_of_the_last_accepted_text() {
+        let mut filter = SelectionFilter::new(SelectionOptions::default());
+        assert_eq!(filter.accept("hello"), Some("hello".into()));
+        assert_eq!(filter.accept("hello\n"), None);
+        // A rejected selection in between doesn't reset the dedupe.
+        assert_eq!(filter.accept("x"), None);
+        assert_eq!(filter.accept("hello"), None);
+        assert_eq!(filter.accept("world"), Some("world".into()));
+        assert_eq!(filter.accept("hello"), Some("hello".into()));
+    }
+
+    #[test]
+    fn timer_settles_once_after_the_last_change() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut timer = SettleTimer::new(ms(300));
+        assert!(!timer.settled(start));
+
+        timer.changed(start);
+        timer.changed(start + ms(200));
+        assert!(!timer.settled(start + ms(400)));
+        assert!(timer.settled(start + ms(500)));
+        assert!(!timer.settled(start + ms(900)));
+    }
+}
@@ -0,0 +1,195 @@
+//! Typed analysis result requested in JSON mode.
+//!
+//! Unlike the tagged sections, the per-token glosses keep word, part of speech
+//! and meaning apart, so learning records and exports can use them directly.
+//! For display the result is rendered into the same sections as the tagged
+//! output, so the floating window handles both modes alike.
+
+use serde::{Deserialize, Serialize};
+
+use crate::llm_client::structured::{JsonSchema, StructuredOutput};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructuredAnalysis {
+    pub original: String,
+    pub tokens: Vec<TokenGloss>,
+    pub grammar_notes: Vec<GrammarNote>,
+    pub translation: String,
+    pub colloquial: String,
+    pub simplified: String,
+}
+
+/// One word (or character, for CJK) of the original text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenGloss {
+    /// The token exactly as it appears in the text.
+    pub text: String,
+    /// Dictionary form, e.g. "llover" for "llueva".
+    pub lemma: String,
+    /// Part of speech, e.g. "verb".
+    pub part_of_speech: String,
+    /// Meaning in the user's target language.
+    pub gloss: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrammarNote {
+    /// The pattern or construction, e.g. "ojalá que + subjunctive".
+    pub pattern: String,
+    pub explanation: String,
+}
+
+impl StructuredOutput for StructuredAnalysis {
+    fn json_schema() -> JsonSchema {
+        let string = serde_json::json!({ "type": "string" });
+        let object = |properties: serde_json::Value, required: &[&str]| {
+            serde_json::json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+                "additionalProperties": false,
+            })
+        };
+
+        let token = object(
+            serde_json::json!({
+                "text": string,
+                "lemma": string,
+                "partOfSpeech": string,
+                "gloss": string,
+            }),
+            &["text", "lemma", "partOfSpeech", "gloss"],
+        );
+        let note = object(
+            serde_json::json!({ "pattern": string, "explanation": string }),
+            &["pattern", "explanation"],
+        );
+        let schema = object(
+            serde_json::json!({
+                "original": string,
+                "tokens": { "type": "array", "items": token },
+                "grammarNotes": { "type": "array", "items": note },
+                "translation": string,
+                "colloquial": string,
+                "simplified": string,
+            }),
+            &["original", "tokens", "grammarNotes", "translation", "colloquial", "simplified"],
+        );
+
+        JsonSchema {
+            name: "text_analysis".into(),
+            description: "Structured language analysis of the given text".into(),
+            schema,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.tokens.is_empty() {
+            return Err("tokens is empty".into());
+        }
+        if let Some(index) = self
+            .tokens
+            .iter()
+            .position(|t| t.text.trim().is_empty() || t.gloss.trim().is_empty())
+        {
+            return Err(format!("tokens[{index}] is missing its text or gloss"));
+        }
+        if self.translation.trim().is_empty() {
+            return Err("translation is empty".into());
+        }
+        Ok(())
+    }
+}
+
+impl StructuredAnalysis {
+    /// Render the result as (section key, text) pairs matching the keys of
+    /// `sections::default_sections`. Empty sections are skipped.
+    pub fn to_sections(&self) -> Vec<(&'static str, String)> {
+        let word_by_word = self
+            .tokens
+            .iter()
+            .map(|t| {
+                let lemma = if t.lemma.is_empty() || t.lemma.to_lowercase() == t.text.to_lowercase() {
+                    String::new()
+                } else {
+                    format!(" ← {}", t.lemma)
+                };
+                format!("{}{lemma} ({}) = {}", t.text, t.part_of_speech, t.gloss)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let structure = self
+            .grammar_notes
+            .iter()
+            .map(|n| format!("{}: {}", n.pattern, n.explanation))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        [
+            ("original", self.original.trim().to_string()),
+            ("wordByWord", word_by_word),
+            ("structure", structure),
+            ("translation", self.translation.trim().to_string()),
+            ("colloquial", self.colloquial.trim().to_string()),
+            ("simplified", self.simplified.trim().to_string()),
+        ]
+        .into_iter()
+        .filter(|(_, text)| !text.is_empty())
+        .collect()
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::structured::parse_output;
+
+    const REPLY: &str = r#"{
+        "original": "Ojalá que llueva",
+        "tokens": [
+            {"text": "Ojalá", "lemma": "ojalá", "partOfSpeech": "interjection", "gloss": "hopefully"},
+            {"text": "que", "lemma": "que", "partOfSpeech": "conjunction", "gloss": "that"},
+            {"text": "llueva", "lemma": "llover", "partOfSpeech": "verb", "gloss": "it rains"}
+        ],
+        "grammarNotes": [{"pattern": "ojalá que + subjunctive", "explanation": "expresses a wish"}],
+        "translation": "I hope it rains",
+        "colloquial": "Fingers crossed for rain",
+        "simplified": ""
+    }"#;
+
+    #[test]
+    fn parses_reply_and_renders_sections() {
+        let analysis: StructuredAnalysis = parse_output(REPLY).unwrap();
+        assert_eq!(analysis.tokens[2].lemma, "llover");
+
+        let sections = analysis.to_sections();
+        let keys: Vec<_> = sections.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec!["original", "wordByWord", "structure", "translation", "colloquial"]);
+        assert!(sections[1].1.contains("llueva ← llover (verb) = it rains"));
+        assert!(sections[1].1.starts_with("Ojalá (interjection) = hopefully"));
+    }
+
+    #[test]
+    fn rejects_missing_glosses_and_translation() {
+        let missing_gloss = REPLY.replace(r#""gloss": "that""#, r#""gloss": """#);
+        assert_eq!(
+            parse_output::<StructuredAnalysis>(&missing_gloss).unwrap_err(),
+            "tokens[1] is missing its text or gloss"
+        );
+        let no_translation = REPLY.replace("I hope it rains", " ");
+        assert!(parse_output::<StructuredAnalysis>(&no_translation).is_err());
+    }
+
+    #[test]
+    fn schema_requires_every_field() {
+        let schema = StructuredAnalysis::json_schema().schema;
+        let required = schema["required"].as_array().unwrap();
+        assert_eq!(required.len(), schema["properties"].as_object().unwrap().len());
+        assert_eq!(schema["properties"]["tokens"]["items"]["additionalProperties"], false);
+    }
+}
@@ -2,11 +2,15 @@ use serde::Serialize;
 
 use crate::api_config::ApiProvider;
 use crate::error::VeyaError;
+use crate::learner_profile::primary_subtag;
 use crate::retry::RetryPolicy;
+use crate::usage::UsageRecorder;
 
 /// Configuration for a single TTS service endpoint.
 #[derive(Debug, Clone)]
 pub struct TtsConfig {
+    /// ID of the API config this was built from, used for usage accounting.
+    pub config_id: String,
     pub provider: ApiProvider,
     pub base_url: String,
     pub model_name: String,
@@ -27,6 +31,7 @@ pub struct TtsClient {
     configs: Vec<TtsConfig>,
     http_client: reqwest::Client,
     retry_policy: RetryPolicy,
+    usage_recorder: Option<UsageRecorder>,
 }
 
 impl TtsClient {
@@ -39,9 +44,16 @@ impl TtsClient {
             configs,
             http_client,
             retry_policy,
+            usage_recorder: None,
         }
     }
 
+    /// Record the synthesized character count of every successful request.
+    pub fn with_usage_recorder(mut self, recorder: UsageRecorder) -> Self {
+        self.usage_recorder = Some(recorder);
+        self
+    }
+
     /// Synthesize text to audio bytes, routing to the TTS service
     /// configured for the given language code.
     pub async fn synthesize(
@@ -56,7 +68,8 @@ impl TtsClient {
         let text_owned = text.to_string();
         let opts = options.clone();
 
-        self.retry_policy
+        let audio = self
+            .retry_policy
             .execute(|| {
                 let cfg = config_clone.clone();
                 let cl = client.clone();
@@ -64,7 +77,12 @@ impl TtsClient {
                 let o = opts.clone();
                 async move { Self::synthesize_once(&cfg, &cl, &t, &o).await }
             })
-            .await
+            .await?;
+
+        if let Some(recorder) = &self.usage_recorder {
+            recorder.record_tts(&config.config_id, &config.model_name, text.chars().count() as u64);
+        }
+        Ok(audio)
     }
 
     /// Returns the TTS config for the given language.
@@ -74,12 +92,9 @@ impl TtsClient {
         if let Some(cfg) = self.configs.iter().find(|c| c.language == language) {
             return Ok(cfg);
         }
-        // Try prefix match (e.g. "en" matches "en-US")
-        if let Some(cfg) = self
-            .configs
-            .iter()
-            .find(|c| language.starts_with(&c.language) || c.language.starts_with(language))
-        {
+        // Try primary-subtag match (e.g. "en" matches "en-US", "zh" does not match "zha")
+        let primary = primary_subtag(language);
+        if let Some(cfg) = self.configs.iter().find(|c| primary_subtag(&c.language) == primary) {
             return Ok(cfg);
         }
         // Fallback to first config
@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::db::{Database, ModelPriceRow, UsageAggregateRow};
+use crate::error::VeyaError;
+
+// ── Types ────────────────────────────────────────────────────────
+
+/// Token counts reported by an LLM provider for one request.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn is_empty(&self) -> bool {
+        self.input_tokens == 0 && self.output_tokens == 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageKind {
+    Llm,
+    Tts,
+}
+
+impl UsageKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Llm => "llm",
+            Self::Tts => "tts",
+        }
+    }
+}
+
+/// Time window for a usage summary.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageRange {
+    /// Since local midnight.
+    Today,
+    /// The last 7 days.
+    Week,
+    /// The last 30 days.
+    Month,
+    All,
+}
+
+impl UsageRange {
+    /// SQLite expression for the (UTC) lower bound of `created_at`.
+    pub(crate) fn since_sql(&self) -> &'static str {
+        match self {
+            Self::Today => "datetime('now', 'localtime', 'start of day', 'utc')",
+            Self::Week => "datetime('now', '-7 days')",
+            Self::Month => "datetime('now', '-30 days')",
+            Self::All => "'0000-00-00 00:00:00'",
+        }
+    }
+}
+
+/// User-configured prices for a model, in the user's currency of choice.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelPrice {
+    pub model_name: String,
+    /// Price per 1M input (prompt) tokens.
+    #[serde(default)]
+    pub input_per_million: f64,
+    /// Price per 1M output (completion) tokens.
+    #[serde(default)]
+    pub output_per_million: f64,
+    /// Price per 1M synthesized characters (TTS models).
+    #[serde(default)]
+    pub per_million_chars: f64,
+}
+
+impl ModelPrice {
+    fn from_row(row: &ModelPriceRow) -> Self {
+        Self {
+            model_name: row.model_name.clone(),
+            input_per_million: row.input_per_million,
+            output_per_million: row.output_per_million,
+            per_million_chars: row.per_million_chars,
+        }
+    }
+
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64, characters: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_million
+            + output_tokens as f64 * self.output_per_million
+            + characters as f64 * self.per_million_chars)
+            / 1_000_000.0
+    }
+}
+
+/// Aggregated usage for one API config + model within a range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSummaryEntry {
+    pub api_config_id: String,
+    pub model_name: String,
+    pub kind: UsageKind,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub characters: u64,
+    /// None when no price is configured for the model.
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub range: UsageRange,
+    pub entries: Vec<UsageSummaryEntry>,
+    /// Sum of all entries with a known price.
+    pub total_cost: f64,
+}
+
+// ── Recorder ─────────────────────────────────────────────────────
+
+/// Writes usage records for one API config. Attached to `LlmClient` / `TtsClient`.
+#[derive(Clone)]
+pub struct UsageRecorder {
+    db: Arc<Database>,
+}
+
+impl UsageRecorder {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    pub fn record_llm(&self, api_config_id: &str, model_name: &str, usage: &TokenUsage) {
+        if usage.is_empty() {
+            return;
+        }
+        if let Err(e) = self.db.insert_usage_record(
+            api_config_id,
+            model_name,
+            UsageKind::Llm.as_str(),
+            usage.input_tokens,
+            usage.output_tokens,
+            0,
+        ) {
+            log::warn!("Failed to record LLM usage: {e}");
+        }
+    }
+
+    pub fn record_tts(&self, api_config_id: &str, model_name: &str, characters: u64) {
+        if let Err(e) = self.db.insert_usage_record(
+            api_config_id,
+            model_name,
+            UsageKind::Tts.as_str(),
+            0,
+            0,
+            characters,
+        ) {
+            log::warn!("Failed to record TTS usage: {e}");
+        }
+    }
+}
+
+// ── Core logic (testable without Tauri) ──────────────────────────
+
+pub fn usage_summary(db: &Database, range: UsageRange) -> Result<UsageSummary, VeyaError> {
+    let prices: Vec<ModelPrice> = db.get_model_prices()?.iter().map(ModelPrice::from_row).collect();
+    let rows = db.get_usage_aggregates(range.since_sql())?;
+
+    let entries: Vec<UsageSummaryEntry> = rows
+        .into_iter()
+        .map(|row: UsageAggregateRow| {
+            let cost = prices
+                .iter()
+                .find(|p| p.model_name == row.model_name)
+                .map(|p| p.cost(row.input_tokens, row.output_tokens, row.characters));
+            UsageSummaryEntry {
+                api_config_id: row.api_config_id,
+                model_name: row.model_name,
+                kind: if row.kind == "tts" { UsageKind::Tts } else { UsageKind::Llm },
+                requests: row.requests,
+                input_tokens: row.input_tokens,
+                output_tokens: row.output_tokens,
+                characters: row.characters,
+                cost,
+            }
+        })
+        .collect();
+
+    let total_cost = entries.iter().filter_map(|e| e.cost).sum();
+    Ok(UsageSummary { range, entries, total_cost })
+}
+
+// ── Tauri Commands ───────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn get_usage_summary(
+    range: UsageRange,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<UsageSummary, VeyaError> {
+    usage_summary(&db, range)
+}
+
+#[tauri::command]
+pub async fn get_model_prices(
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<Vec<ModelPrice>, VeyaError> {
+    Ok(db.get_model_prices()?.iter().map(ModelPrice::from_row).collect())
+}
+
+#[tauri::command]
+pub async fn save_model_price(
+    price: ModelPrice,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<(), VeyaError> {
+    db.upsert_model_price(
+        &price.model_name,
+        price.input_per_million,
+        price.output_per_million,
+        price.per_million_chars,
+    )
+}
+
+#[tauri::command]
+pub async fn delete_model_price(
+    model_name: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<(), VeyaError> {
+    db.delete_model_price(&model_name)
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_db() -> (Arc<Database>, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let db = Database::open(dir.path().to_path_buf()).unwrap();
+        (Arc::new(db), dir)
+    }
+
+    #[test]
+    fn summary_aggregates_per_config_and_model() {
+        let (db, _dir) = test_db();
+        let recorder = UsageRecorder::new(db.clone());
+        recorder.record_llm("c1", "gpt-4o", &TokenUsage { input_tokens: 100, output_tokens: 50 });
+        recorder.record_llm("c1", "gpt-4o", &TokenUsage { input_tokens: 200, output_tokens: 25 });
+        recorder.record_tts("c2", "tts-1", 1_000);
+
+        let summary = usage_summary(&db, UsageRange::Today).unwrap();
+        assert_eq!(summary.entries.len(), 2);
+
+        let llm = summary.entries.iter().find(|e| e.kind == UsageKind::Llm).unwrap();
+        assert_eq!(llm.requests, 2);
+        assert_eq!(llm.input_tokens, 300);
+        assert_eq!(llm.output_tokens, 75);
+        assert_eq!(llm.cost, None);
+
+        let tts = summary.entries.iter().find(|e| e.kind == UsageKind::Tts).unwrap();
+        assert_eq!(tts.characters, 1_000);
+    }
+
+    #[test]
+    fn summary_applies_model_prices() {
+        let (db, _dir) = test_db();
+        db.upsert_model_price("gpt-4o", 2.5, 10.0, 0.0).unwrap();
+        db.upsert_model_price("tts-1", 0.0, 0.0, 15.0).unwrap();
+        let recorder = UsageRecorder::new(db.clone());
+        recorder.record_llm("c1", "gpt-4o", &TokenUsage { input_tokens: 1_000_000, output_tokens: 100_000 });
+        recorder.record_tts("c2", "tts-1", 200_000);
+
+        let summary = usage_summary(&db, UsageRange::All).unwrap();
+        let llm = summary.entries.iter().find(|e| e.kind == UsageKind::Llm).unwrap();
+        assert!((llm.cost.unwrap() - 3.5).abs() < 1e-9);
+        assert!((summary.total_cost - 6.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn empty_llm_usage_is_not_recorded() {
+        let (db, _dir) = test_db();
+        UsageRecorder::new(db.clone()).record_llm("c1", "m", &TokenUsage::default());
+        assert!(usage_summary(&db, UsageRange::All).unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn usage_range_serializes_snake_case() {
+        let range: UsageRange = serde_json::from_str("\"week\"").unwrap();
+        assert_eq!(range, UsageRange::Week);
+    }
+}
@@ -2,18 +2,33 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter, Manager};
 
-use crate::api_config::ApiConfig;
+use crate::api_config::{resolve_feature_chain, ModelFeature};
 use crate::db::Database;
 use crate::error::VeyaError;
-use crate::llm_client::{LlmClient, LlmConfig, Message};
+use crate::language_detect::{detect, DetectionHints};
+use crate::learner_profile::LearnerProfile;
+use crate::llm_client::content::ImagePart;
+use crate::llm_client::{GenerationParams, LlmClient, Message};
+use crate::prompt_template::{PromptId, PromptTemplate, PromptVars};
 use crate::retry::RetryPolicy;
-use crate::settings::AppSettings;
+use crate::settings::{AppSettings, CaptureMode, OcrEngineKind};
 use crate::stronghold_store::StrongholdStore;
+use crate::usage::UsageRecorder;
 
 // ── Constants ────────────────────────────────────────────────────
 
 const EVENT_STREAM_CHUNK: &str = "veya://vision-capture/stream-chunk";
 
+/// OCR correction should stay close to the recognized text.
+const OCR_COMPLETION_TEMPERATURE: f32 = 0.1;
+
+/// Transcriptions should be literal too.
+const VISION_TRANSCRIPTION_TEMPERATURE: f32 = 0.1;
+
+/// Longest side of images sent to vision models. Providers downscale
+/// larger images anyway, so sending them only costs time and tokens.
+const MAX_VISION_IMAGE_SIDE: u32 = 2048;
+
 // ── Types ────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +37,14 @@ pub struct CaptureRegion {
     pub y: f64,
     pub width: f64,
     pub height: f64,
+    /// Screenshot pixels per region unit: the display's scale factor when
+    /// the region is in logical points, 1.0 when it is already in pixels.
+    #[serde(default = "default_scale_factor")]
+    pub scale_factor: f64,
+}
+
+fn default_scale_factor() -> f64 {
+    1.0
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,8 +55,17 @@ pub struct VisionCaptureChunk {
     pub content: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_ai_inferred: Option<bool>,
+    /// Lines, words and their boxes, on `ocr_result` from an OCR engine.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ocr: Option<OcrResult>,
 }
 
+pub mod ocr;
+pub mod raster;
+
+use ocr::OcrResult;
+use raster::{OcrPreprocess, Raster};
+
 // ── Platform-specific screenshot capture ─────────────────────────
 
 pub fn capture_screen() -> Result<Vec<u8>, VeyaError> {
@@ -41,57 +73,53 @@ pub fn capture_screen() -> Result<Vec<u8>, VeyaError> {
     {
         macos_capture::capture_full_screen()
     }
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(target_os = "linux")]
+    {
+        linux_capture::capture_full_screen()
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
     {
         Err(VeyaError::OcrFailed("Screen capture not supported on this platform".into()))
     }
 }
 
 pub fn crop_image(image_data: &[u8], region: &CaptureRegion) -> Result<Vec<u8>, VeyaError> {
-    #[cfg(target_os = "macos")]
-    {
-        macos_capture::crop_png(image_data, region)
-    }
-    #[cfg(not(target_os = "macos"))]
-    {
-        let _ = (image_data, region);
-        Err(VeyaError::OcrFailed("Image cropping not supported on this platform".into()))
-    }
+    Raster::decode_png(image_data)?.crop(region)?.encode_png()
 }
 
-// ── Platform-specific OCR ────────────────────────────────────────
-
-pub fn recognize_text(image_data: &[u8]) -> Result<String, VeyaError> {
-    #[cfg(target_os = "macos")]
-    {
-        macos_ocr::recognize(image_data)
-    }
-    #[cfg(not(target_os = "macos"))]
-    {
-        let _ = image_data;
-        Err(VeyaError::OcrFailed("OCR not supported on this platform".into()))
+/// PNG bytes of the cropped region as the OCR engine should see them, and
+/// the rotation deskewing applied to them in degrees.
+pub fn prepare_for_ocr(cropped: &Raster, preprocess: &OcrPreprocess) -> Result<(Vec<u8>, f64), VeyaError> {
+    if preprocess.is_noop() {
+        Ok((cropped.encode_png()?, 0.0))
+    } else {
+        let (image, rotation) = cropped.preprocess(preprocess);
+        Ok((image.encode_png()?, rotation))
     }
 }
 
-// ── AI completion prompt ─────────────────────────────────────────
-
-fn build_ocr_completion_prompt(ocr_text: &str) -> Vec<Message> {
-    let system_prompt = r#"You are an OCR post-processing assistant. The user will provide text recognized by OCR from a screenshot. Your job is to:
+// ── OCR ──────────────────────────────────────────────────────────
 
-1. Fix any obvious OCR errors (misrecognized characters, broken words)
-2. Infer and complete any truncated or partially visible text
-3. Preserve the original structure and formatting
-
-Output your response in this exact format:
-[CORRECTED] The corrected/completed full text
-[INFERRED] A comma-separated list of phrases or words that you inferred or corrected (that were NOT in the original OCR output). If nothing was inferred, write "none".
+/// Recognize text in a PNG with the given engine, hinting the learner's
+/// languages to engines that need them.
+pub fn recognize_text(
+    image_data: &[u8],
+    engine: OcrEngineKind,
+    languages: &[String],
+) -> Result<OcrResult, VeyaError> {
+    ocr::engine_for(engine)?.recognize(image_data, languages)
+}
 
-Be conservative — only infer content when you have high confidence."#;
+// ── AI completion prompt ─────────────────────────────────────────
 
-    vec![
-        Message { role: "system".into(), content: system_prompt.into() },
-        Message { role: "user".into(), content: format!("OCR recognized text:\n{ocr_text}") },
-    ]
+fn build_ocr_completion_prompt(
+    template: &PromptTemplate,
+    ocr_text: &str,
+    profile: &LearnerProfile,
+) -> Vec<Message> {
+    // The learner's languages are the likeliest candidates for ambiguous glyphs.
+    let language = detect(ocr_text, &DetectionHints::for_learner(profile)).language;
+    template.render(&PromptVars::for_learner(ocr_text, &language, profile))
 }
 
 /// Parse the AI completion response to extract corrected text and inferred parts.
@@ -125,40 +153,94 @@ pub fn parse_completion_response(response: &str) -> (String, Vec<String>) {
     (corrected, inferred)
 }
 
-// ── Helper: resolve active vision/text model config ──────────────
+// ── Vision-model transcription ───────────────────────────────────
+
+/// The transcription prompt with the image attached to the user turn. The
+/// learner variables describe the first language being learned.
+fn build_vision_transcription_prompt(
+    template: &PromptTemplate,
+    image_png: &[u8],
+    profile: &LearnerProfile,
+) -> Vec<Message> {
+    let language = ocr::ocr_languages(profile).swap_remove(0);
+    let mut messages = template.render(&PromptVars::for_learner("", &language, profile));
+    if let Some(user) = messages.iter_mut().rev().find(|m| m.role == "user") {
+        user.images.push(ImagePart::png(image_png));
+    }
+    messages
+}
 
-fn resolve_vision_llm_config(
-    db: &Database,
+/// Split a vision-model reply into the transcription and the analysis.
+/// Untagged replies are taken as a bare transcription.
+pub fn parse_transcription_response(response: &str) -> (String, String) {
+    let Some((_, tagged)) = response.split_once("[TRANSCRIPTION]") else {
+        return (response.trim().to_string(), String::new());
+    };
+    let (transcription, analysis) = tagged.split_once("[ANALYSIS]").unwrap_or((tagged, ""));
+    let transcription = transcription.trim();
+    let transcription = if transcription.eq_ignore_ascii_case("none") { "" } else { transcription };
+    (transcription.to_string(), analysis.trim().to_string())
+}
+
+async fn read_with_vision_model(
+    app: &AppHandle,
+    db: &Arc<Database>,
     store: &StrongholdStore,
     settings: &AppSettings,
-) -> Result<(LlmConfig, RetryPolicy), VeyaError> {
-    let rows = db.get_api_configs()?;
-    let config_row = rows
-        .iter()
-        .find(|r| r.model_type == "vision" && r.is_active)
-        .or_else(|| rows.iter().find(|r| r.model_type == "text" && r.is_active))
+    profile: &LearnerProfile,
+    cropped: &Raster,
+) -> Result<(), VeyaError> {
+    let image = cropped.fit_within(MAX_VISION_IMAGE_SIDE).encode_png()?;
+    let chain = resolve_feature_chain(db, store, ModelFeature::VisionTranscription)?;
+    let client = LlmClient::from_chain(chain, RetryPolicy::new(settings.retry_count, 500, 10_000))
         .ok_or_else(|| {
             VeyaError::ModelUnavailable(
-                "No active vision or text model configured. Please add one in Settings.".into(),
+                "No vision model configured. Add one in Settings, or switch the capture mode back to OCR.".into(),
             )
-        })?;
+        })?
+        .with_task_defaults(GenerationParams::with_temperature(VISION_TRANSCRIPTION_TEMPERATURE))
+        .with_usage_recorder(UsageRecorder::new(db.clone()));
+
+    let template = PromptTemplate::load(db, PromptId::VisionTranscription)?;
+    let response = client.chat(build_vision_transcription_prompt(&template, &image, profile)).await?;
+    let (transcription, analysis) = parse_transcription_response(&response);
+    if transcription.is_empty() {
+        return Err(VeyaError::OcrFailed("No text recognized in the selected region".into()));
+    }
 
-    let api_config = ApiConfig::from_row(config_row)?;
-    let api_key = if api_config.is_local {
-        String::new()
-    } else {
-        store.get_api_key(&api_config.id)?.unwrap_or_default()
-    };
+    let _ = app.emit(EVENT_STREAM_CHUNK, VisionCaptureChunk {
+        chunk_type: "ocr_result".into(),
+        content: Some(transcription),
+        is_ai_inferred: Some(true),
+        ocr: None,
+    });
+    if !analysis.is_empty() {
+        let _ = app.emit(EVENT_STREAM_CHUNK, VisionCaptureChunk {
+            chunk_type: "analysis_delta".into(),
+            content: Some(analysis),
+            is_ai_inferred: Some(true),
+            ocr: None,
+        });
+    }
+    Ok(())
+}
+
+// ── Helper: resolve vision/text model client ─────────────────────
+
+/// Build a client for OCR completion: the feature override, else the vision
+/// chain, or the text chain when no vision model is configured.
+fn resolve_vision_llm_client(
+    db: &Database,
+    store: &StrongholdStore,
+    settings: &AppSettings,
+) -> Result<LlmClient, VeyaError> {
+    let chain = resolve_feature_chain(db, store, ModelFeature::OcrCompletion)?;
 
-    Ok((
-        LlmConfig {
-            provider: api_config.provider,
-            base_url: api_config.base_url,
-            model_name: api_config.model_name,
-            api_key,
-        },
-        RetryPolicy::new(settings.retry_count, 500, 10_000),
-    ))
+    LlmClient::from_chain(chain, RetryPolicy::new(settings.retry_count, 500, 10_000)).ok_or_else(|| {
+        VeyaError::ModelUnavailable(
+            "No active vision or text model configured. Please add one in Settings.".into(),
+        )
+    })
 }
 
 // ── Tauri Commands ───────────────────────────────────────────────
@@ -172,24 +254,35 @@ pub async fn start_capture(app: AppHandle) -> Result<(), VeyaError> {
     let screenshot_bytes = capture_screen()?;
     app.manage(CaptureScreenshot(Arc::new(screenshot_bytes)));
 
-    if let Some(overlay) = app.get_webview_window("capture-overlay") {
-        let _ = overlay.show();
-        let _ = overlay.set_focus();
-    } else {
-        use tauri::{WebviewUrl, WebviewWindowBuilder};
-        let _overlay = WebviewWindowBuilder::new(
-            &app,
-            "capture-overlay",
-            WebviewUrl::App("/capture".into()),
-        )
-        .title("Veya Capture")
-        .fullscreen(true)
-        .decorations(false)
-        .always_on_top(true)
-        .skip_taskbar(true)
-        .build()
-        .map_err(|e| VeyaError::OcrFailed(format!("Failed to create capture overlay: {e}")))?;
+    let overlay = match app.get_webview_window("capture-overlay") {
+        Some(overlay) => overlay,
+        None => {
+            use tauri::{WebviewUrl, WebviewWindowBuilder};
+            WebviewWindowBuilder::new(
+                &app,
+                "capture-overlay",
+                WebviewUrl::App("/capture".into()),
+            )
+            .title("Veya Capture")
+            .visible(false)
+            .decorations(false)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .build()
+            .map_err(|e| VeyaError::OcrFailed(format!("Failed to create capture overlay: {e}")))?
+        }
+    };
+
+    // The screenshot is of the primary monitor (X11 and macOS alike), and the
+    // overlay maps selections onto it by its own size, so it has to cover
+    // that monitor rather than wherever the window manager would put it.
+    if let Ok(Some(monitor)) = app.primary_monitor() {
+        let _ = overlay.set_fullscreen(false);
+        let _ = overlay.set_position(*monitor.position());
     }
+    let _ = overlay.set_fullscreen(true);
+    let _ = overlay.show();
+    let _ = overlay.set_focus();
 
     Ok(())
 }
@@ -223,40 +316,63 @@ pub async fn process_capture(
     }
 
     // Crop to the selected region
-    let cropped = crop_image(&image_data, &region)?;
+    let settings = AppSettings::load(&db)?;
+    let profile = LearnerProfile::load(&db)?;
+    let cropped = Raster::decode_png(&image_data)?.crop(&region)?;
+
+    // Vision-model mode: the model reads the image itself
+    if settings.capture_mode == CaptureMode::VisionModel {
+        read_with_vision_model(&app, &db, &store, &settings, &profile, &cropped).await?;
+        emit_done(&app);
+        return Ok(());
+    }
 
-    // Run native OCR
-    let ocr_text = recognize_text(&cropped)?;
-    if ocr_text.trim().is_empty() {
+    // Run OCR off the async runtime, on a cleaned-up copy if configured;
+    // engines may shell out or take a while
+    let (ocr_input, rotation) = prepare_for_ocr(&cropped, &settings.ocr_preprocess)?;
+    let languages = ocr::ocr_languages(&profile);
+    let engine = settings.ocr_engine;
+    let mut ocr_result = tokio::task::spawn_blocking(move || recognize_text(&ocr_input, engine, &languages))
+        .await
+        .map_err(|e| VeyaError::OcrFailed(format!("OCR task failed: {e}")))??;
+    // Boxes are relative to the deskewed copy; map them onto the selection
+    ocr_result.unrotate(rotation, cropped.width, cropped.height);
+    if ocr_result.is_empty() {
         return Err(VeyaError::OcrFailed("No text recognized in the selected region".into()));
     }
 
     // Emit OCR result
+    let ocr_text = ocr_result.text();
     let _ = app.emit(EVENT_STREAM_CHUNK, VisionCaptureChunk {
         chunk_type: "ocr_result".into(),
         content: Some(ocr_text.clone()),
         is_ai_inferred: Some(false),
+        ocr: Some(ocr_result),
     });
 
     // Optionally run AI completion
     if ai_completion {
-        let settings = AppSettings::load(&db)?;
-        let (llm_config, retry_policy) = resolve_vision_llm_config(&db, &store, &settings)?;
-        let client = LlmClient::new(llm_config, retry_policy);
+        let client = resolve_vision_llm_client(&db, &store, &settings)?
+            .with_task_defaults(GenerationParams::with_temperature(OCR_COMPLETION_TEMPERATURE))
+            .with_usage_recorder(UsageRecorder::new(db.inner().clone()));
 
-        match client.chat(build_ocr_completion_prompt(&ocr_text)).await {
+        let template = PromptTemplate::load(&db, PromptId::OcrCompletion)?;
+        let messages = build_ocr_completion_prompt(&template, &ocr_text, &profile);
+        match client.chat(messages).await {
             Ok(response) => {
                 let (corrected, inferred_parts) = parse_completion_response(&response);
                 let _ = app.emit(EVENT_STREAM_CHUNK, VisionCaptureChunk {
                     chunk_type: "ai_completion".into(),
                     content: Some(corrected),
                     is_ai_inferred: Some(true),
+                    ocr: None,
                 });
                 if !inferred_parts.is_empty() {
                     let _ = app.emit(EVENT_STREAM_CHUNK, VisionCaptureChunk {
                         chunk_type: "analysis_delta".into(),
                         content: Some(serde_json::to_string(&inferred_parts).unwrap_or_default()),
                         is_ai_inferred: Some(true),
+                        ocr: None,
                     });
                 }
             }
@@ -265,21 +381,30 @@ pub async fn process_capture(
                     chunk_type: "error".into(),
                     content: Some(format!("AI completion failed: {e}")),
                     is_ai_inferred: None,
+                    ocr: None,
                 });
             }
         }
     }
 
-    // Emit done
+    emit_done(&app);
+    Ok(())
+}
+
+fn emit_done(app: &AppHandle) {
     let _ = app.emit(EVENT_STREAM_CHUNK, VisionCaptureChunk {
         chunk_type: "done".into(),
         content: None,
         is_ai_inferred: None,
+        ocr: None,
     });
-
-    Ok(())
 }
 
+// ── Linux: Screenshot via X11 or xdg-desktop-portal ──────────────
+
+#[cfg(target_os = "linux")]
+mod linux_capture;
+
 // ── macOS: Screenshot via Core Graphics ──────────────────────────
 
 #[cfg(target_os = "macos")]
@@ -291,7 +416,6 @@ mod macos_capture {
     type CGImageRef = *mut c_void;
     type CFDataRef = *const c_void;
     type CFMutableDataRef = *mut c_void;
-    type CGImageSourceRef = *mut c_void;
     type CGImageDestinationRef = *mut c_void;
     type CFStringRef = *const c_void;
 
@@ -310,7 +434,6 @@ mod macos_capture {
 
     extern "C" {
         fn CGWindowListCreateImage(bounds: CGRect, opts: u32, wid: u32, img_opt: u32) -> CGImageRef;
-        fn CGImageCreateWithImageInRect(image: CGImageRef, rect: CGRect) -> CGImageRef;
         fn CGImageRelease(image: CGImageRef);
         fn CGMainDisplayID() -> u32;
         fn CGDisplayPixelsWide(display: u32) -> usize;
@@ -321,12 +444,8 @@ mod macos_capture {
         fn CGImageDestinationAddImage(dest: CGImageDestinationRef, image: CGImageRef, props: *const c_void);
         fn CGImageDestinationFinalize(dest: CGImageDestinationRef) -> bool;
 
-        fn CGImageSourceCreateWithData(data: CFDataRef, opts: *const c_void) -> CGImageSourceRef;
-        fn CGImageSourceCreateImageAtIndex(src: CGImageSourceRef, idx: usize, opts: *const c_void) -> CGImageRef;
-
         // CoreFoundation
         fn CFDataCreateMutable(alloc: *const c_void, cap: isize) -> CFMutableDataRef;
-        fn CFDataCreate(alloc: *const c_void, bytes: *const u8, len: isize) -> CFDataRef;
         fn CFDataGetLength(data: CFDataRef) -> isize;
         fn CFDataGetBytePtr(data: CFDataRef) -> *const u8;
         fn CFRelease(cf: *const c_void);
@@ -351,25 +470,6 @@ mod macos_capture {
         }
     }
 
-    pub fn crop_png(png_data: &[u8], region: &CaptureRegion) -> Result<Vec<u8>, VeyaError> {
-        unsafe {
-            let cg_image = cgimage_from_png(png_data)?;
-            let rect = CGRect {
-                origin: CGPoint { x: region.x, y: region.y },
-                size: CGSize { width: region.width, height: region.height },
-            };
-            let cropped = CGImageCreateWithImageInRect(cg_image, rect);
-            if cropped.is_null() {
-                CGImageRelease(cg_image);
-                return Err(VeyaError::OcrFailed("Failed to crop image".into()));
-            }
-            let result = cgimage_to_png(cropped);
-            CGImageRelease(cropped);
-            CGImageRelease(cg_image);
-            result
-        }
-    }
-
     unsafe fn cgimage_to_png(image: CGImageRef) -> Result<Vec<u8>, VeyaError> {
         let md = CFDataCreateMutable(std::ptr::null(), 0);
         if md.is_null() { return Err(VeyaError::OcrFailed("CFDataCreateMutable failed".into())); }
@@ -390,20 +490,6 @@ mod macos_capture {
         CFRelease(md as _);
         Ok(bytes)
     }
-
-    unsafe fn cgimage_from_png(data: &[u8]) -> Result<CGImageRef, VeyaError> {
-        let cf_data = CFDataCreate(std::ptr::null(), data.as_ptr(), data.len() as isize);
-        if cf_data.is_null() { return Err(VeyaError::OcrFailed("CFDataCreate failed".into())); }
-
-        let src = CGImageSourceCreateWithData(cf_data, std::ptr::null());
-        if src.is_null() { CFRelease(cf_data); return Err(VeyaError::OcrFailed("CGImageSourceCreate failed".into())); }
-
-        let image = CGImageSourceCreateImageAtIndex(src, 0, std::ptr::null());
-        CFRelease(src as _);
-        CFRelease(cf_data);
-        if image.is_null() { return Err(VeyaError::OcrFailed("Failed to decode PNG".into())); }
-        Ok(image)
-    }
 }
 
 // ── macOS: OCR via Vision Framework (using objc crate) ───────────
@@ -411,16 +497,39 @@ mod macos_capture {
 #[cfg(target_os = "macos")]
 mod macos_ocr {
     use super::*;
+    use super::ocr::{BoundingBox, OcrLine, OcrWord};
     use objc::runtime::{Class, Object, BOOL, YES};
     use objc::{msg_send, sel, sel_impl};
     use std::ffi::c_void;
 
+    /// CGRect, normalized to the image with the origin at the bottom left.
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct NormalizedRect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    }
+
+    impl From<NormalizedRect> for BoundingBox {
+        fn from(r: NormalizedRect) -> Self {
+            BoundingBox { x: r.x, y: 1.0 - r.y - r.height, width: r.width, height: r.height }
+        }
+    }
+
+    #[repr(C)]
+    struct NSRange {
+        location: usize,
+        length: usize,
+    }
+
     /// Perform OCR on PNG image bytes using macOS Vision Framework.
-    pub fn recognize(image_data: &[u8]) -> Result<String, VeyaError> {
+    pub fn recognize(image_data: &[u8]) -> Result<OcrResult, VeyaError> {
         unsafe { recognize_inner(image_data) }
     }
 
-    unsafe fn recognize_inner(image_data: &[u8]) -> Result<String, VeyaError> {
+    unsafe fn recognize_inner(image_data: &[u8]) -> Result<OcrResult, VeyaError> {
         // 1. Create NSData from bytes
         let nsdata_cls = Class::get("NSData")
             .ok_or_else(|| VeyaError::OcrFailed("NSData class not found".into()))?;
@@ -487,11 +596,11 @@ mod macos_ocr {
         // 6. Extract results
         let results: *mut Object = msg_send![request, results];
         if results.is_null() {
-            return Ok(String::new());
+            return Ok(OcrResult::default());
         }
 
         let count: usize = msg_send![results, count];
-        let mut text_parts = Vec::new();
+        let mut lines = Vec::new();
 
         for i in 0..count {
             let observation: *mut Object = msg_send![results, objectAtIndex: i];
@@ -505,15 +614,47 @@ mod macos_ocr {
 
             let candidate: *mut Object = msg_send![candidates, objectAtIndex: 0usize];
             let ns_string: *mut Object = msg_send![candidate, string];
-            if !ns_string.is_null() {
-                let text = nsstring_to_rust(ns_string);
-                if !text.is_empty() {
-                    text_parts.push(text);
-                }
-            }
+            let text = nsstring_to_rust(ns_string);
+            if text.is_empty() { continue; }
+
+            let bbox: NormalizedRect = msg_send![observation, boundingBox];
+            let confidence: f32 = msg_send![candidate, confidence];
+            let words = words_of(candidate, &text, bbox.into(), confidence);
+            lines.push(OcrLine { text, bbox: bbox.into(), confidence, words });
         }
 
-        Ok(text_parts.join("\n"))
+        Ok(OcrResult { lines })
+    }
+
+    /// The space-separated words of a recognized line, boxed by Vision.
+    /// Vision only scores whole lines, so words share the line's confidence.
+    unsafe fn words_of(candidate: *mut Object, text: &str, line_box: BoundingBox, confidence: f32) -> Vec<OcrWord> {
+        let mut words = Vec::new();
+        // NSString ranges count UTF-16 code units.
+        let mut offset = 0;
+        for (i, part) in text.split(' ').enumerate() {
+            if i > 0 {
+                offset += 1;
+            }
+            let length = part.encode_utf16().count();
+            if length > 0 {
+                let range = NSRange { location: offset, length };
+                let mut error: *mut Object = std::ptr::null_mut();
+                let observation: *mut Object = msg_send![candidate,
+                    boundingBoxForRange: range
+                    error: &mut error as *mut *mut Object
+                ];
+                let bbox = if observation.is_null() {
+                    line_box
+                } else {
+                    let rect: NormalizedRect = msg_send![observation, boundingBox];
+                    rect.into()
+                };
+                words.push(OcrWord { text: part.to_string(), bbox, confidence });
+            }
+            offset += length;
+        }
+        words
     }
 
     /// Convert an NSString pointer to a Rust String.
@@ -563,13 +704,41 @@ mod tests {
         assert!(inferred.is_empty());
     }
 
+    #[test]
+    fn parse_transcription_response_splits_sections() {
+        let response = "[TRANSCRIPTION] x² + 1 = 0\ni = √-1\n[ANALYSIS] An equation with no real roots.";
+        let (transcription, analysis) = parse_transcription_response(response);
+        assert_eq!(transcription, "x² + 1 = 0\ni = √-1");
+        assert_eq!(analysis, "An equation with no real roots.");
+
+        assert_eq!(parse_transcription_response("[TRANSCRIPTION] none\n[ANALYSIS] Empty."), (String::new(), "Empty.".into()));
+        assert_eq!(parse_transcription_response("Just text"), ("Just text".into(), String::new()));
+    }
+
+    #[test]
+    fn vision_prompt_attaches_the_image_to_the_user_turn() {
+        let template = PromptTemplate::default_for(PromptId::VisionTranscription);
+        let profile = LearnerProfile::for_locale("zh-CN");
+        let messages = build_vision_transcription_prompt(&template, &[1, 2, 3], &profile);
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].images.is_empty());
+        assert_eq!(messages[1].images, vec![ImagePart::png(&[1, 2, 3])]);
+        assert!(!messages[0].content.contains("{{"));
+    }
+
     #[test]
     fn capture_region_serialization() {
-        let region = CaptureRegion { x: 10.0, y: 20.0, width: 300.0, height: 200.0 };
+        let region = CaptureRegion { x: 10.0, y: 20.0, width: 300.0, height: 200.0, scale_factor: 2.0 };
         let json = serde_json::to_string(&region).unwrap();
         let de: CaptureRegion = serde_json::from_str(&json).unwrap();
         assert_eq!(de.x, 10.0);
         assert_eq!(de.width, 300.0);
+        assert_eq!(de.scale_factor, 2.0);
+
+        // Regions without a scale factor are already in pixels.
+        let de: CaptureRegion =
+            serde_json::from_str(r#"{"x":1,"y":2,"width":3,"height":4}"#).unwrap();
+        assert_eq!(de.scale_factor, 1.0);
     }
 
     #[test]
@@ -578,10 +747,19 @@ mod tests {
             chunk_type: "ocr_result".into(),
             content: Some("Hello".into()),
             is_ai_inferred: Some(false),
+            ocr: Some(OcrResult {
+                lines: vec![ocr::OcrLine {
+                    text: "Hello".into(),
+                    bbox: ocr::BoundingBox { x: 0.1, y: 0.2, width: 0.5, height: 0.25 },
+                    confidence: 0.5,
+                    words: Vec::new(),
+                }],
+            }),
         };
         let json = serde_json::to_string(&chunk).unwrap();
         assert!(json.contains("\"type\":\"ocr_result\""));
         assert!(json.contains("\"is_ai_inferred\":false"));
+        assert!(json.contains(r#""ocr":{"lines":[{"text":"Hello","bbox":{"x":0.1,"y":0.2,"width":0.5,"height":0.25},"confidence":0.5,"words":[]}]}"#));
     }
 
     #[test]
@@ -590,9 +768,11 @@ mod tests {
             chunk_type: "done".into(),
             content: None,
             is_ai_inferred: None,
+            ocr: None,
         };
         let json = serde_json::to_string(&chunk).unwrap();
         assert!(!json.contains("content"));
         assert!(!json.contains("is_ai_inferred"));
+        assert!(!json.contains("ocr"));
     }
 }
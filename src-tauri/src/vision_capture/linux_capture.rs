@@ -0,0 +1,329 @@
+//! Full-screen capture on Linux.
+//!
+//! X11 sessions read the root window directly (the primary RandR monitor,
+//! or the whole root when there is none); the capture overlay is placed on
+//! the primary monitor to match. Wayland compositors don't let
+//! clients read other windows, so there the xdg-desktop-portal Screenshot
+//! API is used instead; it is also the fallback when no X server answers.
+//! Both paths return PNG bytes, like `macos_capture::capture_full_screen`.
+
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::xproto::{ConnectionExt as _, ImageFormat, ImageOrder, Screen, Setup, Window};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+use crate::error::VeyaError;
+use crate::text_insight::linux_selection::is_wayland_session;
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const PORTAL_SCREENSHOT: &str = "org.freedesktop.portal.Screenshot";
+const PORTAL_REQUEST: &str = "org.freedesktop.portal.Request";
+
+/// The portal may show a permission dialog the first time; give the user
+/// time to answer it.
+const PORTAL_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn capture_full_screen() -> Result<Vec<u8>, VeyaError> {
+    if is_wayland_session() {
+        return capture_portal();
+    }
+    capture_x11().or_else(|x11_error| {
+        log::warn!("X11 screen capture failed, trying the desktop portal: {x11_error}");
+        capture_portal()
+    })
+}
+
+fn capture_error(e: impl std::fmt::Display) -> VeyaError {
+    VeyaError::OcrFailed(format!("Screen capture failed: {e}"))
+}
+
+// ── X11 ──────────────────────────────────────────────────────────
+
+fn capture_x11() -> Result<Vec<u8>, VeyaError> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(capture_error)?;
+    let setup = conn.setup();
+    let screen = &setup.roots[screen_num];
+    let (x, y, width, height) = primary_monitor(&conn, screen.root)
+        .unwrap_or((0, 0, screen.width_in_pixels, screen.height_in_pixels));
+
+    let image = conn
+        .get_image(ImageFormat::Z_PIXMAP, screen.root, x, y, width, height, !0)
+        .map_err(capture_error)?
+        .reply()
+        .map_err(capture_error)?;
+    let layout = PixelLayout::for_root(setup, screen, image.depth)?;
+    let rgb = layout.to_rgb(&image.data, width.into(), height.into())?;
+    encode_png(width.into(), height.into(), &rgb)
+}
+
+/// Bounds of the primary monitor, so multi-head setups capture the screen
+/// the overlay is shown on rather than one wide strip.
+fn primary_monitor(conn: &impl Connection, root: Window) -> Option<(i16, i16, u16, u16)> {
+    // GetOutputPrimary is RandR 1.3.
+    conn.randr_query_version(1, 3).ok()?.reply().ok()?;
+    let output = conn.randr_get_output_primary(root).ok()?.reply().ok()?.output;
+    if output == x11rb::NONE {
+        return None;
+    }
+    let info = conn
+        .randr_get_output_info(output, x11rb::CURRENT_TIME)
+        .ok()?
+        .reply()
+        .ok()?;
+    if info.crtc == x11rb::NONE {
+        return None;
+    }
+    let crtc = conn
+        .randr_get_crtc_info(info.crtc, x11rb::CURRENT_TIME)
+        .ok()?
+        .reply()
+        .ok()?;
+    (crtc.width > 0 && crtc.height > 0).then_some((crtc.x, crtc.y, crtc.width, crtc.height))
+}
+
+/// How a ZPixmap image of the root visual is laid out in memory.
+#[derive(Debug, Clone, PartialEq)]
+struct PixelLayout {
+    bits_per_pixel: u8,
+    scanline_pad: u8,
+    lsb_first: bool,
+    red_mask: u32,
+    green_mask: u32,
+    blue_mask: u32,
+}
+
+impl PixelLayout {
+    fn for_root(setup: &Setup, screen: &Screen, depth: u8) -> Result<Self, VeyaError> {
+        let format = setup
+            .pixmap_formats
+            .iter()
+            .find(|f| f.depth == depth)
+            .ok_or_else(|| capture_error(format!("no pixmap format for depth {depth}")))?;
+        let visual = screen
+            .allowed_depths
+            .iter()
+            .flat_map(|d| &d.visuals)
+            .find(|v| v.visual_id == screen.root_visual)
+            .ok_or_else(|| capture_error("root visual not found"))?;
+        Ok(Self {
+            bits_per_pixel: format.bits_per_pixel,
+            scanline_pad: format.scanline_pad,
+            lsb_first: setup.image_byte_order == ImageOrder::LSB_FIRST,
+            red_mask: visual.red_mask,
+            green_mask: visual.green_mask,
+            blue_mask: visual.blue_mask,
+        })
+    }
+
+    /// Packed 8-bit RGB rows for PNG encoding.
+    fn to_rgb(&self, data: &[u8], width: usize, height: usize) -> Result<Vec<u8>, VeyaError> {
+        let bytes_per_pixel = match self.bits_per_pixel {
+            16 | 24 | 32 => usize::from(self.bits_per_pixel / 8),
+            bpp => return Err(capture_error(format!("unsupported {bpp}-bit pixmap format"))),
+        };
+        let pad = usize::from(self.scanline_pad.max(8));
+        let stride = (width * usize::from(self.bits_per_pixel)).div_ceil(pad) * pad / 8;
+        if data.len() < stride * height {
+            return Err(capture_error("image data is shorter than its dimensions"));
+        }
+
+        let mut rgb = Vec::with_capacity(width * height * 3);
+        for row in data.chunks_exact(stride).take(height) {
+            for px in row[..width * bytes_per_pixel].chunks_exact(bytes_per_pixel) {
+                let pixel = if self.lsb_first {
+                    px.iter().rev().fold(0u32, |acc, &b| acc << 8 | u32::from(b))
+                } else {
+                    px.iter().fold(0u32, |acc, &b| acc << 8 | u32::from(b))
+                };
+                rgb.extend([
+                    channel(pixel, self.red_mask),
+                    channel(pixel, self.green_mask),
+                    channel(pixel, self.blue_mask),
+                ]);
+            }
+        }
+        Ok(rgb)
+    }
+}
+
+/// Scales the masked bits of `pixel` to 0..=255.
+fn channel(pixel: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let max = mask >> mask.trailing_zeros();
+    let value = (pixel & mask) >> mask.trailing_zeros();
+    (u64::from(value) * 255 / u64::from(max)) as u8
+}
+
+fn encode_png(width: u32, height: u32, rgb: &[u8]) -> Result<Vec<u8>, VeyaError> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(capture_error)?;
+    writer.write_image_data(rgb).map_err(capture_error)?;
+    writer.finish().map_err(capture_error)?;
+    Ok(out)
+}
+
+// ── xdg-desktop-portal ───────────────────────────────────────────
+
+fn capture_portal() -> Result<Vec<u8>, VeyaError> {
+    let conn = zbus::blocking::Connection::session().map_err(capture_error)?;
+    let sender = conn
+        .unique_name()
+        .ok_or_else(|| capture_error("no D-Bus unique name"))?
+        .trim_start_matches(':')
+        .replace('.', "_");
+    let token = format!("veya{}", uuid::Uuid::new_v4().simple());
+
+    // Subscribe before calling so a fast response can't be missed.
+    let request_path = format!("/org/freedesktop/portal/desktop/request/{sender}/{token}");
+    let request = zbus::blocking::Proxy::new(
+        &conn,
+        PORTAL_DESTINATION,
+        request_path.as_str(),
+        PORTAL_REQUEST,
+    )
+    .map_err(capture_error)?;
+    let mut responses = request.receive_signal("Response").map_err(capture_error)?;
+
+    let screenshot =
+        zbus::blocking::Proxy::new(&conn, PORTAL_DESTINATION, PORTAL_PATH, PORTAL_SCREENSHOT)
+            .map_err(capture_error)?;
+    let options = HashMap::from([
+        ("handle_token", Value::from(token.as_str())),
+        ("interactive", Value::from(false)),
+    ]);
+    let _: OwnedObjectPath = screenshot
+        .call("Screenshot", &("", options))
+        .map_err(capture_error)?;
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(responses.next());
+    });
+    let message = rx
+        .recv_timeout(PORTAL_TIMEOUT)
+        .map_err(|_| capture_error("the desktop portal did not respond"))?
+        .ok_or_else(|| capture_error("the desktop portal closed the request"))?;
+    let (response, results): (u32, HashMap<String, OwnedValue>) =
+        message.body().deserialize().map_err(capture_error)?;
+    if response != 0 {
+        return Err(VeyaError::PermissionDenied(
+            "Screenshot was cancelled or denied by the desktop portal".into(),
+        ));
+    }
+
+    let uri = results
+        .get("uri")
+        .and_then(|v| String::try_from(v.clone()).ok())
+        .ok_or_else(|| capture_error("the desktop portal returned no screenshot"))?;
+    let path = file_uri_path(&uri).ok_or_else(|| capture_error(format!("unexpected URI {uri}")))?;
+    let png = std::fs::read(&path).map_err(capture_error)?;
+    // The portal saves into the user's Pictures folder; don't leave a copy
+    // behind for every capture.
+    if let Err(e) = std::fs::remove_file(&path) {
+        log::warn!("Failed to remove portal screenshot {path}: {e}");
+    }
+    Ok(png)
+}
+
+/// Local path of a `file://` URI, percent-decoded.
+fn file_uri_path(uri: &str) -> Option<String> {
+    let encoded = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' && tail.len() >= 2 {
+            let hex = std::str::from_utf8(&tail[..2]).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bgrx_layout() -> PixelLayout {
+        PixelLayout {
+            bits_per_pixel: 32,
+            scanline_pad: 32,
+            lsb_first: true,
+            red_mask: 0x00ff_0000,
+            green_mask: 0x0000_ff00,
+            blue_mask: 0x0000_00ff,
+        }
+    }
+
+    #[test]
+    fn converts_32_bit_pixels_using_the_visual_masks() {
+        // Two pixels in little-endian BGRX: pure red, then (1, 2, 3).
+        let data = [0, 0, 255, 0, 3, 2, 1, 0];
+        let rgb = bgrx_layout().to_rgb(&data, 2, 1).unwrap();
+        assert_eq!(rgb, vec![255, 0, 0, 1, 2, 3]);
+
+        let msb = PixelLayout {
+            lsb_first: false,
+            ..bgrx_layout()
+        };
+        assert_eq!(msb.to_rgb(&[0, 1, 2, 3], 1, 1).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn converts_16_bit_pixels_and_skips_row_padding() {
+        let layout = PixelLayout {
+            bits_per_pixel: 16,
+            scanline_pad: 32,
+            lsb_first: true,
+            red_mask: 0xf800,
+            green_mask: 0x07e0,
+            blue_mask: 0x001f,
+        };
+        // One white RGB565 pixel per row, padded to four bytes.
+        let data = [0xff, 0xff, 0xaa, 0xaa, 0x00, 0xf8, 0xaa, 0xaa];
+        let rgb = layout.to_rgb(&data, 1, 2).unwrap();
+        assert_eq!(rgb, vec![255, 255, 255, 255, 0, 0]);
+        assert!(layout.to_rgb(&data[..6], 1, 2).is_err());
+    }
+
+    #[test]
+    fn decodes_file_uris() {
+        assert_eq!(
+            file_uri_path("file:///home/a/Pictures/Screenshot%20from%202024.png").as_deref(),
+            Some("/home/a/Pictures/Screenshot from 2024.png")
+        );
+        assert_eq!(file_uri_path("https://example.com/x.png"), None);
+    }
+
+    /// Needs an X server with something on it, e.g.
+    /// `Xvfb :99 -screen 0 320x200x24 & DISPLAY=:99 cargo test -- --ignored`.
+    #[test]
+    #[ignore = "requires an X server (run under Xvfb)"]
+    fn captures_the_x11_root_window_as_png() {
+        let (conn, screen_num) = x11rb::connect(None).expect("X server");
+        let screen = &conn.setup().roots[screen_num];
+        let (_, _, width, height) = primary_monitor(&conn, screen.root)
+            .unwrap_or((0, 0, screen.width_in_pixels, screen.height_in_pixels));
+
+        let png = capture_x11().unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        let reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        assert_eq!(reader.info().width, u32::from(width));
+        assert_eq!(reader.info().height, u32::from(height));
+    }
+}
@@ -0,0 +1,516 @@
+//! Text recognition for captured regions. Apple Vision on macOS, and
+//! Tesseract (through its command-line tool) wherever it is installed, so
+//! captures work offline on Linux too.
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use super::raster::rotation_source;
+use crate::error::VeyaError;
+use crate::language_detect::is_unspaced_script;
+use crate::learner_profile::{primary_subtag, LearnerProfile};
+use crate::settings::OcrEngineKind;
+
+const TESSERACT_BINARY: &str = "tesseract";
+/// Used when none of the learner's languages has a pack installed; every
+/// Tesseract install ships it.
+const TESSERACT_FALLBACK_LANGUAGE: &str = "eng";
+
+// ── Results ──────────────────────────────────────────────────────
+
+/// Recognized text, line by line in reading order, with where each line and
+/// word sits in the image and how sure the engine is about it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OcrResult {
+    pub lines: Vec<OcrLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcrLine {
+    pub text: String,
+    pub bbox: BoundingBox,
+    /// 0.0 to 1.0.
+    pub confidence: f32,
+    pub words: Vec<OcrWord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcrWord {
+    pub text: String,
+    pub bbox: BoundingBox,
+    /// 0.0 to 1.0.
+    pub confidence: f32,
+}
+
+/// A rectangle as fractions of the capture region, from its top-left
+/// corner, so it maps onto the selection whatever the image was scaled to.
+/// Boxes found on a deskewed copy are mapped back by `OcrResult::unrotate`
+/// and then enclose the slightly tilted text, so they are a little loose.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl OcrResult {
+    /// The text with one recognized line per line.
+    pub fn text(&self) -> String {
+        self.lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("\n")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.iter().all(|l| l.text.trim().is_empty())
+    }
+
+    /// Map boxes recognized on a copy of the `width` × `height` capture that
+    /// was rotated by `degrees` (deskewing) back onto the capture.
+    pub fn unrotate(&mut self, degrees: f64, width: u32, height: u32) {
+        if degrees == 0.0 || width == 0 || height == 0 {
+            return;
+        }
+        let source = rotation_source(width, height, degrees);
+        let image = (f64::from(width), f64::from(height));
+        for line in &mut self.lines {
+            line.bbox = line.bbox.mapped(&source, image);
+            for word in &mut line.words {
+                word.bbox = word.bbox.mapped(&source, image);
+            }
+        }
+    }
+}
+
+impl BoundingBox {
+    fn from_pixels(left: f64, top: f64, width: f64, height: f64, image: (f64, f64)) -> Self {
+        Self {
+            x: left / image.0,
+            y: top / image.1,
+            width: width / image.0,
+            height: height / image.1,
+        }
+    }
+
+    /// The upright rectangle around this box's corners moved by `map`
+    /// (pixel to pixel), clipped to the image.
+    fn mapped(self, map: &impl Fn(f64, f64) -> (f64, f64), image: (f64, f64)) -> Self {
+        let (left, top) = (self.x * image.0, self.y * image.1);
+        let (right, bottom) = ((self.x + self.width) * image.0, (self.y + self.height) * image.1);
+        let corners = [(left, top), (right, top), (left, bottom), (right, bottom)].map(|(x, y)| map(x, y));
+        let min_x = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min).clamp(0.0, image.0);
+        let max_x = corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max).clamp(0.0, image.0);
+        let min_y = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min).clamp(0.0, image.1);
+        let max_y = corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max).clamp(0.0, image.1);
+        Self::from_pixels(min_x, min_y, max_x - min_x, max_y - min_y, image)
+    }
+
+    fn union(self, other: Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Self {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+}
+
+// ── Engines ──────────────────────────────────────────────────────
+
+pub trait OcrEngine: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Lines and words in the PNG image. `languages` are the learner's
+    /// language codes ("es", "zh-TW"), most relevant first; engines map
+    /// them to their own models or ignore them.
+    fn recognize(&self, png: &[u8], languages: &[String]) -> Result<OcrResult, VeyaError>;
+}
+
+/// The engine configured in settings. `Auto` is Apple Vision on macOS and
+/// Tesseract elsewhere.
+pub fn engine_for(kind: OcrEngineKind) -> Result<Box<dyn OcrEngine>, VeyaError> {
+    match kind {
+        OcrEngineKind::Auto if cfg!(target_os = "macos") => engine_for(OcrEngineKind::AppleVision),
+        OcrEngineKind::Auto | OcrEngineKind::Tesseract => Ok(Box::new(TesseractOcr::detect()?)),
+        #[cfg(target_os = "macos")]
+        OcrEngineKind::AppleVision => Ok(Box::new(AppleVisionOcr)),
+        #[cfg(not(target_os = "macos"))]
+        OcrEngineKind::AppleVision => Err(VeyaError::OcrFailed(
+            "Apple Vision OCR is only available on macOS. Choose Tesseract in Settings.".into(),
+        )),
+    }
+}
+
+/// Languages a capture is likely to be in: the ones being learned, or the
+/// native language for learners who haven't picked any yet.
+pub fn ocr_languages(profile: &LearnerProfile) -> Vec<String> {
+    if profile.target_languages.is_empty() {
+        vec![profile.native_language.clone()]
+    } else {
+        profile.target_languages.iter().map(|t| t.language.clone()).collect()
+    }
+}
+
+// ── Apple Vision ─────────────────────────────────────────────────
+
+#[cfg(target_os = "macos")]
+pub struct AppleVisionOcr;
+
+#[cfg(target_os = "macos")]
+impl OcrEngine for AppleVisionOcr {
+    fn name(&self) -> &'static str {
+        "Apple Vision"
+    }
+
+    /// Vision detects the language by itself.
+    fn recognize(&self, png: &[u8], _languages: &[String]) -> Result<OcrResult, VeyaError> {
+        super::macos_ocr::recognize(png)
+    }
+}
+
+// ── Tesseract ────────────────────────────────────────────────────
+
+pub struct TesseractOcr {
+    /// Installed language packs, e.g. "eng", "chi_sim".
+    installed: Vec<String>,
+}
+
+impl TesseractOcr {
+    /// Checks that the `tesseract` tool runs and lists its language packs.
+    pub fn detect() -> Result<Self, VeyaError> {
+        let output = Command::new(TESSERACT_BINARY)
+            .arg("--list-langs")
+            .output()
+            .map_err(|e| {
+                VeyaError::OcrFailed(format!(
+                    "Tesseract is not installed or not on PATH ({e}). Install it (e.g. `apt install tesseract-ocr`) or choose another OCR engine."
+                ))
+            })?;
+        if !output.status.success() {
+            return Err(VeyaError::OcrFailed(format!(
+                "tesseract --list-langs failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        // Older versions print the list on stderr.
+        let listing = [output.stdout, output.stderr].concat();
+        Ok(Self {
+            installed: parse_language_list(&String::from_utf8_lossy(&listing)),
+        })
+    }
+
+    /// The `-l` argument for the learner's languages: every installed pack
+    /// they map to, or English when none is installed.
+    fn language_arg(&self, languages: &[String]) -> Result<String, VeyaError> {
+        let mut packs: Vec<&str> = Vec::new();
+        for language in languages {
+            for &pack in tesseract_packs(language) {
+                if packs.contains(&pack) {
+                    continue;
+                }
+                if self.installed.iter().any(|i| i == pack) {
+                    packs.push(pack);
+                } else {
+                    log::warn!("Tesseract language pack '{pack}' for '{language}' is not installed");
+                }
+            }
+        }
+        if packs.is_empty() {
+            if !self.installed.iter().any(|i| i == TESSERACT_FALLBACK_LANGUAGE) {
+                return Err(VeyaError::OcrFailed(format!(
+                    "No Tesseract language pack installed for {}",
+                    languages.join(", ")
+                )));
+            }
+            packs.push(TESSERACT_FALLBACK_LANGUAGE);
+        }
+        Ok(packs.join("+"))
+    }
+
+    /// Runs `tesseract stdin stdout` on the image with extra arguments
+    /// (e.g. an output config) and returns what it printed.
+    pub(super) fn run(&self, png: &[u8], languages: &[String], extra_args: &[&str]) -> Result<String, VeyaError> {
+        let mut child = Command::new(TESSERACT_BINARY)
+            .args(["stdin", "stdout", "-l", &self.language_arg(languages)?])
+            .args(extra_args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| VeyaError::OcrFailed(format!("Failed to run tesseract: {e}")))?;
+        // Tesseract reads all of its input before writing anything, so this
+        // can't deadlock on a full stdout pipe.
+        let written = child.stdin.take().map(|mut stdin| stdin.write_all(png));
+        let output = child
+            .wait_with_output()
+            .map_err(|e| VeyaError::OcrFailed(format!("Failed to run tesseract: {e}")))?;
+        if !output.status.success() {
+            return Err(VeyaError::OcrFailed(format!(
+                "tesseract failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        if let Some(Err(e)) = written {
+            return Err(VeyaError::OcrFailed(format!("Failed to send the image to tesseract: {e}")));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl OcrEngine for TesseractOcr {
+    fn name(&self) -> &'static str {
+        "Tesseract"
+    }
+
+    fn recognize(&self, png: &[u8], languages: &[String]) -> Result<OcrResult, VeyaError> {
+        Ok(parse_tsv(&self.run(png, languages, &["tsv"])?))
+    }
+}
+
+/// Tesseract pack names for a language code. Chinese defaults to the
+/// simplified pack unless the code names a traditional region or script.
+fn tesseract_packs(language: &str) -> &'static [&'static str] {
+    match primary_subtag(language).as_str() {
+        "en" => &["eng"],
+        "zh" => {
+            let tag = language.to_lowercase();
+            if ["hant", "tw", "hk", "mo"].iter().any(|t| tag.split(['-', '_']).any(|s| s == *t)) {
+                &["chi_tra"]
+            } else {
+                &["chi_sim"]
+            }
+        }
+        "ja" => &["jpn"],
+        "ko" => &["kor"],
+        "fr" => &["fra"],
+        "de" => &["deu"],
+        "es" => &["spa"],
+        "pt" => &["por"],
+        "ru" => &["rus"],
+        "it" => &["ita"],
+        _ => &[],
+    }
+}
+
+/// Pack names from `tesseract --list-langs`, skipping the header line.
+fn parse_language_list(listing: &str) -> Vec<String> {
+    listing
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.contains(char::is_whitespace) && !l.ends_with(':'))
+        .map(String::from)
+        .collect()
+}
+
+/// Lines from Tesseract's TSV output. Word rows (level 5) are grouped into
+/// lines by their block, paragraph and line numbers; the page row (level 1)
+/// gives the image size the pixel boxes are relative to.
+fn parse_tsv(tsv: &str) -> OcrResult {
+    let mut image = (1.0, 1.0);
+    let mut lines: Vec<(&[&str], Vec<OcrWord>)> = Vec::new();
+    let rows: Vec<Vec<&str>> = tsv.lines().skip(1).map(|row| row.split('\t').collect()).collect();
+    for cols in rows.iter().filter(|cols| cols.len() >= 11) {
+        let num = |i: usize| cols[i].trim().parse::<f64>().unwrap_or(0.0);
+        match cols[0] {
+            "1" => image = (num(8).max(1.0), num(9).max(1.0)),
+            "5" => {
+                let text = cols.get(11).map_or("", |t| t.trim());
+                if text.is_empty() {
+                    continue;
+                }
+                let word = OcrWord {
+                    text: text.to_string(),
+                    bbox: BoundingBox::from_pixels(num(6), num(7), num(8), num(9), image),
+                    confidence: (num(10) / 100.0).clamp(0.0, 1.0) as f32,
+                };
+                let key = &cols[2..5];
+                match lines.last_mut() {
+                    Some((line, words)) if *line == key => words.push(word),
+                    _ => lines.push((key, vec![word])),
+                }
+            }
+            _ => {}
+        }
+    }
+    OcrResult {
+        lines: lines.into_iter().map(|(_, words)| line_from_words(words)).collect(),
+    }
+}
+
+/// A line spanning its words, as sure as they are on average. Tesseract
+/// splits Chinese and Japanese into one "word" per character; the spaces it
+/// would put between them are dropped.
+fn line_from_words(words: Vec<OcrWord>) -> OcrLine {
+    let text = words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" ");
+    let bbox = words.iter().map(|w| w.bbox).reduce(BoundingBox::union).expect("lines have words");
+    let confidence = words.iter().map(|w| w.confidence).sum::<f32>() / words.len() as f32;
+    OcrLine {
+        text: join_unspaced(&text),
+        bbox,
+        confidence,
+        words,
+    }
+}
+
+fn join_unspaced(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::with_capacity(line.len());
+    for (i, &c) in chars.iter().enumerate() {
+        if c == ' ' {
+            let prev = out.chars().next_back();
+            let next = chars[i + 1..].iter().find(|&&n| n != ' ');
+            if prev.is_some_and(is_unspaced_script) && next.is_some_and(|&n| is_unspaced_script(n)) {
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::learner_profile::{CefrLevel, TargetLanguage};
+
+    fn tesseract(installed: &[&str]) -> TesseractOcr {
+        TesseractOcr {
+            installed: installed.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn codes(codes: &[&str]) -> Vec<String> {
+        codes.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn languages_come_from_targets_then_native() {
+        let mut profile = LearnerProfile {
+            native_language: "zh".into(),
+            target_languages: Vec::new(),
+        };
+        assert_eq!(ocr_languages(&profile), codes(&["zh"]));
+
+        profile.target_languages = vec![
+            TargetLanguage { language: "ja".into(), level: CefrLevel::B1 },
+            TargetLanguage { language: "en".into(), level: CefrLevel::C1 },
+        ];
+        assert_eq!(ocr_languages(&profile), codes(&["ja", "en"]));
+    }
+
+    #[test]
+    fn language_arg_uses_installed_packs_in_order() {
+        let engine = tesseract(&["chi_sim", "eng", "jpn", "osd"]);
+        assert_eq!(engine.language_arg(&codes(&["ja", "en", "en-GB"])).unwrap(), "jpn+eng");
+        assert_eq!(engine.language_arg(&codes(&["zh-CN"])).unwrap(), "chi_sim");
+        // Missing packs are skipped, falling back to English.
+        assert_eq!(engine.language_arg(&codes(&["zh-Hant", "xx"])).unwrap(), "eng");
+        assert!(tesseract(&["deu"]).language_arg(&codes(&["fr"])).is_err());
+    }
+
+    #[test]
+    fn parses_list_langs_output() {
+        let listing = "List of available languages in \"/usr/share/tesseract-ocr/5/tessdata/\" (3):\nchi_sim\neng\nosd\n";
+        assert_eq!(parse_language_list(listing), codes(&["chi_sim", "eng", "osd"]));
+    }
+
+    fn tsv(rows: &[&str]) -> String {
+        let header = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext";
+        std::iter::once(header).chain(rows.iter().copied()).collect::<Vec<_>>().join("\n")
+    }
+
+    fn assert_box(actual: BoundingBox, [x, y, width, height]: [f64; 4]) {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(
+            close(actual.x, x) && close(actual.y, y) && close(actual.width, width) && close(actual.height, height),
+            "{actual:?}"
+        );
+    }
+
+    #[test]
+    fn parses_tsv_into_lines_and_words() {
+        let output = tsv(&[
+            "1\t1\t0\t0\t0\t0\t0\t0\t200\t100\t-1\t",
+            "4\t1\t1\t1\t1\t0\t10\t10\t110\t20\t-1\t",
+            "5\t1\t1\t1\t1\t1\t10\t10\t50\t20\t96\tHello",
+            "5\t1\t1\t1\t1\t2\t70\t12\t50\t18\t40\twor1d",
+            "5\t1\t1\t1\t1\t3\t120\t12\t10\t18\t95\t ",
+            "5\t1\t1\t1\t2\t1\t10\t50\t20\t20\t90\t这",
+            "5\t1\t1\t1\t2\t2\t30\t50\t20\t20\t80\t是",
+        ]);
+        let result = parse_tsv(&output);
+        assert_eq!(result.text(), "Hello wor1d\n这是");
+
+        let first = &result.lines[0];
+        assert_eq!(first.words.len(), 2);
+        assert_box(first.bbox, [0.05, 0.1, 0.55, 0.2]);
+        assert!((first.confidence - 0.68).abs() < 1e-6);
+        assert_box(first.words[1].bbox, [0.35, 0.12, 0.25, 0.18]);
+        assert!((first.words[1].confidence - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn joins_tesseract_spacing_in_cjk_only() {
+        assert_eq!(join_unspaced("这 是 一 个 API 测 试"), "这是一个 API 测试");
+        assert_eq!(join_unspaced("日本 語 の テキスト"), "日本語のテキスト");
+        assert_eq!(join_unspaced("한국어 문장"), "한국어 문장");
+    }
+
+    #[test]
+    fn tsv_without_words_has_no_text() {
+        assert!(parse_tsv(&tsv(&["1\t1\t0\t0\t0\t0\t0\t0\t200\t100\t-1\t"])).is_empty());
+    }
+
+    #[test]
+    fn boxes_from_a_deskewed_copy_map_back_onto_the_capture() {
+        use crate::vision_capture::raster::{PixelFormat, Raster};
+
+        // A dark 60×20 block at (40, 30) on a 200×100 capture.
+        let (width, height) = (200, 100);
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| if (40..100).contains(&x) && (30..50).contains(&y) { 0 } else { 255 })
+            .collect();
+        let capture = Raster { width, height, format: PixelFormat::Gray, pixels };
+
+        // Where the block ends up on the copy rotated by 3°.
+        let rotated = capture.rotate(3.0);
+        let ink: Vec<(u32, u32)> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| rotated.pixels[(y * width + x) as usize] < 128)
+            .collect();
+        let (min_x, max_x) = (ink.iter().map(|p| p.0).min().unwrap(), ink.iter().map(|p| p.0).max().unwrap());
+        let (min_y, max_y) = (ink.iter().map(|p| p.1).min().unwrap(), ink.iter().map(|p| p.1).max().unwrap());
+        let bbox = BoundingBox::from_pixels(
+            f64::from(min_x),
+            f64::from(min_y),
+            f64::from(max_x + 1 - min_x),
+            f64::from(max_y + 1 - min_y),
+            (f64::from(width), f64::from(height)),
+        );
+        let word = OcrWord { text: "block".into(), bbox, confidence: 1.0 };
+        let mut result = OcrResult {
+            lines: vec![OcrLine { text: "block".into(), bbox, confidence: 1.0, words: vec![word] }],
+        };
+
+        result.unrotate(3.0, width, height);
+        let mapped = result.lines[0].words[0].bbox;
+        let (left, top) = (mapped.x * 200.0, mapped.y * 100.0);
+        let (right, bottom) = (left + mapped.width * 200.0, top + mapped.height * 100.0);
+        // Loose by the tilt, but around the original block.
+        assert!((34.0..=41.0).contains(&left), "{mapped:?}");
+        assert!((99.0..=106.0).contains(&right), "{mapped:?}");
+        assert!((25.0..=31.0).contains(&top), "{mapped:?}");
+        assert!((49.0..=55.0).contains(&bottom), "{mapped:?}");
+        assert_eq!(result.lines[0].bbox, mapped);
+
+        // Without deskewing nothing moves.
+        let before = result.clone();
+        result.unrotate(0.0, width, height);
+        assert_eq!(result, before);
+    }
+}
@@ -0,0 +1,573 @@
+//! Platform-independent image work for captures: PNG decoding, cropping a
+//! screenshot to the selected region and the optional clean-up passes that
+//! help OCR engines with small, tilted or light-on-dark text.
+
+use serde::{Deserialize, Serialize};
+
+use super::CaptureRegion;
+use crate::error::VeyaError;
+
+/// Tilt beyond this is treated as layout, not scanning error.
+const MAX_SKEW_DEGREES: f64 = 5.0;
+const SKEW_STEP_DEGREES: f64 = 0.25;
+/// Estimates need enough ink to mean anything.
+const MIN_SKEW_INK_PIXELS: usize = 64;
+const MAX_UPSCALE: u32 = 4;
+
+fn image_error(e: impl std::fmt::Display) -> VeyaError {
+    VeyaError::OcrFailed(format!("Image processing failed: {e}"))
+}
+
+// ── Options ──────────────────────────────────────────────────────
+
+/// Clean-up passes applied to the cropped region before OCR. All off by
+/// default: native engines do their own, and each pass costs time.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OcrPreprocess {
+    pub grayscale: bool,
+    /// Integer scale factor, 1 to disable. Clamped to 1..=4.
+    pub upscale: u32,
+    /// Otsu thresholding to pure black and white. Implies grayscale.
+    pub binarize: bool,
+    /// Rotate slightly tilted text (up to 5°) back to horizontal.
+    pub deskew: bool,
+    /// Invert light-on-dark captures so text is dark on light.
+    #[serde(alias = "invert_dark")]
+    pub invert_dark: bool,
+}
+
+impl Default for OcrPreprocess {
+    fn default() -> Self {
+        Self {
+            grayscale: false,
+            upscale: 1,
+            binarize: false,
+            deskew: false,
+            invert_dark: false,
+        }
+    }
+}
+
+impl OcrPreprocess {
+    pub fn is_noop(&self) -> bool {
+        !self.grayscale && self.upscale <= 1 && !self.binarize && !self.deskew && !self.invert_dark
+    }
+}
+
+// ── Raster ───────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Gray,
+    Rgba,
+}
+
+impl PixelFormat {
+    fn channels(self) -> usize {
+        match self {
+            Self::Gray => 1,
+            Self::Rgba => 4,
+        }
+    }
+}
+
+/// An 8-bit image, rows top to bottom without padding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Raster {
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    pub pixels: Vec<u8>,
+}
+
+impl Raster {
+    pub fn decode_png(data: &[u8]) -> Result<Self, VeyaError> {
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(image_error)?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buf).map_err(image_error)?;
+        buf.truncate(frame.buffer_size());
+
+        let (format, pixels) = match frame.color_type {
+            png::ColorType::Grayscale => (PixelFormat::Gray, buf),
+            png::ColorType::GrayscaleAlpha => (
+                PixelFormat::Rgba,
+                buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            ),
+            png::ColorType::Rgb => (
+                PixelFormat::Rgba,
+                buf.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+            ),
+            png::ColorType::Rgba => (PixelFormat::Rgba, buf),
+            png::ColorType::Indexed => return Err(image_error("palette was not expanded")),
+        };
+        Ok(Self {
+            width: frame.width,
+            height: frame.height,
+            format,
+            pixels,
+        })
+    }
+
+    pub fn encode_png(&self) -> Result<Vec<u8>, VeyaError> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, self.width, self.height);
+        encoder.set_color(match self.format {
+            PixelFormat::Gray => png::ColorType::Grayscale,
+            PixelFormat::Rgba => png::ColorType::Rgba,
+        });
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(image_error)?;
+        writer.write_image_data(&self.pixels).map_err(image_error)?;
+        writer.finish().map_err(image_error)?;
+        Ok(out)
+    }
+
+    fn channels(&self) -> usize {
+        self.format.channels()
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> &[u8] {
+        let c = self.channels();
+        let i = (y as usize * self.width as usize + x as usize) * c;
+        &self.pixels[i..i + c]
+    }
+
+    /// Bilinear sample at a pixel-center coordinate, clamped to the edges.
+    fn sample(&self, x: f64, y: f64, out: &mut Vec<u8>) {
+        let max_x = f64::from(self.width - 1);
+        let max_y = f64::from(self.height - 1);
+        let x = x.clamp(0.0, max_x);
+        let y = y.clamp(0.0, max_y);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (x1, y1) = ((x0 + 1.0).min(max_x), (y0 + 1.0).min(max_y));
+        let (fx, fy) = (x - x0, y - y0);
+        let corners = [
+            self.pixel(x0 as u32, y0 as u32),
+            self.pixel(x1 as u32, y0 as u32),
+            self.pixel(x0 as u32, y1 as u32),
+            self.pixel(x1 as u32, y1 as u32),
+        ];
+        for ch in 0..self.channels() {
+            let [a, b, c, d] = corners.map(|p| f64::from(p[ch]));
+            let top = a + (b - a) * fx;
+            let bottom = c + (d - c) * fx;
+            out.push((top + (bottom - top) * fy).round() as u8);
+        }
+    }
+
+    /// Builds an image of the given size by sampling this one through
+    /// `source`, which maps output pixel centers to source coordinates.
+    fn resample(&self, width: u32, height: u32, source: impl Fn(f64, f64) -> (f64, f64)) -> Self {
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * self.channels());
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = source(f64::from(x), f64::from(y));
+                self.sample(sx, sy, &mut pixels);
+            }
+        }
+        Self {
+            width,
+            height,
+            format: self.format,
+            pixels,
+        }
+    }
+
+    /// The part of the image under `region`, converting the region from
+    /// logical points to pixels with its scale factor.
+    pub fn crop(&self, region: &CaptureRegion) -> Result<Self, VeyaError> {
+        let (x, y, width, height) = pixel_bounds(region, self.width, self.height)
+            .ok_or_else(|| image_error("the selected region is outside the screenshot"))?;
+        let c = self.channels();
+        let row_len = width as usize * c;
+        let mut pixels = Vec::with_capacity(row_len * height as usize);
+        for row in y..y + height {
+            let start = (row as usize * self.width as usize + x as usize) * c;
+            pixels.extend_from_slice(&self.pixels[start..start + row_len]);
+        }
+        Ok(Self {
+            width,
+            height,
+            format: self.format,
+            pixels,
+        })
+    }
+
+    /// Luma (BT.601) of every pixel; transparent areas count as white.
+    fn luma(&self) -> Vec<u8> {
+        match self.format {
+            PixelFormat::Gray => self.pixels.clone(),
+            PixelFormat::Rgba => self
+                .pixels
+                .chunks_exact(4)
+                .map(|p| {
+                    let y = (299 * u32::from(p[0]) + 587 * u32::from(p[1]) + 114 * u32::from(p[2])) / 1000;
+                    let a = u32::from(p[3]);
+                    ((y * a + 255 * (255 - a)) / 255) as u8
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_gray(&self) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            format: PixelFormat::Gray,
+            pixels: self.luma(),
+        }
+    }
+
+    /// Whether the background is dark, judged by the median luma: text
+    /// rarely covers half of a capture.
+    pub fn is_dark(&self) -> bool {
+        let histogram = histogram(&self.luma());
+        let half = (self.width as usize * self.height as usize).div_ceil(2);
+        let mut seen = 0;
+        for (value, count) in histogram.iter().enumerate() {
+            seen += count;
+            if seen >= half {
+                return value < 128;
+            }
+        }
+        false
+    }
+
+    pub fn invert(&mut self) {
+        match self.format {
+            PixelFormat::Gray => self.pixels.iter_mut().for_each(|v| *v = 255 - *v),
+            PixelFormat::Rgba => self
+                .pixels
+                .chunks_exact_mut(4)
+                .for_each(|p| p[..3].iter_mut().for_each(|v| *v = 255 - *v)),
+        }
+    }
+
+    pub fn upscale(&self, factor: u32) -> Self {
+        let factor = factor.clamp(1, MAX_UPSCALE);
+        if factor == 1 {
+            return self.clone();
+        }
+        let f = f64::from(factor);
+        self.resample(self.width * factor, self.height * factor, |x, y| {
+            ((x + 0.5) / f - 0.5, (y + 0.5) / f - 0.5)
+        })
+    }
+
+    /// Shrinks the image so neither side exceeds `max_side`, averaging the
+    /// source pixels under each output pixel. Smaller images are unchanged.
+    pub fn fit_within(&self, max_side: u32) -> Self {
+        let long_side = self.width.max(self.height);
+        if long_side <= max_side || max_side == 0 {
+            return self.clone();
+        }
+        let scale = f64::from(long_side) / f64::from(max_side);
+        let width = ((f64::from(self.width) / scale).round() as u32).max(1);
+        let height = ((f64::from(self.height) / scale).round() as u32).max(1);
+        let span = |i: u32, len: u32| {
+            let start = ((f64::from(i) * scale).floor() as u32).min(len - 1);
+            let end = ((f64::from(i + 1) * scale).ceil() as u32).clamp(start + 1, len);
+            start..end
+        };
+
+        let c = self.channels();
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * c);
+        for y in 0..height {
+            let rows = span(y, self.height);
+            for x in 0..width {
+                let cols = span(x, self.width);
+                let mut sums = [0u32; 4];
+                for sy in rows.clone() {
+                    for sx in cols.clone() {
+                        for (sum, &v) in sums.iter_mut().zip(self.pixel(sx, sy)) {
+                            *sum += u32::from(v);
+                        }
+                    }
+                }
+                let count = rows.len() as u32 * cols.len() as u32;
+                pixels.extend(sums[..c].iter().map(|sum| ((sum + count / 2) / count) as u8));
+            }
+        }
+        Self {
+            width,
+            height,
+            format: self.format,
+            pixels,
+        }
+    }
+
+    /// Black text on white, split at the Otsu threshold.
+    pub fn binarize(&self) -> Self {
+        let luma = self.luma();
+        let threshold = otsu_threshold(&histogram(&luma));
+        Self {
+            width: self.width,
+            height: self.height,
+            format: PixelFormat::Gray,
+            pixels: luma.into_iter().map(|v| if v > threshold { 255 } else { 0 }).collect(),
+        }
+    }
+
+    /// Rotates the content by `degrees` about the center, keeping the size.
+    /// Uncovered corners repeat the nearest edge pixels.
+    pub fn rotate(&self, degrees: f64) -> Self {
+        self.resample(self.width, self.height, rotation_source(self.width, self.height, degrees))
+    }
+
+    /// The tilt of the text lines in degrees, in the sense of `rotate`, or
+    /// zero when there isn't enough text to tell. Picks the angle whose
+    /// horizontal projection of the ink is most sharply peaked.
+    pub fn estimate_skew(&self) -> f64 {
+        let luma = self.luma();
+        let threshold = otsu_threshold(&histogram(&luma));
+        let dark_background = self.is_dark();
+        let cx = f64::from(self.width) / 2.0;
+        let cy = f64::from(self.height) / 2.0;
+        let ink: Vec<(f64, f64)> = luma
+            .iter()
+            .enumerate()
+            .filter(|(_, &v)| (v <= threshold) != dark_background)
+            .map(|(i, _)| {
+                let x = (i % self.width as usize) as f64;
+                let y = (i / self.width as usize) as f64;
+                (x - cx, y - cy)
+            })
+            .collect();
+        if ink.len() < MIN_SKEW_INK_PIXELS || ink.len() > luma.len() / 2 {
+            return 0.0;
+        }
+
+        let offset = (f64::from(self.width) + f64::from(self.height)) as usize;
+        let mut bins = vec![0u64; offset * 2 + 2];
+        let steps = (MAX_SKEW_DEGREES / SKEW_STEP_DEGREES) as i32;
+        let mut best = (0.0, 0u64);
+        for step in -steps..=steps {
+            let angle = f64::from(step) * SKEW_STEP_DEGREES;
+            let (sin, cos) = angle.to_radians().sin_cos();
+            bins.iter_mut().for_each(|b| *b = 0);
+            for &(x, y) in &ink {
+                let row = (y * cos - x * sin).round() as isize + offset as isize;
+                bins[row as usize] += 1;
+            }
+            let score = bins.iter().map(|b| b * b).sum::<u64>();
+            // Prefer the smallest correction among ties.
+            if score > best.1 || (score == best.1 && angle.abs() < f64::abs(best.0)) {
+                best = (angle, score);
+            }
+        }
+        best.0
+    }
+
+    /// Applies the enabled passes: deskew and dark-mode inversion before
+    /// upscaling (cheaper, and thresholds see the original pixels), then
+    /// grayscale or binarization last. Also returns the rotation deskewing
+    /// applied, in degrees (zero if none), to map positions back.
+    pub fn preprocess(&self, options: &OcrPreprocess) -> (Self, f64) {
+        let mut image = self.clone();
+        let mut rotation = 0.0;
+        if options.invert_dark && image.is_dark() {
+            image.invert();
+        }
+        if options.deskew {
+            let skew = image.estimate_skew();
+            if skew != 0.0 {
+                rotation = -skew;
+                image = image.rotate(rotation);
+            }
+        }
+        image = image.upscale(options.upscale);
+        if options.binarize {
+            image = image.binarize();
+        } else if options.grayscale {
+            image = image.to_gray();
+        }
+        (image, rotation)
+    }
+}
+
+/// Maps a position in a `width` × `height` image rotated by `degrees` (as
+/// by `Raster::rotate`) to where it was before the rotation.
+pub fn rotation_source(width: u32, height: u32, degrees: f64) -> impl Fn(f64, f64) -> (f64, f64) {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let cx = f64::from(width.saturating_sub(1)) / 2.0;
+    let cy = f64::from(height.saturating_sub(1)) / 2.0;
+    move |x, y| {
+        let (dx, dy) = (x - cx, y - cy);
+        (cx + dx * cos + dy * sin, cy - dx * sin + dy * cos)
+    }
+}
+
+/// Pixel rectangle `(x, y, width, height)` covered by `region`, clipped to
+/// the image. `None` when nothing of it is on the image.
+pub fn pixel_bounds(region: &CaptureRegion, image_width: u32, image_height: u32) -> Option<(u32, u32, u32, u32)> {
+    let scale = if region.scale_factor.is_finite() && region.scale_factor > 0.0 {
+        region.scale_factor
+    } else {
+        1.0
+    };
+    let clamp = |v: f64, max: u32| v.clamp(0.0, f64::from(max)) as u32;
+    let left = clamp((region.x * scale).floor(), image_width);
+    let top = clamp((region.y * scale).floor(), image_height);
+    let right = clamp(((region.x + region.width) * scale).ceil(), image_width);
+    let bottom = clamp(((region.y + region.height) * scale).ceil(), image_height);
+    (right > left && bottom > top).then(|| (left, top, right - left, bottom - top))
+}
+
+fn histogram(luma: &[u8]) -> [usize; 256] {
+    let mut histogram = [0; 256];
+    luma.iter().for_each(|&v| histogram[v as usize] += 1);
+    histogram
+}
+
+/// The threshold maximizing between-class variance; values at or below it
+/// are the dark class.
+fn otsu_threshold(histogram: &[usize; 256]) -> u8 {
+    let total: usize = histogram.iter().sum();
+    let weighted_total: f64 = histogram.iter().enumerate().map(|(v, &n)| v as f64 * n as f64).sum();
+    let (mut dark_count, mut dark_sum) = (0usize, 0.0);
+    let mut best = (0u8, -1.0);
+    for (value, &count) in histogram.iter().enumerate().take(255) {
+        dark_count += count;
+        dark_sum += value as f64 * count as f64;
+        let light_count = total - dark_count;
+        if dark_count == 0 || light_count == 0 {
+            continue;
+        }
+        let dark_mean = dark_sum / dark_count as f64;
+        let light_mean = (weighted_total - dark_sum) / light_count as f64;
+        let variance = dark_count as f64 * light_count as f64 * (dark_mean - light_mean).powi(2);
+        if variance > best.1 {
+            best = (value as u8, variance);
+        }
+    }
+    best.0
+}
+
+// ── Tests ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(x: f64, y: f64, width: f64, height: f64, scale_factor: f64) -> CaptureRegion {
+        CaptureRegion { x, y, width, height, scale_factor }
+    }
+
+    fn gray(width: u32, height: u32, f: impl Fn(u32, u32) -> u8) -> Raster {
+        let pixels = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| f(x, y)).collect();
+        Raster { width, height, format: PixelFormat::Gray, pixels }
+    }
+
+    /// Dark text lines two pixels thick every ten rows, with gaps between
+    /// "words" so rows aren't uniform.
+    fn text_lines(width: u32, height: u32) -> Raster {
+        gray(width, height, |x, y| if y % 10 < 2 && x % 12 < 9 { 20 } else { 235 })
+    }
+
+    #[test]
+    fn png_roundtrip_expands_rgb_to_rgba() {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 1, 2, 3]).unwrap();
+        writer.finish().unwrap();
+
+        let raster = Raster::decode_png(&out).unwrap();
+        assert_eq!(raster.format, PixelFormat::Rgba);
+        assert_eq!(raster.pixels, vec![255, 0, 0, 255, 1, 2, 3, 255]);
+        assert_eq!(Raster::decode_png(&raster.encode_png().unwrap()).unwrap(), raster);
+        assert!(Raster::decode_png(b"not a png").is_err());
+    }
+
+    #[test]
+    fn crop_scales_logical_regions_to_pixels() {
+        let image = gray(8, 6, |x, y| (y * 10 + x) as u8);
+        // A 2×1 point region at (1, 1) on a 2× display covers 4×2 pixels.
+        let cropped = image.crop(&region(1.0, 1.0, 2.0, 1.0, 2.0)).unwrap();
+        assert_eq!((cropped.width, cropped.height), (4, 2));
+        assert_eq!(cropped.pixels, vec![22, 23, 24, 25, 32, 33, 34, 35]);
+
+        // Fractional scales round outwards; regions are clipped to the image.
+        assert_eq!(pixel_bounds(&region(1.0, 1.0, 1.0, 1.0, 1.5), 8, 6), Some((1, 1, 2, 2)));
+        assert_eq!(pixel_bounds(&region(6.0, 4.0, 10.0, 10.0, 1.0), 8, 6), Some((6, 4, 2, 2)));
+        assert_eq!(pixel_bounds(&region(9.0, 0.0, 5.0, 5.0, 1.0), 8, 6), None);
+        assert!(image.crop(&region(0.0, 0.0, 0.0, 3.0, 1.0)).is_err());
+    }
+
+    #[test]
+    fn dark_captures_are_detected_and_inverted() {
+        let mut dark = gray(4, 4, |x, _| if x == 0 { 230 } else { 30 });
+        assert!(dark.is_dark());
+        dark.invert();
+        assert!(!dark.is_dark());
+        assert_eq!(dark.pixels[..4], [25, 225, 225, 225]);
+    }
+
+    #[test]
+    fn binarize_splits_at_the_otsu_threshold() {
+        let image = gray(4, 1, |x, _| [40, 60, 180, 200][x as usize]);
+        assert_eq!(image.binarize().pixels, vec![0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn upscale_keeps_flat_areas_and_edges() {
+        let image = gray(2, 1, |x, _| if x == 0 { 0 } else { 200 });
+        let up = image.upscale(2);
+        assert_eq!((up.width, up.height), (4, 2));
+        assert_eq!(up.pixels[..4], [0, 50, 150, 200]);
+        assert_eq!(image.upscale(0), image);
+    }
+
+    #[test]
+    fn fit_within_averages_down_to_the_limit() {
+        let image = gray(4, 2, |x, y| [[0, 100, 200, 200], [0, 100, 0, 0]][y as usize][x as usize]);
+        let small = image.fit_within(2);
+        assert_eq!((small.width, small.height), (2, 1));
+        assert_eq!(small.pixels, vec![50, 100]);
+        assert_eq!(image.fit_within(4), image);
+    }
+
+    #[test]
+    fn deskew_recovers_the_rotation_of_text_lines() {
+        let straight = text_lines(160, 80);
+        assert_eq!(straight.estimate_skew(), 0.0);
+
+        let tilted = straight.rotate(3.0);
+        let skew = tilted.estimate_skew();
+        assert!((skew - 3.0).abs() <= SKEW_STEP_DEGREES, "estimated {skew}");
+
+        let options = OcrPreprocess { deskew: true, ..OcrPreprocess::default() };
+        let (deskewed, rotation) = tilted.preprocess(&options);
+        assert!(deskewed.estimate_skew().abs() <= SKEW_STEP_DEGREES);
+        assert_eq!(rotation, -skew);
+
+        // Blank captures have nothing to straighten.
+        assert_eq!(gray(50, 50, |_, _| 255).estimate_skew(), 0.0);
+    }
+
+    #[test]
+    fn preprocess_turns_dark_mode_text_into_black_on_white() {
+        let dark_mode = gray(160, 80, |x, y| if y % 10 < 2 && x % 12 < 9 { 220 } else { 25 });
+        let options = OcrPreprocess {
+            upscale: 2,
+            binarize: true,
+            invert_dark: true,
+            ..OcrPreprocess::default()
+        };
+        assert!(!options.is_noop());
+        assert!(OcrPreprocess::default().is_noop());
+
+        let (out, rotation) = dark_mode.preprocess(&options);
+        assert_eq!(rotation, 0.0);
+        assert_eq!((out.width, out.height, out.format), (320, 160, PixelFormat::Gray));
+        assert!(out.pixels.iter().all(|&v| v == 0 || v == 255));
+        // Top-left is a text pixel, now black; row 10 (y = 5 before scaling) is background.
+        assert_eq!(out.pixels[0], 0);
+        assert_eq!(out.pixels[10 * 320], 255);
+    }
+}
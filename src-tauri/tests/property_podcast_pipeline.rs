@@ -105,7 +105,7 @@ fn script_strategy() -> impl Strategy<Value = String> {
 }
 
 fn input_strategy() -> impl Strategy<Value = PodcastInput> {
-    (content_strategy(), source_strategy()).prop_map(|(content, source)| PodcastInput { content, source })
+    (content_strategy(), source_strategy()).prop_map(|(content, source)| PodcastInput { content, source, language: None })
 }
 
 fn options_strategy() -> impl Strategy<Value = PodcastOptions> {
@@ -155,7 +155,7 @@ proptest! {
         content in content_strategy(),
         script in script_strategy(),
     ) {
-        let input = PodcastInput { content, source };
+        let input = PodcastInput { content, source, language: None };
         let options = PodcastOptions {
             speed: SpeedMode::Normal,
             mode: PodcastMode::Bilingual,
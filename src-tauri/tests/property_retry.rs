@@ -57,4 +57,20 @@ proptest! {
             Ok(())
         })?;
     }
+
+    /// Backoff delays double per attempt and never exceed the configured cap.
+    #[test]
+    fn retry_delay_is_exponential_and_capped(
+        base in 1u64..1_000,
+        max in 1u64..60_000,
+        attempt in 0u32..40,
+    ) {
+        let policy = RetryPolicy::new(3, base, max);
+        let delay = policy.delay_for(attempt).as_millis() as u64;
+        prop_assert!(delay <= max);
+        prop_assert!(policy.delay_for(attempt + 1).as_millis() as u64 >= delay);
+        if attempt == 0 {
+            prop_assert_eq!(delay, base.min(max));
+        }
+    }
 }
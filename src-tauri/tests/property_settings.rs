@@ -10,7 +10,8 @@
 use proptest::prelude::*;
 use tempfile::TempDir;
 use veya_lib::db::Database;
-use veya_lib::settings::AppSettings;
+use veya_lib::settings::{AppSettings, CaptureMode, ClipboardTrigger, OcrEngineKind};
+use veya_lib::vision_capture::raster::OcrPreprocess;
 
 /// Strategy for generating a valid locale string.
 fn arb_locale() -> impl Strategy<Value = String> {
@@ -42,6 +43,19 @@ fn arb_shortcut() -> impl Strategy<Value = String> {
     (modifiers, keys).prop_map(|(m, k)| format!("{m}+Shift+{k}"))
 }
 
+/// Strategy for generating OCR preprocessing options.
+fn arb_ocr_preprocess() -> impl Strategy<Value = OcrPreprocess> {
+    (any::<bool>(), 1u32..=4, any::<bool>(), any::<bool>(), any::<bool>()).prop_map(
+        |(grayscale, upscale, binarize, deskew, invert_dark)| OcrPreprocess {
+            grayscale,
+            upscale,
+            binarize,
+            deskew,
+            invert_dark,
+        },
+    )
+}
+
 /// Strategy for generating a complete valid AppSettings.
 fn arb_settings() -> impl Strategy<Value = AppSettings> {
     (
@@ -51,17 +65,45 @@ fn arb_settings() -> impl Strategy<Value = AppSettings> {
         1u32..20,                // retry_count
         arb_shortcut(),          // shortcut_capture
         arb_locale(),            // locale
+        any::<bool>(),           // structured_analysis
+        any::<bool>(),           // clipboard_watch_enabled
+        prop_oneof![
+            Just(ClipboardTrigger::AnyCopy),
+            Just(ClipboardTrigger::WithModifier),
+            Just(ClipboardTrigger::DoubleCopy),
+        ],                       // clipboard_watch_trigger
+        0u32..5_000,             // clipboard_watch_debounce_ms
+        prop::collection::vec("[A-Za-z0-9 .]{1,20}", 0..4), // clipboard_watch_excluded_apps
+        (
+            prop_oneof![Just(CaptureMode::Ocr), Just(CaptureMode::VisionModel)], // capture_mode
+            prop_oneof![
+                Just(OcrEngineKind::Auto),
+                Just(OcrEngineKind::AppleVision),
+                Just(OcrEngineKind::Tesseract),
+            ],                   // ocr_engine
+            arb_ocr_preprocess(), // ocr_preprocess
+        ),
     )
-        .prop_map(|(ai, cache_mb, clean_days, retry, shortcut, locale)| {
-            AppSettings {
-                ai_completion_enabled: ai,
-                cache_max_size_mb: cache_mb,
-                cache_auto_clean_days: clean_days,
-                retry_count: retry,
-                shortcut_capture: shortcut,
-                locale,
-            }
-        })
+        .prop_map(
+            |(ai, cache_mb, clean_days, retry, shortcut, locale, structured, watch, trigger, debounce, excluded, (mode, engine, preprocess))| {
+                AppSettings {
+                    ai_completion_enabled: ai,
+                    cache_max_size_mb: cache_mb,
+                    cache_auto_clean_days: clean_days,
+                    retry_count: retry,
+                    shortcut_capture: shortcut,
+                    locale,
+                    structured_analysis: structured,
+                    clipboard_watch_enabled: watch,
+                    clipboard_watch_trigger: trigger,
+                    clipboard_watch_debounce_ms: debounce,
+                    clipboard_watch_excluded_apps: excluded,
+                    capture_mode: mode,
+                    ocr_engine: engine,
+                    ocr_preprocess: preprocess,
+                }
+            },
+        )
 }
 
 proptest! {
@@ -82,6 +124,10 @@ proptest! {
         prop_assert_eq!(loaded.retry_count, settings.retry_count);
         prop_assert_eq!(&loaded.shortcut_capture, &settings.shortcut_capture);
         prop_assert_eq!(&loaded.locale, &settings.locale);
+        prop_assert_eq!(loaded.structured_analysis, settings.structured_analysis);
+        prop_assert_eq!(loaded.capture_mode, settings.capture_mode);
+        prop_assert_eq!(loaded.ocr_engine, settings.ocr_engine);
+        prop_assert_eq!(loaded.ocr_preprocess, settings.ocr_preprocess);
     }
 
     /// Switching locale and saving should immediately reflect in the next load.
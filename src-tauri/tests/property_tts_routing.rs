@@ -4,10 +4,14 @@
 // the TTS client should route requests to the service address configured
 // for that language, not to any other language's service address.
 //
+// Mixed-language segments are split into language spans, each routed to
+// its own service.
+//
 // Validates: Requirement 3.7
 
 use proptest::prelude::*;
 use veya_lib::api_config::ApiProvider;
+use veya_lib::cast_engine::{tts_pieces, PodcastMode};
 use veya_lib::retry::RetryPolicy;
 use veya_lib::tts_client::{TtsClient, TtsConfig};
 
@@ -28,6 +32,7 @@ fn lang_code_strategy() -> impl Strategy<Value = String> {
 /// Build a TtsConfig for a given language with a unique base_url.
 fn make_config(lang: &str, index: usize) -> TtsConfig {
     TtsConfig {
+        config_id: format!("tts-{}-{}", lang, index),
         provider: ApiProvider::Openai,
         base_url: format!("https://tts-{}-{}.example.com", lang, index),
         model_name: "tts-1".to_string(),
@@ -123,4 +128,38 @@ proptest! {
         let expected_url = "https://tts-en-1.example.com".to_string();
         prop_assert_eq!(routed_url, expected_url);
     }
+
+    /// A bilingual segment mixing Chinese with English terms is synthesized
+    /// span by span: pieces cover the segment, alternate between the zh and
+    /// en services, and each piece is voiced in its own language.
+    #[test]
+    fn mixed_segments_route_each_span_to_its_language(
+        words in prop::collection::vec((
+            prop::sample::select(vec!["这个", "我们的", "今天的", "怎么用"]),
+            prop::sample::select(vec!["API", "rate limit", "Docker", "pull request"]),
+        ), 1..5),
+    ) {
+        let segment: String = words.iter().map(|(zh, en)| format!("{zh} {en} ")).collect();
+        let segment = segment.trim_end();
+        let client = TtsClient::new(vec![make_config("en", 1), make_config("zh", 2)], RetryPolicy::new(0, 100, 1000));
+
+        let pieces = tts_pieces(&client, segment, &PodcastMode::Bilingual, "en", "zh");
+        prop_assert_eq!(pieces.iter().map(|(text, _)| text.as_str()).collect::<String>(), segment);
+        prop_assert_eq!(pieces.len(), words.len() * 2);
+        for (i, (text, language)) in pieces.iter().enumerate() {
+            let expected = if i % 2 == 0 { "zh" } else { "en" };
+            prop_assert_eq!(language.as_str(), expected, "piece {:?}", text);
+        }
+
+        let immersive = tts_pieces(&client, segment, &PodcastMode::Immersive, "en", "zh");
+        prop_assert_eq!(immersive, vec![(segment.to_string(), "zh".to_string())]);
+    }
+}
+
+/// Prefix matching stops at subtag boundaries: "zha" (Zhuang) is not "zh".
+#[test]
+fn longer_code_does_not_match_shorter_prefix() {
+    let client = TtsClient::new(vec![make_config("en", 1), make_config("zh", 2)], RetryPolicy::new(0, 100, 1000));
+    assert_eq!(client.route_url("zha").unwrap(), "https://tts-en-1.example.com");
+    assert_eq!(client.route_url("zh_TW").unwrap(), "https://tts-zh-2.example.com");
 }
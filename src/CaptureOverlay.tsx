@@ -118,13 +118,17 @@ function CaptureOverlay() {
       return;
     }
 
-    // Account for device pixel ratio for the actual capture region
-    const dpr = window.devicePixelRatio || 1;
+    // The region is in CSS pixels; the screenshot may be larger on HiDPI
+    // displays, so send its pixel-per-point ratio along for the crop.
+    const scaleFactor = imageRef.current
+      ? imageRef.current.naturalWidth / window.innerWidth
+      : window.devicePixelRatio || 1;
     const region = {
-      x: x * dpr,
-      y: y * dpr,
-      width: w * dpr,
-      height: h * dpr,
+      x,
+      y,
+      width: w,
+      height: h,
+      scale_factor: scaleFactor,
     };
 
     try {
@@ -7,19 +7,50 @@ import { useAppStore } from "../store";
 import StreamContent from "./StreamContent";
 import ActionBar from "./ActionBar";
 import AudioPlayer from "./AudioPlayer";
-import type { StreamContent as StreamContentType } from "../store";
+import type { StreamContent as StreamContentType, PresetSection } from "../store";
 
 interface TextInsightChunk {
-  type: "start" | "delta" | "done" | "error";
+  type: "start" | "section_start" | "delta" | "section_end" | "retrying" | "fallback" | "done" | "error" | "cancelled";
+  request_id: string;
   section?: keyof StreamContentType["sections"];
   content?: string;
   language?: string;
+  /** Detected languages, most likely first (on `start`). */
+  language_candidates?: { language: string; confidence: number }[];
+  /** Sections of the analysis preset (on `start`). */
+  sections?: PresetSection[];
+  /** The result is replayed from the analysis cache (on `start`). */
+  cached?: boolean;
+  model?: string;
+  /** Query record the backend saved the parsed result as (on `done`). */
+  record_id?: string;
+}
+
+/** Fractions of the capture region, from its top-left corner. */
+interface OcrBoundingBox {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+interface OcrWord {
+  text: string;
+  bbox: OcrBoundingBox;
+  /** 0 to 1. */
+  confidence: number;
+}
+
+interface OcrLine extends OcrWord {
+  words: OcrWord[];
 }
 
 interface VisionCaptureChunk {
   type: "ocr_result" | "ai_completion" | "analysis_delta" | "done" | "error";
   content?: string;
   is_ai_inferred?: boolean;
+  /** Lines and words with boxes and confidence (on `ocr_result` from an OCR engine). */
+  ocr?: { lines: OcrLine[] };
 }
 
 interface CastEngineProgress {
@@ -88,13 +119,20 @@ export default function FloatingWindow() {
   const clearError = useAppStore((s) => s.clearError);
   const setPodcastProgress = useAppStore((s) => s.setPodcastProgress);
 
-  // Track detected language from text-insight start events
-  const detectedLanguageRef = useRef<string | undefined>(undefined);
+  // Request ID of the text analysis on screen; chunks of others are ignored
+  const activeRequestRef = useRef<string | null>(null);
 
   // Handle window blur → auto-hide when not pinned
   const handleBlur = useCallback(async () => {
     if (!pinned) {
       hideWindow();
+      // Nobody is watching the stream any more
+      const requestId = activeRequestRef.current;
+      if (requestId && useAppStore.getState().floatingWindow.currentContent?.isStreaming) {
+        invoke("cancel_analysis", { requestId }).catch((e) =>
+          console.error("cancel_analysis failed:", e),
+        );
+      }
       try {
         await invoke("hide_floating_window");
       } catch (e) {
@@ -118,31 +156,50 @@ export default function FloatingWindow() {
     const unlisten = listen<TextInsightChunk>(
       "veya://text-insight/stream-chunk",
       ({ payload }) => {
+        // A newer analysis supersedes the one on screen; drop stragglers
+        if (payload.type === "start") {
+          activeRequestRef.current = payload.request_id;
+        } else if (payload.request_id !== activeRequestRef.current) {
+          return;
+        }
         switch (payload.type) {
           case "start":
             clearContent();
             clearError();
-            detectedLanguageRef.current = payload.language;
-            updateContent({ source: "text_insight", isStreaming: true, sections: {} });
+            updateContent({
+              source: "text_insight",
+              isStreaming: true,
+              sections: {},
+              presetSections: payload.sections,
+              cached: payload.cached,
+            });
             showWindow();
             break;
           case "delta":
             if (payload.section && payload.content) {
-              setStreamingSection(payload.section, payload.content);
+              const current = useAppStore.getState().floatingWindow.currentContent;
+              const previous = current?.sections[payload.section] ?? "";
+              setStreamingSection(payload.section, previous + payload.content);
             }
             break;
+          case "retrying":
+          case "fallback":
+            // The backend restarts the stream; drop partial output.
+            updateContent({ sections: {} });
+            break;
           case "done":
+            // The backend saves the parsed result as a learning record.
             updateContent({ isStreaming: false });
-            // Auto-save learning record
-            {
-              const snap = useAppStore.getState().floatingWindow.currentContent;
-              if (snap) saveQueryRecord(snap, detectedLanguageRef.current);
-            }
             break;
           case "error":
             updateContent({ isStreaming: false });
             setError(resolveErrorMessage(payload.content, t));
             break;
+          case "cancelled":
+            // Keep what has streamed so far
+            updateContent({ isStreaming: false });
+            activeRequestRef.current = null;
+            break;
         }
       },
     );
@@ -162,13 +219,18 @@ export default function FloatingWindow() {
         switch (payload.type) {
           case "ocr_result":
             accumulatedText = payload.content ?? "";
-            aiRanges = [];
+            // A vision model's transcription is inferred as a whole.
+            aiRanges =
+              payload.is_ai_inferred && accumulatedText
+                ? [{ start: 0, end: accumulatedText.length }]
+                : [];
             clearContent();
             clearError();
             updateContent({
               source: "vision_capture",
               isStreaming: true,
               sections: { original: accumulatedText },
+              aiInferredRanges: [...aiRanges],
             });
             showWindow();
             break;
@@ -1,7 +1,24 @@
 import { useEffect, useState, useCallback, useRef } from "react";
 import { useTranslation } from "react-i18next";
 import { invoke } from "@tauri-apps/api/core";
-import { useAppStore, type AppSettings } from "../store";
+import {
+  useAppStore,
+  type AppSettings,
+  type CaptureMode,
+  type CefrLevel,
+  type ClipboardTrigger,
+  type LearnerProfile,
+  type OcrEngineKind,
+  type OcrPreprocess,
+} from "../store";
+
+const PROFILE_LANGUAGES = ["zh", "en", "ja", "ko", "fr", "de", "es", "pt", "ru", "it"];
+const CEFR_LEVELS: CefrLevel[] = ["A1", "A2", "B1", "B2", "C1", "C2"];
+const CLIPBOARD_TRIGGERS: ClipboardTrigger[] = ["double_copy", "with_modifier", "any_copy"];
+const CAPTURE_MODES: CaptureMode[] = ["ocr", "vision_model"];
+const OCR_ENGINES: OcrEngineKind[] = ["auto", "apple_vision", "tesseract"];
+const OCR_PREPROCESS_PASSES = ["grayscale", "binarize", "deskew", "invertDark"] as const;
+const OCR_UPSCALE_FACTORS = [1, 2, 3, 4];
 
 interface SettingsPageProps {
   onNavigateApiConfig: () => void;
@@ -44,6 +61,38 @@ export default function SettingsPage({ onNavigateApiConfig }: SettingsPageProps)
     }
   };
 
+  // --- Learner profile ---
+  const [profile, setProfile] = useState<LearnerProfile | null>(null);
+
+  useEffect(() => {
+    invoke<LearnerProfile>("get_learner_profile")
+      .then(setProfile)
+      .catch((e) => console.error("get_learner_profile failed:", e));
+  }, []);
+
+  const savePreprocess = (patch: Partial<OcrPreprocess>) =>
+    save({ ocrPreprocess: { ...settings.ocrPreprocess, ...patch } });
+
+  const saveProfile = async (next: LearnerProfile) => {
+    setProfile(next);
+    try {
+      setProfile(await invoke<LearnerProfile>("update_learner_profile", { profile: next }));
+    } catch (e) {
+      console.error("update_learner_profile failed:", e);
+    }
+  };
+
+  const addTargetLanguage = () => {
+    if (!profile) return;
+    const used = new Set(profile.target_languages.map((t) => t.language));
+    const language = PROFILE_LANGUAGES.find((l) => l !== profile.native_language && !used.has(l));
+    if (!language) return;
+    saveProfile({
+      ...profile,
+      target_languages: [...profile.target_languages, { language, level: "A1" }],
+    });
+  };
+
   const handleLocaleChange = async (locale: string) => {
     await save({ locale });
     i18n.changeLanguage(locale);
@@ -148,6 +197,147 @@ export default function SettingsPage({ onNavigateApiConfig }: SettingsPageProps)
       </label>
       <p className="settings-hint">{t("settings.aiCompletionDesc")}</p>
 
+      {/* Structured (JSON-mode) analysis toggle */}
+      <label className="settings-row">
+        <span className="settings-label">{t("settings.structuredAnalysis")}</span>
+        <input
+          type="checkbox"
+          checked={settings.structuredAnalysis}
+          onChange={(e) => save({ structuredAnalysis: e.target.checked })}
+          aria-label={t("settings.structuredAnalysis")}
+        />
+      </label>
+      <p className="settings-hint">{t("settings.structuredAnalysisDesc")}</p>
+
+      {/* Clipboard-watch trigger mode */}
+      <label className="settings-row">
+        <span className="settings-label">{t("settings.clipboardWatch")}</span>
+        <input
+          type="checkbox"
+          checked={settings.clipboardWatchEnabled}
+          onChange={(e) => save({ clipboardWatchEnabled: e.target.checked })}
+          aria-label={t("settings.clipboardWatch")}
+        />
+      </label>
+      <p className="settings-hint">{t("settings.clipboardWatchDesc")}</p>
+
+      {settings.clipboardWatchEnabled && (
+        <>
+          <label className="settings-row">
+            <span className="settings-label">{t("settings.clipboardTrigger")}</span>
+            <select
+              value={settings.clipboardWatchTrigger}
+              onChange={(e) => save({ clipboardWatchTrigger: e.target.value as ClipboardTrigger })}
+              className="settings-select"
+            >
+              {CLIPBOARD_TRIGGERS.map((trigger) => (
+                <option key={trigger} value={trigger}>
+                  {t(`settings.clipboardTriggers.${trigger}`)}
+                </option>
+              ))}
+            </select>
+          </label>
+
+          <label className="settings-row">
+            <span className="settings-label">{t("settings.clipboardDebounce")}</span>
+            <input
+              type="number"
+              min={0}
+              max={5000}
+              step={50}
+              value={settings.clipboardWatchDebounceMs}
+              onChange={(e) => save({ clipboardWatchDebounceMs: Math.max(0, Number(e.target.value) || 0) })}
+              className="settings-input-number"
+            />
+          </label>
+
+          <label className="settings-row">
+            <span className="settings-label">{t("settings.clipboardExcludedApps")}</span>
+            <textarea
+              value={settings.clipboardWatchExcludedApps.join("\n")}
+              onChange={(e) => setSettings({ ...settings, clipboardWatchExcludedApps: e.target.value.split("\n") })}
+              onBlur={(e) =>
+                save({
+                  clipboardWatchExcludedApps: e.target.value
+                    .split("\n")
+                    .map((app) => app.trim())
+                    .filter(Boolean),
+                })
+              }
+              rows={4}
+              className="settings-textarea"
+              aria-label={t("settings.clipboardExcludedApps")}
+            />
+          </label>
+          <p className="settings-hint">{t("settings.clipboardExcludedAppsDesc")}</p>
+        </>
+      )}
+
+      {/* How captured regions are read */}
+      <label className="settings-row">
+        <span className="settings-label">{t("settings.captureMode")}</span>
+        <select
+          value={settings.captureMode}
+          onChange={(e) => save({ captureMode: e.target.value as CaptureMode })}
+          className="settings-select"
+        >
+          {CAPTURE_MODES.map((mode) => (
+            <option key={mode} value={mode}>
+              {t(`settings.captureModes.${mode}`)}
+            </option>
+          ))}
+        </select>
+      </label>
+      <p className="settings-hint">{t("settings.captureModeDesc")}</p>
+
+      {/* OCR engine */}
+      <label className="settings-row">
+        <span className="settings-label">{t("settings.ocrEngine")}</span>
+        <select
+          value={settings.ocrEngine}
+          onChange={(e) => save({ ocrEngine: e.target.value as OcrEngineKind })}
+          className="settings-select"
+        >
+          {OCR_ENGINES.map((engine) => (
+            <option key={engine} value={engine}>
+              {t(`settings.ocrEngines.${engine}`)}
+            </option>
+          ))}
+        </select>
+      </label>
+      <p className="settings-hint">{t("settings.ocrEngineDesc")}</p>
+
+      {/* OCR preprocessing for captured regions */}
+      <div className="settings-row">
+        <span className="settings-label">{t("settings.ocrPreprocess")}</span>
+      </div>
+      {OCR_PREPROCESS_PASSES.map((pass) => (
+        <label key={pass} className="settings-row settings-subrow">
+          <span className="settings-label">{t(`settings.ocrPreprocessPasses.${pass}`)}</span>
+          <input
+            type="checkbox"
+            checked={settings.ocrPreprocess[pass]}
+            onChange={(e) => savePreprocess({ [pass]: e.target.checked })}
+            aria-label={t(`settings.ocrPreprocessPasses.${pass}`)}
+          />
+        </label>
+      ))}
+      <label className="settings-row settings-subrow">
+        <span className="settings-label">{t("settings.ocrUpscale")}</span>
+        <select
+          value={settings.ocrPreprocess.upscale}
+          onChange={(e) => savePreprocess({ upscale: Number(e.target.value) })}
+          className="settings-select"
+        >
+          {OCR_UPSCALE_FACTORS.map((factor) => (
+            <option key={factor} value={factor}>
+              {factor === 1 ? t("settings.ocrUpscaleOff") : `${factor}×`}
+            </option>
+          ))}
+        </select>
+      </label>
+      <p className="settings-hint">{t("settings.ocrPreprocessDesc")}</p>
+
       {/* Cache settings */}
       <label className="settings-row">
         <span className="settings-label">{t("settings.cacheMaxSize")}</span>
@@ -221,6 +411,84 @@ export default function SettingsPage({ onNavigateApiConfig }: SettingsPageProps)
         </select>
       </label>
 
+      {/* Learner profile */}
+      {profile && (
+        <>
+          <label className="settings-row">
+            <span className="settings-label">{t("settings.nativeLanguage")}</span>
+            <select
+              value={profile.native_language}
+              onChange={(e) => saveProfile({ ...profile, native_language: e.target.value })}
+              className="settings-select"
+              aria-label={t("settings.nativeLanguage")}
+            >
+              {PROFILE_LANGUAGES.map((l) => (
+                <option key={l} value={l}>{t(`languages.${l}`, l)}</option>
+              ))}
+            </select>
+          </label>
+          {profile.target_languages.map((target, index) => (
+            <div className="settings-row" key={target.language}>
+              <span className="settings-label">{t("settings.targetLanguage")}</span>
+              <select
+                value={target.language}
+                onChange={(e) =>
+                  saveProfile({
+                    ...profile,
+                    target_languages: profile.target_languages.map((t, i) =>
+                      i === index ? { ...t, language: e.target.value } : t,
+                    ),
+                  })
+                }
+                className="settings-select"
+                aria-label={t("settings.targetLanguage")}
+              >
+                {PROFILE_LANGUAGES.filter(
+                  (l) => l === target.language || !profile.target_languages.some((t) => t.language === l),
+                ).map((l) => (
+                  <option key={l} value={l}>{t(`languages.${l}`, l)}</option>
+                ))}
+              </select>
+              <select
+                value={target.level}
+                onChange={(e) =>
+                  saveProfile({
+                    ...profile,
+                    target_languages: profile.target_languages.map((t, i) =>
+                      i === index ? { ...t, level: e.target.value as CefrLevel } : t,
+                    ),
+                  })
+                }
+                className="settings-select"
+                aria-label={t("settings.cefrLevel")}
+              >
+                {CEFR_LEVELS.map((level) => (
+                  <option key={level} value={level}>{level}</option>
+                ))}
+              </select>
+              <button
+                className="settings-btn"
+                onClick={() =>
+                  saveProfile({
+                    ...profile,
+                    target_languages: profile.target_languages.filter((_, i) => i !== index),
+                  })
+                }
+              >
+                {t("settings.removeTargetLanguage")}
+              </button>
+            </div>
+          ))}
+          <div className="settings-row">
+            <span className="settings-label">{t("settings.targetLanguages")}</span>
+            <button className="settings-btn" onClick={addTargetLanguage}>
+              {t("settings.addTargetLanguage")}
+            </button>
+          </div>
+          <p className="settings-hint">{t("settings.learnerProfileDesc")}</p>
+        </>
+      )}
+
       {/* API Config entry */}
       <div className="settings-row">
         <span className="settings-label">{t("settings.apiConfig")}</span>
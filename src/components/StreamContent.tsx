@@ -26,14 +26,22 @@ const i18nKeyMap: Record<string, string> = {
 export default function StreamContent({ content }: StreamContentProps) {
   const { t } = useTranslation();
 
+  // Standard sections use translated labels; custom preset sections their own title.
+  const sections = content.presetSections
+    ? content.presetSections.map(({ key, title }) => ({
+        key,
+        label: i18nKeyMap[key] ? t(i18nKeyMap[key]) : title,
+      }))
+    : sectionKeys.map((key) => ({ key, label: t(i18nKeyMap[key]) }));
+
   return (
     <div className="stream-content">
-      {sectionKeys.map((key) => {
+      {sections.map(({ key, label }) => {
         const value = content.sections[key];
         if (!value && !content.isStreaming) return null;
         return (
           <div key={key} className="stream-section">
-            <h4 className="stream-section-label">{t(i18nKeyMap[key])}</h4>
+            <h4 className="stream-section-label">{label}</h4>
             <div className="stream-section-body">
               {content.source === "vision_capture" &&
                 content.aiInferredRanges &&
@@ -53,6 +61,9 @@ export default function StreamContent({ content }: StreamContentProps) {
           </div>
         );
       })}
+      {content.cached && (
+        <p className="stream-cached-note">{t("textInsight.cached")}</p>
+      )}
       {content.isStreaming && (
         <p className="streaming-indicator" role="status">
           {t("textInsight.analyzing")}
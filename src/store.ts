@@ -1,5 +1,20 @@
 import { create } from "zustand";
 
+/** A section declared by an analysis preset. */
+export interface PresetSection {
+  key: string;
+  title: string;
+  instruction: string;
+}
+
+export interface AnalysisPreset {
+  id: string;
+  name: string;
+  sections: PresetSection[];
+  builtin: boolean;
+  updated_at: string | null;
+}
+
 export interface StreamContent {
   source: "text_insight" | "vision_capture";
   sections: {
@@ -9,7 +24,13 @@ export interface StreamContent {
     translation?: string;
     colloquial?: string;
     simplified?: string;
+    /** Sections declared by custom analysis presets. */
+    [key: string]: string | undefined;
   };
+  /** Sections of the analysis preset in display order; defaults to the standard six. */
+  presetSections?: PresetSection[];
+  /** The analysis was replayed from the response cache. */
+  cached?: boolean;
   aiInferredRanges?: Array<{ start: number; end: number }>;
   isStreaming: boolean;
 }
@@ -29,6 +50,35 @@ export interface AppSettings {
   retryCount: number;
   shortcutCapture: string;
   locale: string;
+  structuredAnalysis: boolean;
+  clipboardWatchEnabled: boolean;
+  clipboardWatchTrigger: ClipboardTrigger;
+  clipboardWatchDebounceMs: number;
+  clipboardWatchExcludedApps: string[];
+  captureMode: CaptureMode;
+  ocrEngine: OcrEngineKind;
+  ocrPreprocess: OcrPreprocess;
+}
+
+export type ClipboardTrigger = "any_copy" | "with_modifier" | "double_copy";
+
+export type CaptureMode = "ocr" | "vision_model";
+
+export type OcrEngineKind = "auto" | "apple_vision" | "tesseract";
+
+export interface OcrPreprocess {
+  grayscale: boolean;
+  upscale: number;
+  binarize: boolean;
+  deskew: boolean;
+  invertDark: boolean;
+}
+
+export type CefrLevel = "A1" | "A2" | "B1" | "B2" | "C1" | "C2";
+
+export interface LearnerProfile {
+  native_language: string;
+  target_languages: { language: string; level: CefrLevel }[];
 }
 
 export interface FloatingWindowState {
@@ -79,6 +129,20 @@ const defaultSettings: AppSettings = {
   retryCount: 3,
   shortcutCapture: "CommandOrControl+Shift+S",
   locale: "zh-CN",
+  structuredAnalysis: false,
+  clipboardWatchEnabled: false,
+  clipboardWatchTrigger: "double_copy",
+  clipboardWatchDebounceMs: 300,
+  clipboardWatchExcludedApps: ["1Password", "Bitwarden", "KeePassXC", "Keychain Access"],
+  captureMode: "ocr",
+  ocrEngine: "auto",
+  ocrPreprocess: {
+    grayscale: false,
+    upscale: 1,
+    binarize: false,
+    deskew: false,
+    invertDark: false,
+  },
 };
 
 export const useAppStore = create<AppState>((set) => ({